@@ -34,7 +34,7 @@ fn bench_reissue_1_to_100(c: &mut Criterion) {
                 .unwrap(),
             starting_dbc.amount_secrets_bearer().unwrap(),
         )
-        .add_outputs_by_amount((0..N_OUTPUTS).into_iter().map(|_| {
+        .add_outputs_by_amount((0..N_OUTPUTS).map(|_| {
             let owner_once =
                 OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
             (Token::from_nano(1), owner_once)
@@ -60,7 +60,7 @@ fn bench_reissue_1_to_100(c: &mut Criterion) {
 
         if let Ok(report) = guard.report().build() {
             let file =
-                std::fs::File::create(&format!("reissue_split_1_to_{}.svg", N_OUTPUTS)).unwrap();
+                std::fs::File::create(format!("reissue_split_1_to_{}.svg", N_OUTPUTS)).unwrap();
             report.flamegraph(file).unwrap();
         };
     });
@@ -82,7 +82,7 @@ fn bench_reissue_100_to_1(c: &mut Criterion) {
                 .unwrap(),
             starting_dbc.amount_secrets_bearer().unwrap(),
         )
-        .add_outputs_by_amount((0..N_OUTPUTS).into_iter().map(|_| {
+        .add_outputs_by_amount((0..N_OUTPUTS).map(|_| {
             let owner_once =
                 OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
             (Token::from_nano(1), owner_once)
@@ -130,12 +130,13 @@ fn bench_reissue_100_to_1(c: &mut Criterion) {
 
         if let Ok(report) = guard.report().build() {
             let file =
-                std::fs::File::create(&format!("reissue_merge_{}_to_1.svg", N_OUTPUTS)).unwrap();
+                std::fs::File::create(format!("reissue_merge_{}_to_1.svg", N_OUTPUTS)).unwrap();
             report.flamegraph(file).unwrap();
         };
     });
 }
 
+#[allow(clippy::result_large_err)]
 fn generate_dbc_of_value(
     amount: Token,
     rng: &mut (impl RngCore + CryptoRng),
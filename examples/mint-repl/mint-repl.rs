@@ -53,7 +53,7 @@ impl MintInfo {
     // returns the first spentbook node.
     fn spentbook(&self) -> Result<&mock::SpentBookNode> {
         self.spentbook_nodes
-            .get(0)
+            .first()
             .ok_or_else(|| anyhow!("Spentbook not yet created"))
     }
 }
@@ -246,7 +246,7 @@ fn newkey() -> Result<()> {
         println!(
             "  {}. {}",
             i,
-            encode(&sks.public_keys().public_key_share(i).to_bytes())
+            encode(sks.public_keys().public_key_share(i).to_bytes())
         );
     }
 
@@ -337,7 +337,7 @@ fn print_mintinfo_human(mintinfo: &MintInfo) -> Result<()> {
             "    {}. {}",
             i,
             encode(
-                &mintinfo
+                mintinfo
                     .secret_key_set
                     .public_keys()
                     .public_key_share(i)
@@ -359,7 +359,7 @@ fn print_mintinfo_human(mintinfo: &MintInfo) -> Result<()> {
     for (i, spentbook) in mintinfo.spentbook_nodes.iter().enumerate() {
         println!("\n-- SpentBook Node {} --\n", i);
         for (key_image, _tx) in spentbook.iter() {
-            println!("  {}", encode(&key_image.to_bytes()));
+            println!("  {}", encode(key_image.to_bytes()));
         }
     }
 
@@ -370,7 +370,7 @@ fn print_mintinfo_human(mintinfo: &MintInfo) -> Result<()> {
 
 /// displays Dbc in human readable form
 fn print_dbc_human(dbc: &Dbc, outputs: bool, secret_key_base: Option<SecretKey>) -> Result<()> {
-    println!("hash: {}\n", encode(&dbc.hash()));
+    println!("hash: {}\n", encode(dbc.hash()));
 
     let result = match secret_key_base {
         // use base SecretKey from input param if available.
@@ -462,16 +462,12 @@ fn decode_input() -> Result<()> {
                 pks.threshold(),
                 pks.threshold() + 1
             );
-            println!("  public_key: {}", encode(&pks.public_key().to_bytes()));
+            println!("  public_key: {}", encode(pks.public_key().to_bytes()));
             // temporary: the 2nd line matches ian coleman's bls tool output.  but why not the first?
             //            println!("PublicKeyShare[0]: {}", to_be_hex(&pks.public_key_share(0))? );
             println!("\n  PublicKeyShares:");
             for i in 0..pks.threshold() + 1 {
-                println!(
-                    "    {} : {}",
-                    i,
-                    encode(&pks.public_key_share(i).to_bytes())
-                );
+                println!("    {} : {}", i, encode(pks.public_key_share(i).to_bytes()));
             }
             println!("-- End PublicKeySet --\n");
         }
@@ -534,7 +530,7 @@ fn verify(mintinfo: &MintInfo) -> Result<()> {
 
     let secret_key = match dbc.owner_base() {
         Owner::SecretKey(sk) => sk.inner().clone(),
-        Owner::PublicKey(_pk) => {
+        _ => {
             let sk_input = readline_prompt_nl("\nSecret Key, or '[c]ancel': ")?;
             let sk: SecretKey = if dbc_input == "c" {
                 println!("\nVerify cancelled\n");
@@ -579,7 +575,7 @@ fn prepare_tx(mintinfo: &MintInfo) -> Result<DbcBuilder> {
 
         let base_secret_key = match dbc.owner_base() {
             Owner::SecretKey(sk) => sk.inner().clone(),
-            Owner::PublicKey(_) => {
+            _ => {
                 println!("We need a SecretKey in order to decrypt the input amount.");
                 loop {
                     let key = readline_prompt_nl("\nSecretKey: ")?;
@@ -603,8 +599,8 @@ fn prepare_tx(mintinfo: &MintInfo) -> Result<DbcBuilder> {
     // Get outputs from user
     // note, we upcast to i128 to allow negative value.
     // This permits unbalanced inputs/outputs to reach sn_dbc layer for verification.
-    let inputs_amount_sum = tx_builder.inputs_amount_sum();
-    while let Some(remaining) = inputs_amount_sum.checked_sub(tx_builder.outputs_amount_sum()) {
+    let inputs_amount_sum = tx_builder.inputs_amount_sum()?;
+    while let Some(remaining) = inputs_amount_sum.checked_sub(tx_builder.outputs_amount_sum()?) {
         println!();
         println!("------------");
         println!("Output #{}", i);
@@ -626,7 +622,7 @@ fn prepare_tx(mintinfo: &MintInfo) -> Result<DbcBuilder> {
                 "\nThe amount should normally be in the range 1..{}. Change it? [y/n]: ",
                 remaining
             ))?;
-            if answer.to_ascii_lowercase() != "n" {
+            if !answer.eq_ignore_ascii_case("n") {
                 continue;
             }
         }
@@ -668,7 +664,7 @@ fn prepare_tx(mintinfo: &MintInfo) -> Result<DbcBuilder> {
 
     println!("\n\nPreparing RingCtTransaction...\n\n");
 
-    let dbc_builder = tx_builder.build(&mut rng::thread_rng())?;
+    let dbc_builder = tx_builder.build(rng::thread_rng())?;
 
     Ok(dbc_builder)
 }
@@ -753,14 +749,14 @@ fn reissue_auto_cli(mintinfo: &mut MintInfo) -> Result<()> {
             tx_builder = tx_builder.add_input_dbc(dbc, &base_sk)?;
         }
 
-        let inputs_sum = tx_builder.inputs_amount_sum();
+        let inputs_sum = tx_builder.inputs_amount_sum()?;
 
-        while tx_builder.outputs_amount_sum() < inputs_sum || tx_builder.outputs().is_empty() {
+        while tx_builder.outputs_amount_sum()? < inputs_sum || tx_builder.outputs().is_empty() {
             let amount = if tx_builder.outputs().len() >= max_outputs - 1 {
-                inputs_sum.as_nano() - tx_builder.outputs_amount_sum().as_nano()
+                inputs_sum.as_nano() - tx_builder.outputs_amount_sum()?.as_nano()
             } else {
                 // randomize output amount
-                let diff = inputs_sum.as_nano() - tx_builder.outputs_amount_sum().as_nano();
+                let diff = inputs_sum.as_nano() - tx_builder.outputs_amount_sum()?.as_nano();
 
                 let is_last = rng.gen_range(0..max_outputs + 1) == max_outputs;
                 if is_last {
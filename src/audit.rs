@@ -0,0 +1,215 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    AmountSecrets, Dbc, Error, PublicKey, Result, SpentProof, SpentProofKeyVerifier, Token,
+    TransactionVerifier,
+};
+use bls_ringct::{group::Curve, ringct::RingCtTransaction, RevealedCommitment};
+use blsttc::{Ciphertext, SecretKey};
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A single Dbc disclosed in an AuditBundle.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    output_public_key: PublicKey,
+    amount_secrets_cipher: Ciphertext,
+    transaction: RingCtTransaction,
+    spent_proofs: BTreeSet<SpentProof>,
+}
+
+impl AuditEntry {
+    /// returns the one-time-use PublicKey of the disclosed Dbc
+    pub fn output_public_key(&self) -> PublicKey {
+        self.output_public_key
+    }
+
+    /// returns the transaction that created the disclosed Dbc
+    pub fn transaction(&self) -> &RingCtTransaction {
+        &self.transaction
+    }
+}
+
+/// Discloses the amounts of selected Dbcs to an auditor.
+///
+/// The owner decrypts the AmountSecrets of each Dbc and re-encrypts them
+/// to the auditor's PublicKey.  No spend key, derivation index or owner
+/// base key is disclosed, so the auditor learns the amounts but can
+/// neither spend the Dbcs nor link them to the owner's other Dbcs.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct AuditBundle {
+    auditor: PublicKey,
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditBundle {
+    /// create an AuditBundle for the given Dbcs, all of which must be owned
+    /// by the given base SecretKey.
+    pub fn new<'a>(
+        dbcs: impl IntoIterator<Item = &'a Dbc>,
+        base_sk: &SecretKey,
+        auditor: PublicKey,
+    ) -> Result<Self> {
+        let entries = dbcs
+            .into_iter()
+            .map(|dbc| {
+                Ok(AuditEntry {
+                    output_public_key: dbc.owner_once(base_sk)?.public_key(),
+                    amount_secrets_cipher: dbc.amount_secrets(base_sk)?.encrypt(&auditor),
+                    transaction: dbc.transaction.clone(),
+                    spent_proofs: dbc.spent_proofs.clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { auditor, entries })
+    }
+
+    /// returns the PublicKey of the auditor the bundle is encrypted to
+    pub fn auditor(&self) -> PublicKey {
+        self.auditor
+    }
+
+    /// returns the disclosed entries
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Decrypts and verifies each entry, returning the one-time-use
+    /// PublicKey and amount of each disclosed Dbc, in bundle order.
+    ///
+    /// Each entry's transaction is verified along with its spent proofs,
+    /// and the decrypted amount and blinding factor must open the
+    /// commitment of the matching transaction output.
+    ///
+    /// see TransactionVerifier::verify() for a description of
+    /// verifier requirements.
+    pub fn verify<K: SpentProofKeyVerifier>(
+        &self,
+        auditor_sk: &SecretKey,
+        verifier: &K,
+    ) -> Result<Vec<(PublicKey, Token)>> {
+        if auditor_sk.public_key() != self.auditor {
+            return Err(Error::SecretKeyDoesNotMatchPublicKey);
+        }
+
+        self.entries
+            .iter()
+            .map(|entry| {
+                TransactionVerifier::verify(verifier, &entry.transaction, &entry.spent_proofs)?;
+
+                let output = entry
+                    .transaction
+                    .outputs
+                    .iter()
+                    .find(|o| entry.output_public_key.eq(o.public_key()))
+                    .ok_or(Error::OutputProofNotFound)?;
+
+                let amount_secrets =
+                    AmountSecrets::try_from((auditor_sk, &entry.amount_secrets_cipher))?;
+                let rc: RevealedCommitment = amount_secrets.clone().into();
+                if rc.commit(&Default::default()).to_affine() != output.commitment() {
+                    return Err(Error::AmountCommitmentsDoNotMatch);
+                }
+
+                Ok((entry.output_public_key, amount_secrets.amount()))
+            })
+            .collect()
+    }
+
+    /// Verifies the bundle (see ::verify()) and returns the sum of the
+    /// disclosed amounts.
+    pub fn verify_total<K: SpentProofKeyVerifier>(
+        &self,
+        auditor_sk: &SecretKey,
+        verifier: &K,
+    ) -> Result<Token> {
+        self.verify(auditor_sk, verifier)?
+            .into_iter()
+            .try_fold(Token::zero(), |sum, (_, amount)| {
+                sum.checked_add(amount).ok_or(Error::ExcessiveTokenValue)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::pay_from_genesis,
+        rand::{distributions::Standard, Rng},
+        Owner,
+    };
+
+    #[test]
+    fn auditor_sees_amounts_of_disclosed_dbcs() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let owner_sk: SecretKey = rng.sample(Standard);
+        let auditor_sk: SecretKey = rng.sample(Standard);
+
+        let (spentbook_node, owned) = pay_from_genesis(
+            &[100, 250, 7],
+            &Owner::from(owner_sk.public_key()),
+            &mut rng,
+        )?;
+
+        // disclose only two of the three Dbcs.
+        let disclosed: Vec<&Dbc> = owned
+            .iter()
+            .filter(|dbc| dbc.amount_secrets(&owner_sk).unwrap().amount() != Token::from_nano(7))
+            .collect();
+        let bundle = AuditBundle::new(disclosed, &owner_sk, auditor_sk.public_key())?;
+
+        let audited = bundle.verify(&auditor_sk, &spentbook_node.key_manager)?;
+        assert_eq!(audited.len(), 2);
+        assert_eq!(
+            bundle.verify_total(&auditor_sk, &spentbook_node.key_manager)?,
+            Token::from_nano(350)
+        );
+
+        // only the auditor can read the bundle.
+        assert_eq!(
+            bundle.verify(&owner_sk, &spentbook_node.key_manager).err(),
+            Some(Error::SecretKeyDoesNotMatchPublicKey)
+        );
+
+        // an opening that does not match the commitment is detected.
+        let mut fudged = bundle.clone();
+        fudged.entries[0].amount_secrets_cipher =
+            AmountSecrets::from((1_000_000, Default::default())).encrypt(&auditor_sk.public_key());
+        assert_eq!(
+            fudged
+                .verify(&auditor_sk, &spentbook_node.key_manager)
+                .err(),
+            Some(Error::AmountCommitmentsDoNotMatch)
+        );
+
+        // an entry that does not belong to its transaction is detected,
+        // even though the transaction itself is valid.
+        let mut fudged = bundle;
+        let (other_spentbook_node, other) =
+            pay_from_genesis(&[1], &Owner::from(owner_sk.public_key()), &mut rng)?;
+        fudged.entries[0].transaction = other[0].transaction.clone();
+        fudged.entries[0].spent_proofs = other[0].spent_proofs.clone();
+        fudged.entries.truncate(1);
+        assert_eq!(
+            fudged
+                .verify(&auditor_sk, &other_spentbook_node.key_manager)
+                .err(),
+            Some(Error::OutputProofNotFound)
+        );
+
+        Ok(())
+    }
+}
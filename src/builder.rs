@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use bls_ringct::{bls_bulletproofs::PedersenGens, group::Curve};
+use bls_ringct::{bls_bulletproofs::PedersenGens, group::Curve, ringct::OutputProof};
 pub use bls_ringct::{
     ringct::RingCtTransaction, DecoyInput, MlsagMaterial, Output, RevealedCommitment,
     RingCtMaterial, TrueInput,
@@ -18,15 +18,30 @@ use std::{
 };
 
 use crate::{
-    rand::{CryptoRng, RngCore},
-    AmountSecrets, Commitment, Dbc, DbcContent, Error, Hash, KeyImage, OwnerOnce, Result,
-    SpentProof, SpentProofKeyVerifier, SpentProofShare, Token, TransactionVerifier,
+    rand::{seq::SliceRandom, CryptoRng, RngCore},
+    AmountSecrets, Commitment, Dbc, DbcContent, DbcMetadata, DecoyCandidate, DecoyProvider,
+    DecoySelector, Denominations, Error, Fee, Hash, KeyImage, MultisigInput,
+    MultisigSigningSession, Owner, OwnerOnce, PaymentRequest, Result, SpentProof,
+    SpentProofKeyVerifier, SpentProofShare, Token, TransactionEstimate, TransactionVerifier,
+    UniformDecoySelector,
 };
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 pub type OutputOwnerMap = BTreeMap<PublicKey, OwnerOnce>;
+pub type OutputMetadataMap = BTreeMap<PublicKey, DbcMetadata>;
+
+// the most times ::build_with_decoy_provider() asks a DecoyProvider for decoys.
+const MAX_DECOY_FETCHES: usize = 10;
+
+// inputs with decoys, and the index of the fee output (if any), ready for signing.
+type PreparedMaterial = (
+    RingCtMaterial,
+    Vec<(MultisigInput, Vec<DecoyInput>)>,
+    OutputOwnerMap,
+    Option<usize>,
+);
 
 /// A builder to create a RingCt transaction from
 /// inputs and outputs.
@@ -34,22 +49,38 @@ pub type OutputOwnerMap = BTreeMap<PublicKey, OwnerOnce>;
 #[derive(Debug)]
 pub struct TransactionBuilder {
     true_inputs: Vec<TrueInput>,
+    multisig_inputs: Vec<MultisigInput>,
     ringct_material: RingCtMaterial,
     output_owner_map: OutputOwnerMap,
-    available_decoys: Vec<DecoyInput>,
+    output_metadata: OutputMetadataMap,
+    available_decoys: Vec<DecoyCandidate>,
     decoys_per_input: usize,
     require_all_decoys: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip, default = "TransactionBuilder::default_decoy_selector")
+    )]
+    decoy_selector: Box<dyn DecoySelector>,
+    change_owner: Option<OwnerOnce>,
+    fee: Token,
+    dust_threshold: Token,
 }
 
 impl Default for TransactionBuilder {
     fn default() -> Self {
         Self {
             true_inputs: Default::default(),
+            multisig_inputs: Default::default(),
             ringct_material: Default::default(),
             output_owner_map: Default::default(),
+            output_metadata: Default::default(),
             available_decoys: Default::default(),
             decoys_per_input: 10, // default to 10 decoys per input.
             require_all_decoys: true,
+            decoy_selector: Self::default_decoy_selector(),
+            change_owner: None,
+            fee: Token::zero(),
+            dust_threshold: Token::zero(),
         }
     }
 }
@@ -69,6 +100,45 @@ impl TransactionBuilder {
         self
     }
 
+    /// set decoy_selector option.
+    /// the strategy by which ::build() chooses decoys for each input from
+    /// the available decoys.  Defaults to UniformDecoySelector.
+    pub fn set_decoy_selector(mut self, decoy_selector: impl DecoySelector + 'static) -> Self {
+        self.decoy_selector = Box::new(decoy_selector);
+        self
+    }
+
+    /// set change_owner option.
+    /// if set, ::build() adds an output paying any remainder of
+    /// inputs_amount_sum() - outputs_amount_sum() - fee() to this owner.
+    /// No change output is added if the remainder is zero.
+    pub fn set_change_owner(mut self, change_owner: OwnerOnce) -> Self {
+        self.change_owner = Some(change_owner);
+        self
+    }
+
+    /// set fee option.
+    /// ::build() adds an output paying this amount as a publicly visible fee.
+    /// The inputs must cover outputs_amount_sum() plus the fee.
+    /// See Fee for details.
+    pub fn set_fee(mut self, fee: Token) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// get the fee amount
+    pub fn fee(&self) -> Token {
+        self.fee
+    }
+
+    /// set dust_threshold option.
+    /// ::validate() rejects any output of a smaller amount.
+    /// Outputs of zero amount are always rejected.
+    pub fn set_dust_threshold(mut self, dust_threshold: Token) -> Self {
+        self.dust_threshold = dust_threshold;
+        self
+    }
+
     /// add to pool of available decoys.
     ///
     /// It is best that the size of the pool is larger (even much larger)
@@ -77,14 +147,24 @@ impl TransactionBuilder {
     /// Especially because it is possible that 1 or more of these
     /// is actually a true input, which will not be discovered until
     /// ::build() is called.
-    pub fn add_decoy_inputs(mut self, decoy_inputs: Vec<DecoyInput>) -> Self {
-        let available_decoys = self.available_decoys.clone();
-        let new_decoys = decoy_inputs.iter().filter(|new| {
-            !available_decoys
+    pub fn add_decoy_inputs(self, decoy_inputs: Vec<DecoyInput>) -> Self {
+        self.add_decoy_candidates(decoy_inputs.into_iter().map(DecoyCandidate::from).collect())
+    }
+
+    /// add to pool of available decoys, with their age and transaction, for
+    /// use by the DecoySelector.
+    ///
+    /// see ::add_decoy_inputs().
+    pub fn add_decoy_candidates(mut self, candidates: Vec<DecoyCandidate>) -> Self {
+        for candidate in candidates {
+            if !self
+                .available_decoys
                 .iter()
-                .any(|old| old.public_key() == new.public_key())
-        });
-        self.available_decoys.extend(new_decoys);
+                .any(|old| old.decoy.public_key() == candidate.decoy.public_key())
+            {
+                self.available_decoys.push(candidate);
+            }
+        }
         self
     }
 
@@ -185,6 +265,13 @@ impl TransactionBuilder {
         self
     }
 
+    /// add a Multisig input.  A transaction with Multisig inputs
+    /// must be built with ::build_multisig().
+    pub fn add_multisig_input(mut self, input: MultisigInput) -> Self {
+        self.multisig_inputs.push(input);
+        self
+    }
+
     /// add an output
     pub fn add_output(mut self, output: Output, owner: OwnerOnce) -> Self {
         self.output_owner_map
@@ -221,29 +308,99 @@ impl TransactionBuilder {
         self
     }
 
+    /// add outputs paying an amount to owner_base, split into denominations.
+    ///
+    /// Each output has its own OwnerOnce, derived from owner_base with a
+    /// random derivation index, so the outputs cannot be linked to one
+    /// another by their owner.  See Denominations::split().
+    pub fn add_outputs_by_denomination(
+        self,
+        amount: Token,
+        owner_base: Owner,
+        denominations: &Denominations,
+        rng: &mut impl RngCore,
+    ) -> Result<Self> {
+        let outputs: Vec<(Token, OwnerOnce)> = denominations
+            .split(amount)?
+            .into_iter()
+            .map(|denomination| {
+                (
+                    denomination,
+                    OwnerOnce::from_owner_base(owner_base.clone(), rng),
+                )
+            })
+            .collect();
+        Ok(self.add_outputs_by_amount(outputs))
+    }
+
+    /// add an output by providing Token, OwnerOnce and DbcMetadata.
+    ///
+    /// The metadata is encrypted to the output's one-time-use owner key,
+    /// so only the recipient can read it.  See Dbc::metadata().
+    pub fn add_output_by_amount_with_metadata(
+        mut self,
+        amount: Token,
+        owner: OwnerOnce,
+        metadata: DbcMetadata,
+    ) -> Self {
+        self.output_metadata
+            .insert(owner.as_owner().public_key(), metadata);
+        self.add_output_by_amount(amount, owner)
+    }
+
+    /// add an output paying a PaymentRequest, after verifying it.
+    ///
+    /// now is the current time, as a unix timestamp in seconds, used to
+    /// check the request has not expired.  The output is owned by a
+    /// one-time-use OwnerOnce derived from the request's recipient, and
+    /// carries the request's invoice id and memo as DbcMetadata.
+    pub fn pay_request(
+        self,
+        request: &PaymentRequest,
+        now: u64,
+        rng: &mut impl RngCore,
+    ) -> Result<Self> {
+        request.verify(now)?;
+        let owner = OwnerOnce::from_owner_base(Owner::from(request.recipient()), rng);
+        let metadata = DbcMetadata {
+            memo: request.memo().to_string(),
+            invoice_id: request.invoice_id().to_string(),
+            refund_to: None,
+        };
+        Ok(self.add_output_by_amount_with_metadata(request.amount(), owner, metadata))
+    }
+
     /// get a list of input (true) owners
     pub fn input_owners(&self) -> Vec<PublicKey> {
         self.true_inputs
             .iter()
             .map(|t| t.public_key().into())
+            .chain(self.multisig_inputs.iter().map(|i| i.public_key()))
             .collect()
     }
 
-    /// get sum of input amounts
-    pub fn inputs_amount_sum(&self) -> Token {
-        let amount = self
-            .true_inputs
-            .iter()
-            .map(|t| t.revealed_commitment.value)
-            .sum();
-
-        Token::from_nano(amount)
+    /// get sum of input amounts.
+    /// returns Error::InputsAmountOverflow if the sum overflows, as ::validate().
+    pub fn inputs_amount_sum(&self) -> Result<Token> {
+        let mut sum = Token::zero();
+        for (idx, (_, amount, _)) in self.all_inputs().into_iter().enumerate() {
+            sum = sum
+                .checked_add(Token::from_nano(amount))
+                .ok_or(Error::InputsAmountOverflow(idx))?;
+        }
+        Ok(sum)
     }
 
-    /// get sum of output amounts
-    pub fn outputs_amount_sum(&self) -> Token {
-        let amount = self.ringct_material.outputs.iter().map(|o| o.amount).sum();
-        Token::from_nano(amount)
+    /// get sum of output amounts.
+    /// returns Error::OutputsAmountOverflow if the sum overflows, as ::validate().
+    pub fn outputs_amount_sum(&self) -> Result<Token> {
+        let mut sum = Token::zero();
+        for (idx, output) in self.ringct_material.outputs.iter().enumerate() {
+            sum = sum
+                .checked_add(Token::from_nano(output.amount))
+                .ok_or(Error::OutputsAmountOverflow(idx))?;
+        }
+        Ok(sum)
     }
 
     /// get true inputs
@@ -256,21 +413,333 @@ impl TransactionBuilder {
         &self.ringct_material.outputs
     }
 
+    /// check that the transaction can be built, before any signing.
+    ///
+    /// Inputs are indexed in the order of ::input_owners(), and outputs in
+    /// the order of ::outputs().  Checks that:
+    ///   1. there is at least one output, or a change owner.
+    ///   2. no output has the same public key as an earlier output.
+    ///   3. no input is the same as an earlier input.
+    ///   4. no output is of zero amount or below the dust threshold.
+    ///   5. the sums of inputs, and of outputs plus fee, do not overflow.
+    ///   6. the outputs plus fee equal the inputs, or do not exceed them if
+    ///      there is a change owner.
+    pub fn validate(&self) -> Result<()> {
+        let outputs = &self.ringct_material.outputs;
+        if outputs.is_empty() && self.change_owner.is_none() {
+            return Err(Error::TransactionMustHaveAnOutput);
+        }
+
+        let mut output_public_keys = BTreeSet::new();
+        for (idx, output) in outputs.iter().enumerate() {
+            if !output_public_keys.insert(PublicKey::from(output.public_key())) {
+                return Err(Error::OutputPublicKeyNotUnique(idx));
+            }
+        }
+
+        let inputs: Vec<(PublicKey, u64)> = self
+            .all_inputs()
+            .into_iter()
+            .map(|(pk, amount, _)| (pk, amount))
+            .collect();
+        let mut input_public_keys = BTreeSet::new();
+        for (idx, (pk, _)) in inputs.iter().enumerate() {
+            if !input_public_keys.insert(*pk) {
+                return Err(Error::InputNotUnique(idx));
+            }
+        }
+
+        for (idx, output) in outputs.iter().enumerate() {
+            if output.amount == 0 {
+                return Err(Error::OutputAmountZero(idx));
+            }
+            if Token::from_nano(output.amount) < self.dust_threshold {
+                return Err(Error::OutputAmountDust(idx));
+            }
+        }
+
+        let mut inputs_amount = Token::zero();
+        for (idx, (_, amount)) in inputs.iter().enumerate() {
+            inputs_amount = inputs_amount
+                .checked_add(Token::from_nano(*amount))
+                .ok_or(Error::InputsAmountOverflow(idx))?;
+        }
+        let mut outputs_amount = self.fee;
+        for (idx, output) in outputs.iter().enumerate() {
+            outputs_amount = outputs_amount
+                .checked_add(Token::from_nano(output.amount))
+                .ok_or(Error::OutputsAmountOverflow(idx))?;
+        }
+
+        if outputs_amount > inputs_amount {
+            return Err(Error::OutputsExceedInputs {
+                inputs: inputs_amount,
+                outputs: outputs_amount,
+            });
+        }
+        if inputs_amount > outputs_amount && self.change_owner.is_none() {
+            return Err(Error::InputsExceedOutputs {
+                inputs: inputs_amount,
+                outputs: outputs_amount,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// estimate the size of the transaction and its output Dbcs, and the
+    /// cost to verify it, without building it.
+    ///
+    /// Inputs are assumed to receive <decoys_per_input> decoys each.  A
+    /// change output is included if there is a change owner and a remainder.
+    pub fn estimate(&self) -> TransactionEstimate {
+        let inputs = self.all_inputs();
+        let ring_sizes: Vec<usize> = inputs
+            .iter()
+            .map(|(_, _, num_decoys)| 1 + num_decoys.unwrap_or(self.decoys_per_input))
+            .collect();
+
+        let inputs_amount = inputs
+            .iter()
+            .try_fold(0u64, |sum, (_, amount, _)| sum.checked_add(*amount));
+        let outputs_amount = self
+            .ringct_material
+            .outputs
+            .iter()
+            .try_fold(self.fee.as_nano(), |sum, o| sum.checked_add(o.amount));
+        let change_owner = match (&self.change_owner, inputs_amount, outputs_amount) {
+            (Some(owner), Some(inputs), Some(outputs)) if inputs > outputs => Some(owner),
+            _ => None,
+        };
+
+        let num_outputs = self.ringct_material.outputs.len()
+            + change_owner.iter().count()
+            + if self.fee > Token::zero() { 1 } else { 0 };
+        let dbc_owners = self
+            .ringct_material
+            .outputs
+            .iter()
+            .filter_map(|o| {
+                let pk = PublicKey::from(o.public_key());
+                self.output_owner_map
+                    .get(&pk)
+                    .map(|owner| (owner.owner_base(), self.output_metadata.get(&pk)))
+            })
+            .chain(change_owner.map(|owner| (owner.owner_base(), None)));
+
+        TransactionEstimate::new(&ring_sizes, num_outputs, dbc_owners)
+    }
+
+    // the public key and amount of every input, in the order of
+    // ::input_owners(), and the number of decoys of those that already
+    // have them.  Inputs added via ::add_inputs() alone are not in
+    // true_inputs, so follow.
+    fn all_inputs(&self) -> Vec<(PublicKey, u64, Option<usize>)> {
+        let decoys_of = |true_input: &TrueInput| {
+            self.ringct_material
+                .inputs
+                .iter()
+                .find(|m| m.true_input.public_key() == true_input.public_key())
+                .map(|m| m.decoy_inputs.len())
+        };
+        self.true_inputs
+            .iter()
+            .map(|t| {
+                (
+                    t.public_key().into(),
+                    t.revealed_commitment.value,
+                    decoys_of(t),
+                )
+            })
+            .chain(
+                self.multisig_inputs
+                    .iter()
+                    .map(|i| (i.public_key(), i.amount().as_nano(), None)),
+            )
+            .chain(
+                self.ringct_material
+                    .inputs
+                    .iter()
+                    .filter(|m| {
+                        !self
+                            .true_inputs
+                            .iter()
+                            .any(|t| t.public_key() == m.true_input.public_key())
+                    })
+                    .map(|m| {
+                        (
+                            m.true_input.public_key().into(),
+                            m.true_input.revealed_commitment.value,
+                            Some(m.decoy_inputs.len()),
+                        )
+                    }),
+            )
+            .collect()
+    }
+
+    fn default_decoy_selector() -> Box<dyn DecoySelector> {
+        Box::new(UniformDecoySelector)
+    }
+
     /// build a RingCtTransaction and associated secrets
-    pub fn build(self, mut rng: impl RngCore + CryptoRng) -> Result<DbcBuilder> {
+    pub fn build(mut self, mut rng: impl RngCore + CryptoRng) -> Result<DbcBuilder> {
+        if !self.multisig_inputs.is_empty() {
+            return Err(Error::MultisigSigningRequired);
+        }
+        self.validate()?;
+        let output_metadata = std::mem::take(&mut self.output_metadata);
+        let (ringct_material, _, output_owner_map, fee_idx) = self.prepare(&mut rng)?;
+
+        // Grand finale! sign the ringct_material to generate a Tx.
+        let (transaction, revealed_commitments) = ringct_material.sign(rng)?;
+
+        let fee = match fee_idx {
+            Some(idx) => Fee::from(revealed_commitments[idx]),
+            None => Fee::zero(),
+        };
+
+        Ok(DbcBuilder {
+            fee,
+            output_metadata,
+            ..DbcBuilder::new(
+                transaction,
+                revealed_commitments,
+                output_owner_map,
+                ringct_material,
+            )
+        })
+    }
+
+    /// build a RingCtTransaction and associated secrets, fetching the
+    /// decoys for each input from a DecoyProvider.
+    ///
+    /// Decoys are fetched until the decoy_selector fills a ring of
+    /// <decoys_per_input> for every input, counting any decoys already
+    /// added.  Fetched decoys that are true inputs or duplicates, or that
+    /// the selector leaves out (eg siblings, for the
+    /// DistinctTransactionDecoySelector), are made up by fetching again.
+    pub fn build_with_decoy_provider(
+        self,
+        provider: &dyn DecoyProvider,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<DbcBuilder> {
+        self.fetch_decoys(provider, &mut rng).build(rng)
+    }
+
+    // adds decoys from provider to available_decoys until the decoy_selector
+    // fills the rings of every input that does not yet have any.
+    fn fetch_decoys(mut self, provider: &dyn DecoyProvider, rng: &mut dyn RngCore) -> Self {
+        let true_public_keys: BTreeSet<PublicKey> = self.input_owners().into_iter().collect();
+        let num_inputs = self
+            .true_inputs
+            .iter()
+            .filter(|t| {
+                !self
+                    .ringct_material
+                    .inputs
+                    .iter()
+                    .any(|m| m.true_input.public_key() == t.public_key())
+            })
+            .count()
+            + self.multisig_inputs.len();
+
+        for _ in 0..MAX_DECOY_FETCHES {
+            let usable: Vec<DecoyCandidate> = self
+                .available_decoys
+                .iter()
+                .filter(|d| !true_public_keys.contains(&d.decoy.public_key().into()))
+                .cloned()
+                .collect();
+            let mut decoy_rings =
+                self.decoy_selector
+                    .select(usable, num_inputs, self.decoys_per_input, rng);
+            decoy_rings.resize(num_inputs, vec![]);
+            let num_missing: usize = decoy_rings
+                .iter()
+                .map(|r| self.decoys_per_input.saturating_sub(r.len()))
+                .sum();
+            if num_missing == 0 {
+                break;
+            }
+
+            let fetched = provider.fetch_decoys(num_missing, rng);
+            if fetched.is_empty() {
+                break;
+            }
+            self = self.add_decoy_candidates(
+                fetched
+                    .into_iter()
+                    .filter(|d| !true_public_keys.contains(&d.decoy.public_key().into()))
+                    .collect(),
+            );
+        }
+        self
+    }
+
+    /// build a RingCtTransaction with Multisig inputs.
+    ///
+    /// The returned MultisigSigningSession must collect signature shares
+    /// for each Multisig input before it yields a DbcBuilder.
+    /// See MultisigOwnerShare for the signing protocol.
+    pub fn build_multisig(
+        mut self,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<MultisigSigningSession> {
+        self.validate()?;
+        let output_metadata = std::mem::take(&mut self.output_metadata);
+        let (ringct_material, multisig_inputs, output_owner_map, fee_idx) =
+            self.prepare(&mut rng)?;
+        MultisigSigningSession::new(
+            ringct_material,
+            multisig_inputs,
+            output_owner_map,
+            output_metadata,
+            fee_idx,
+            rng,
+        )
+    }
+
+    /// build one party's contribution to a TransactionProposal.
+    ///
+    /// The inputs must exactly fund the outputs, so a change owner should
+    /// usually be set.  See TransactionProposal for the signing protocol.
+    #[cfg(feature = "serde")]
+    pub fn build_proposal_party(
+        mut self,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<crate::ProposalParty> {
+        if !self.multisig_inputs.is_empty() {
+            return Err(Error::MultisigSigningRequired);
+        }
+        if self.fee > Token::zero() {
+            return Err(Error::TransactionProposalFeeUnsupported);
+        }
+        let output_metadata = std::mem::take(&mut self.output_metadata);
+        let (ringct_material, _, output_owner_map, _) = self.prepare(&mut rng)?;
+        crate::ProposalParty::new(ringct_material, output_owner_map, output_metadata, rng)
+    }
+
+    // assigns decoys to inputs and adds the change and fee outputs.
+    fn prepare(self, mut rng: impl RngCore + CryptoRng) -> Result<PreparedMaterial> {
         let mut ringct_material = self.ringct_material;
         let mut true_inputs = self.true_inputs;
+        let mut output_owner_map = self.output_owner_map;
 
         // get public_keys of all true_inputs.
         let true_public_keys: Vec<_> = true_inputs
             .iter()
             .map(|true_input| true_input.public_key().to_affine())
+            .chain(self.multisig_inputs.iter().map(|i| i.public_key().into()))
             .collect();
         // remove any available decoys that are actually true inputs.
         let available_decoys: Vec<_> = self
             .available_decoys
             .into_iter()
-            .filter(|d| true_public_keys.iter().all(|pk| *pk != d.public_key()))
+            .filter(|d| {
+                true_public_keys
+                    .iter()
+                    .all(|pk| *pk != d.decoy.public_key())
+            })
             .collect();
 
         // remove any true inputs that are already in self.ringct_material.inputs
@@ -282,49 +751,97 @@ impl TransactionBuilder {
                 .any(|m| m.true_input.public_key() == true_input.public_key())
         });
 
+        // add change output, if requested.  This must happen before signing,
+        // so that an overspend is reported here rather than as a failure
+        // to verify the signed transaction.
+        if let Some(change_owner) = self.change_owner {
+            let inputs_amount = ringct_material
+                .inputs
+                .iter()
+                .map(|m| &m.true_input.revealed_commitment)
+                .chain(true_inputs.iter().map(|t| &t.revealed_commitment))
+                .chain(self.multisig_inputs.iter().map(|i| i.revealed_commitment()))
+                .try_fold(Token::zero(), |sum, r| {
+                    sum.checked_add(Token::from_nano(r.value))
+                })
+                .ok_or(Error::ExcessiveTokenValue)?;
+            let outputs_amount = ringct_material
+                .outputs
+                .iter()
+                .try_fold(self.fee, |sum, o| {
+                    sum.checked_add(Token::from_nano(o.amount))
+                })
+                .ok_or(Error::ExcessiveTokenValue)?;
+            let change =
+                inputs_amount
+                    .checked_sub(outputs_amount)
+                    .ok_or(Error::OutputsExceedInputs {
+                        inputs: inputs_amount,
+                        outputs: outputs_amount,
+                    })?;
+            if change > Token::zero() {
+                let pk = change_owner.as_owner().public_key();
+                ringct_material
+                    .outputs
+                    .push(Output::new(pk, change.as_nano()));
+                output_owner_map.insert(pk, change_owner);
+            }
+        }
+
         // calc total number of decoys required for Tx.
-        let num_required_decoys = true_inputs.len() * self.decoys_per_input;
+        let num_inputs = true_inputs.len() + self.multisig_inputs.len();
+        let decoys_per_input = self.decoys_per_input;
+        let num_required_decoys = num_inputs * decoys_per_input;
         if self.require_all_decoys && available_decoys.len() < num_required_decoys {
             return Err(Error::InsufficientDecoys);
         }
 
-        // group available decoys into sets of <decoys_per_input>.
-        let mut decoy_inputs_chunks: Vec<&[DecoyInput]> = match self.decoys_per_input {
-            0 => vec![], // ::chunks() panics if chunk-size is zero.
-            _ => available_decoys.chunks(self.decoys_per_input).collect(),
-        };
-
-        // if we don't have enough sets of decoys, then we need to add any
-        // missing sets, to match true_inputs.len()
-        let empty: Vec<DecoyInput> = vec![];
-        if decoy_inputs_chunks.len() < true_inputs.len() {
-            assert!(!self.require_all_decoys);
-            assert!(num_required_decoys == 0 || num_required_decoys > available_decoys.len());
+        // select a set of decoys for each input.
+        let mut decoy_rings =
+            self.decoy_selector
+                .select(available_decoys, num_inputs, decoys_per_input, &mut rng);
+        decoy_rings.resize(num_inputs, vec![]);
+        if self.require_all_decoys && decoy_rings.iter().any(|r| r.len() < decoys_per_input) {
+            return Err(Error::InsufficientDecoys);
+        }
 
-            // pad to true_inputs.len with empty vec(s).
-            while decoy_inputs_chunks.len() < true_inputs.len() {
-                decoy_inputs_chunks.push(&empty);
-            }
+        // the selector may order decoys by preference, which must not leak
+        // into the ring.  MlsagMaterial places the true input at a random
+        // position.
+        for ring in decoy_rings.iter_mut() {
+            ring.shuffle(&mut rng);
         }
 
-        // create our final ringct inputs, with decoys.
-        for (true_input, decoy_inputs) in true_inputs.into_iter().zip(decoy_inputs_chunks) {
-            ringct_material.inputs.push(MlsagMaterial::new(
-                true_input,
-                decoy_inputs.to_vec(),
-                &mut rng,
-            ));
+        // create our final ringct inputs, with decoys.  Multisig inputs
+        // receive the decoy sets that remain after the true inputs.
+        let mut decoy_rings = decoy_rings.into_iter();
+        for (true_input, decoy_inputs) in true_inputs.into_iter().zip(&mut decoy_rings) {
+            ringct_material
+                .inputs
+                .push(MlsagMaterial::new(true_input, decoy_inputs, &mut rng));
         }
+        let multisig_inputs: Vec<(MultisigInput, Vec<DecoyInput>)> =
+            self.multisig_inputs.into_iter().zip(decoy_rings).collect();
 
-        // Grand finale! sign the ringct_material to generate a Tx.
-        let (transaction, revealed_commitments) = ringct_material.sign(rng)?;
+        // add fee output.  Its public key is derived from the input key images,
+        // so this must happen after the inputs are finalized.
+        let fee_idx = if self.fee > Token::zero() {
+            let key_images: Vec<KeyImage> = ringct_material
+                .inputs
+                .iter()
+                .map(|m| m.true_input.key_image().to_affine().into())
+                .chain(multisig_inputs.iter().map(|(i, _)| i.key_image()))
+                .collect();
+            let fee_pk = Fee::output_public_key(key_images.iter());
+            ringct_material
+                .outputs
+                .push(Output::new(fee_pk, self.fee.as_nano()));
+            Some(ringct_material.outputs.len() - 1)
+        } else {
+            None
+        };
 
-        Ok(DbcBuilder::new(
-            transaction,
-            revealed_commitments,
-            self.output_owner_map,
-            ringct_material,
-        ))
+        Ok((ringct_material, multisig_inputs, output_owner_map, fee_idx))
     }
 }
 
@@ -335,7 +852,13 @@ pub struct DbcBuilder {
     pub transaction: RingCtTransaction,
     pub revealed_commitments: Vec<RevealedCommitment>,
     pub output_owner_map: OutputOwnerMap,
+    pub output_metadata: OutputMetadataMap,
     pub ringct_material: RingCtMaterial,
+    pub fee: Fee,
+    /// outputs of other parties, which do not become Dbcs.  Any other output
+    /// (but the fee) must have a revealed commitment and an owner.
+    /// see ProposalParty::dbc_builder().
+    pub foreign_outputs: BTreeSet<PublicKey>,
 
     pub spent_proof_shares: BTreeMap<KeyImage, HashSet<SpentProofShare>>,
     pub spent_transactions: BTreeMap<Hash, RingCtTransaction>,
@@ -353,7 +876,10 @@ impl DbcBuilder {
             transaction,
             revealed_commitments,
             output_owner_map,
+            output_metadata: Default::default(),
             ringct_material,
+            fee: Fee::zero(),
+            foreign_outputs: Default::default(),
             spent_proof_shares: Default::default(),
             spent_transactions: Default::default(),
         }
@@ -406,12 +932,27 @@ impl DbcBuilder {
     pub fn build<K: SpentProofKeyVerifier>(
         self,
         verifier: &K,
+    ) -> Result<Vec<(Dbc, OwnerOnce, AmountSecrets)>> {
+        self.build_with_rng(verifier, crate::rng::thread_rng())
+    }
+
+    /// as ::build(), but the secrets of each output Dbc are encrypted with
+    /// randomness from rng, so that the output Dbcs are determined by it.
+    pub fn build_with_rng<K: SpentProofKeyVerifier>(
+        self,
+        verifier: &K,
+        rng: impl RngCore + CryptoRng,
     ) -> Result<Vec<(Dbc, OwnerOnce, AmountSecrets)>> {
         let spent_proofs = self.spent_proofs()?;
 
         // verify the Tx, along with spent proofs.
         // note that we do this just once for entire Tx, not once per output Dbc.
-        TransactionVerifier::verify(verifier, &self.transaction, &spent_proofs)?;
+        TransactionVerifier::verify_with_fee(
+            verifier,
+            &self.transaction,
+            &self.fee,
+            &spent_proofs,
+        )?;
 
         // verify there is a matching spent transaction for each spent_proof
         if !spent_proofs.iter().all(|proof| {
@@ -422,19 +963,28 @@ impl DbcBuilder {
         }
 
         // build output DBCs
-        self.build_output_dbcs(spent_proofs)
+        self.build_output_dbcs(spent_proofs, rng)
     }
 
     /// Build the output DBCs (no verification over Tx or spentproof is performed).
     pub fn build_without_verifying(self) -> Result<Vec<(Dbc, OwnerOnce, AmountSecrets)>> {
+        self.build_without_verifying_with_rng(crate::rng::thread_rng())
+    }
+
+    /// as ::build_without_verifying(), but see ::build_with_rng().
+    pub fn build_without_verifying_with_rng(
+        self,
+        rng: impl RngCore + CryptoRng,
+    ) -> Result<Vec<(Dbc, OwnerOnce, AmountSecrets)>> {
         let spent_proofs = self.spent_proofs()?;
-        self.build_output_dbcs(spent_proofs)
+        self.build_output_dbcs(spent_proofs, rng)
     }
 
     // Private helper to build output DBCs
     fn build_output_dbcs(
         self,
         spent_proofs: BTreeSet<SpentProof>,
+        mut rng: impl RngCore + CryptoRng,
     ) -> Result<Vec<(Dbc, OwnerOnce, AmountSecrets)>> {
         let pc_gens = PedersenGens::default();
         let output_commitments: Vec<(Commitment, RevealedCommitment)> = self
@@ -443,42 +993,55 @@ impl DbcBuilder {
             .map(|r| (r.commit(&pc_gens).to_affine(), *r))
             .collect();
 
-        let owner_once_list: Vec<&OwnerOnce> = self
+        // the fee output has no owner, and does not become a Dbc.  Nor do the
+        // outputs of other parties to a TransactionProposal.
+        let fee_pk = Fee::public_key(&self.transaction);
+        let outputs: Vec<&OutputProof> = self
             .transaction
             .outputs
             .iter()
-            .map(|output| {
-                self.output_owner_map
-                    .get(&(*output.public_key()).into())
-                    .ok_or(Error::PublicKeyNotFound)
+            .filter(|output| {
+                let pk = PublicKey::from(*output.public_key());
+                pk != fee_pk && !self.foreign_outputs.contains(&pk)
             })
-            .collect::<Result<_>>()?;
+            .collect();
 
-        // Form the final output DBCs
-        let output_dbcs: Vec<(Dbc, OwnerOnce, AmountSecrets)> = self
-            .transaction
-            .outputs
+        let owned_outputs: Vec<(&OwnerOnce, AmountSecrets)> = outputs
             .iter()
-            .zip(owner_once_list)
-            .map(|(output, owner_once)| {
-                let amount_secrets_list: Vec<AmountSecrets> = output_commitments
+            .map(|output| {
+                let amount_secrets = output_commitments
                     .iter()
-                    .filter(|(c, _)| *c == output.commitment())
+                    .find(|(c, _)| *c == output.commitment())
                     .map(|(_, r)| AmountSecrets::from(*r))
-                    .collect();
-                assert_eq!(amount_secrets_list.len(), 1);
+                    .ok_or(Error::RevealedCommitmentNotFound)?;
+                let owner_once = self
+                    .output_owner_map
+                    .get(&(*output.public_key()).into())
+                    .ok_or(Error::PublicKeyNotFound)?;
+                Ok((owner_once, amount_secrets))
+            })
+            .collect::<Result<_>>()?;
 
+        // Form the final output DBCs
+        let output_dbcs: Vec<(Dbc, OwnerOnce, AmountSecrets)> = owned_outputs
+            .into_iter()
+            .map(|(owner_once, amount_secrets)| {
                 let dbc = Dbc {
-                    content: DbcContent::from((
+                    content: DbcContent::new(
                         owner_once.owner_base.clone(),
                         owner_once.derivation_index,
-                        amount_secrets_list[0].clone(),
-                    )),
+                        amount_secrets.clone(),
+                        self.output_metadata
+                            .get(&owner_once.as_owner().public_key())
+                            .cloned(),
+                        &mut rng,
+                    ),
                     transaction: self.transaction.clone(),
+                    fee: self.fee.clone(),
                     spent_proofs: spent_proofs.clone(),
                     spent_transactions: self.spent_transactions.values().cloned().collect(),
                 };
-                (dbc, owner_once.clone(), amount_secrets_list[0].clone())
+                (dbc, owner_once.clone(), amount_secrets)
             })
             .collect();
 
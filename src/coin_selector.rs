@@ -0,0 +1,385 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{rngs::StdRng, seq::SliceRandom, CryptoRng, RngCore},
+    rng, Dbc, Error, Result, Token,
+};
+use blsttc::SecretKey;
+use std::sync::Mutex;
+
+/// The result of a coin selection: the chosen input Dbcs and the
+/// amount of change that must be paid back to the spender.
+#[derive(Debug, Clone)]
+pub struct CoinSelection {
+    /// the selected Dbcs, with the base SecretKey of each
+    pub inputs: Vec<(Dbc, SecretKey)>,
+    /// the amount by which the selected inputs exceed the target
+    pub change: Token,
+}
+
+/// A strategy for choosing which Dbcs to spend in order to pay a target amount.
+///
+/// Implementations receive the candidate (Dbc, base SecretKey) pairs and must
+/// return a subset whose amounts sum to at least the target.  Candidates that
+/// cannot be decrypted with their SecretKey result in an error.
+pub trait CoinSelector {
+    /// select inputs from `candidates` that cover `target`.
+    fn select(&self, candidates: Vec<(Dbc, SecretKey)>, target: Token) -> Result<CoinSelection>;
+}
+
+/// Selects the largest Dbcs first.  This minimizes the number of inputs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LargestFirst;
+
+/// Selects the smallest Dbcs first.  This consolidates small Dbcs at the
+/// cost of larger transactions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmallestFirst;
+
+/// Searches for a set of Dbcs whose sum matches the target exactly (or
+/// exceeds it by no more than `tolerance`) so that no change output is needed.
+///
+/// This is a depth-first branch and bound search, which gives up after
+/// `max_tries` steps and returns Error::CoinSelectionNotFound.
+#[derive(Debug, Clone, Copy)]
+pub struct BranchAndBound {
+    /// the maximum amount by which the selection may exceed the target
+    pub tolerance: Token,
+    /// the maximum number of search steps before giving up
+    pub max_tries: usize,
+}
+
+impl Default for BranchAndBound {
+    fn default() -> Self {
+        Self {
+            tolerance: Token::zero(),
+            max_tries: 100_000,
+        }
+    }
+}
+
+/// Selects Dbcs in random order until the target is covered.
+///
+/// This avoids the deterministic patterns of the other strategies, which an
+/// observer could use to link inputs or guess at the wallet's contents.
+///
+/// The randomness is drawn from an rng seeded from a CryptoRng at
+/// construction, whose state advances with each selection, so that every
+/// selection is shuffled anew.  The rng is held in a Mutex so that the
+/// selector itself can be used via the (non-generic) CoinSelector trait.
+#[derive(Debug)]
+pub struct RandomSelector {
+    rng: Mutex<StdRng>,
+}
+
+impl Default for RandomSelector {
+    fn default() -> Self {
+        Self::new(&mut rng::thread_rng())
+    }
+}
+
+impl RandomSelector {
+    /// create a RandomSelector, seeded from the provided rng.
+    pub fn new(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        Self {
+            rng: Mutex::new(rng::from_seed(seed)),
+        }
+    }
+}
+
+impl CoinSelector for LargestFirst {
+    fn select(&self, candidates: Vec<(Dbc, SecretKey)>, target: Token) -> Result<CoinSelection> {
+        let mut candidates = with_amounts(candidates)?;
+        candidates.sort_by_key(|(amount, _)| std::cmp::Reverse(*amount));
+        select_in_order(candidates, target)
+    }
+}
+
+impl CoinSelector for SmallestFirst {
+    fn select(&self, candidates: Vec<(Dbc, SecretKey)>, target: Token) -> Result<CoinSelection> {
+        let mut candidates = with_amounts(candidates)?;
+        candidates.sort_by_key(|(amount, _)| *amount);
+        select_in_order(candidates, target)
+    }
+}
+
+impl CoinSelector for RandomSelector {
+    fn select(&self, candidates: Vec<(Dbc, SecretKey)>, target: Token) -> Result<CoinSelection> {
+        let mut candidates = with_amounts(candidates)?;
+        // note: shuffling cannot panic, so a poisoned lock is still usable.
+        let mut rng = self.rng.lock().unwrap_or_else(|e| e.into_inner());
+        candidates.shuffle(&mut *rng);
+        select_in_order(candidates, target)
+    }
+}
+
+impl CoinSelector for BranchAndBound {
+    fn select(&self, candidates: Vec<(Dbc, SecretKey)>, target: Token) -> Result<CoinSelection> {
+        let mut candidates = with_amounts(candidates)?;
+        let total = sum(&candidates)?;
+        if total < target {
+            return Err(Error::InsufficientBalance {
+                available: total,
+                requested: target,
+            });
+        }
+
+        // searching largest first finds solutions (or prunes) sooner.
+        candidates.sort_by_key(|(amount, _)| std::cmp::Reverse(*amount));
+
+        let mut search = BnbSearch::new(
+            candidates.iter().map(|(a, _)| a.as_nano()).collect(),
+            target.as_nano(),
+            target.as_nano().saturating_add(self.tolerance.as_nano()),
+            self.max_tries,
+        );
+        if !search.search(0, 0) {
+            return Err(Error::CoinSelectionNotFound);
+        }
+
+        let inputs: Vec<(Token, (Dbc, SecretKey))> = candidates
+            .into_iter()
+            .zip(search.selected)
+            .filter_map(|(c, s)| if s { Some(c) } else { None })
+            .collect();
+        let change = Token::from_nano(sum(&inputs)?.as_nano() - target.as_nano());
+
+        Ok(CoinSelection {
+            inputs: inputs.into_iter().map(|(_, c)| c).collect(),
+            change,
+        })
+    }
+}
+
+// state for a depth-first search for a subset of amounts with a sum within
+// [lower, upper].  amount i is either included or excluded, and a branch is
+// abandoned as soon as it overshoots upper or can no longer reach lower.
+struct BnbSearch {
+    amounts: Vec<u64>,
+    remaining: Vec<u64>, // remaining[i] is the sum of amounts[i..], saturating at u64::MAX
+    selected: Vec<bool>,
+    lower: u64,
+    upper: u64,
+    tries: usize,
+    max_tries: usize,
+}
+
+impl BnbSearch {
+    fn new(amounts: Vec<u64>, lower: u64, upper: u64, max_tries: usize) -> Self {
+        let mut remaining = vec![0u64; amounts.len() + 1];
+        for i in (0..amounts.len()).rev() {
+            // note: a saturated sum only over-estimates what can be reached,
+            // so it never prunes a branch that could succeed.
+            remaining[i] = remaining[i + 1].saturating_add(amounts[i]);
+        }
+        Self {
+            selected: vec![false; amounts.len()],
+            amounts,
+            remaining,
+            lower,
+            upper,
+            tries: 0,
+            max_tries,
+        }
+    }
+
+    fn search(&mut self, idx: usize, current: u64) -> bool {
+        if current >= self.lower && current <= self.upper && current > 0 {
+            return true;
+        }
+        self.tries += 1;
+        if idx == self.amounts.len()
+            || current > self.upper
+            || current.saturating_add(self.remaining[idx]) < self.lower
+            || self.tries > self.max_tries
+        {
+            return false;
+        }
+
+        // note: a sum that overflows is beyond upper, so is not included.
+        if let Some(with_idx) = current.checked_add(self.amounts[idx]) {
+            self.selected[idx] = true;
+            if self.search(idx + 1, with_idx) {
+                return true;
+            }
+        }
+        self.selected[idx] = false;
+        self.search(idx + 1, current)
+    }
+}
+
+// decrypt the amount of each candidate.
+fn with_amounts(candidates: Vec<(Dbc, SecretKey)>) -> Result<Vec<(Token, (Dbc, SecretKey))>> {
+    candidates
+        .into_iter()
+        .map(|(dbc, sk)| Ok((dbc.amount_secrets(&sk)?.amount(), (dbc, sk))))
+        .collect()
+}
+
+fn sum(candidates: &[(Token, (Dbc, SecretKey))]) -> Result<Token> {
+    candidates
+        .iter()
+        .try_fold(Token::zero(), |sum, (amount, _)| {
+            sum.checked_add(*amount).ok_or(Error::ExcessiveTokenValue)
+        })
+}
+
+// take candidates in order until target is covered.
+fn select_in_order(
+    candidates: Vec<(Token, (Dbc, SecretKey))>,
+    target: Token,
+) -> Result<CoinSelection> {
+    let mut inputs = vec![];
+    let mut selected_sum = Token::zero();
+    for (amount, c) in candidates {
+        if selected_sum >= target && !inputs.is_empty() {
+            break;
+        }
+        selected_sum = selected_sum
+            .checked_add(amount)
+            .ok_or(Error::ExcessiveTokenValue)?;
+        inputs.push(c);
+    }
+
+    match selected_sum.checked_sub(target) {
+        Some(change) if !inputs.is_empty() => Ok(CoinSelection { inputs, change }),
+        _ => Err(Error::InsufficientBalance {
+            available: selected_sum,
+            requested: target,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dbc::tests::spend_genesis, Owner, OwnerOnce};
+    use blsttc::rand::{CryptoRng, RngCore};
+
+    // reissue the genesis Dbc into Dbcs of the given amounts (plus remainder,
+    // which is not returned).
+    fn candidates_of_amounts(
+        amounts: &[u64],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Vec<(Dbc, SecretKey)>> {
+        let owners: Vec<Owner> = amounts
+            .iter()
+            .map(|_| Owner::from_random_secret_key(rng))
+            .collect();
+
+        let (_spentbook_node, outputs) = spend_genesis(rng, |tx_builder, rng| {
+            let outputs: Vec<(Token, OwnerOnce)> = amounts
+                .iter()
+                .zip(owners.iter())
+                .map(|(a, o)| {
+                    (
+                        Token::from_nano(*a),
+                        OwnerOnce::from_owner_base(o.clone(), rng),
+                    )
+                })
+                .collect();
+            Ok(tx_builder.add_outputs_by_amount(outputs))
+        })?;
+
+        let mut candidates = vec![];
+        for (dbc, owner_once, _amount_secrets) in outputs {
+            if let Some(o) = owners.iter().find(|o| **o == owner_once.owner_base) {
+                candidates.push((dbc, o.secret_key()?));
+            }
+        }
+        Ok(candidates)
+    }
+
+    fn selected_amounts(selection: &CoinSelection) -> Result<Vec<u64>> {
+        let mut amounts = selection
+            .inputs
+            .iter()
+            .map(|(dbc, sk)| Ok(dbc.amount_secrets(sk)?.amount().as_nano()))
+            .collect::<Result<Vec<u64>>>()?;
+        amounts.sort_unstable();
+        Ok(amounts)
+    }
+
+    #[test]
+    fn selectors_choose_expected_inputs() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let candidates = candidates_of_amounts(&[10, 20, 30, 45], &mut rng)?;
+        let target = Token::from_nano(50);
+
+        let selection = LargestFirst.select(candidates.clone(), target)?;
+        assert_eq!(selected_amounts(&selection)?, vec![30, 45]);
+        assert_eq!(selection.change, Token::from_nano(25));
+
+        let selection = SmallestFirst.select(candidates.clone(), target)?;
+        assert_eq!(selected_amounts(&selection)?, vec![10, 20, 30]);
+        assert_eq!(selection.change, Token::from_nano(10));
+
+        let selection = BranchAndBound::default().select(candidates.clone(), target)?;
+        assert_eq!(selected_amounts(&selection)?, vec![20, 30]);
+        assert_eq!(selection.change, Token::zero());
+
+        // each selection is shuffled anew.
+        let random_selector = RandomSelector::new(&mut rng);
+        let mut selections = std::collections::BTreeSet::new();
+        for _ in 0..10 {
+            let selection = random_selector.select(candidates.clone(), target)?;
+            let selected_sum: u64 = selected_amounts(&selection)?.iter().sum();
+            assert_eq!(selected_sum, target.as_nano() + selection.change.as_nano());
+            selections.insert(selected_amounts(&selection)?);
+        }
+        assert!(selections.len() > 1);
+
+        let result = BranchAndBound::default().select(candidates.clone(), Token::from_nano(36));
+        assert_eq!(result.err(), Some(Error::CoinSelectionNotFound));
+
+        let selection = BranchAndBound {
+            tolerance: Token::from_nano(4),
+            ..Default::default()
+        }
+        .select(candidates.clone(), Token::from_nano(36))?;
+        assert_eq!(selected_amounts(&selection)?, vec![10, 30]);
+        assert_eq!(selection.change, Token::from_nano(4));
+
+        let result = LargestFirst.select(candidates, Token::from_nano(106));
+        assert_eq!(
+            result.err(),
+            Some(Error::InsufficientBalance {
+                available: Token::from_nano(105),
+                requested: Token::from_nano(106),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn branch_and_bound_search() {
+        let amounts = vec![45, 30, 20, 10];
+
+        let mut search = BnbSearch::new(amounts.clone(), 60, 60, 1000);
+        assert!(search.search(0, 0));
+        assert_eq!(search.selected, vec![false, true, true, true]);
+
+        let mut search = BnbSearch::new(amounts.clone(), 46, 46, 1000);
+        assert!(!search.search(0, 0));
+
+        // gives up once max_tries is exceeded.
+        let mut search = BnbSearch::new(amounts, 60, 60, 2);
+        assert!(!search.search(0, 0));
+
+        // amounts whose sum overflows u64.
+        let mut search = BnbSearch::new(vec![u64::MAX, u64::MAX - 1, 1], 2, 2, 1000);
+        assert!(!search.search(0, 0));
+        let mut search = BnbSearch::new(vec![u64::MAX - 1, u64::MAX, 1], u64::MAX, u64::MAX, 1000);
+        assert!(search.search(0, 0));
+        assert_eq!(search.selected, vec![true, false, true]);
+    }
+}
@@ -0,0 +1,734 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    proposal::{public_commitments, range_proof_transcript},
+    rand::{seq::SliceRandom, CryptoRng, RngCore},
+    DbcBuilder, Error, ProposalParty, Result, RingCtTransaction, TransactionProposal,
+};
+use bls_ringct::{
+    bls_bulletproofs::PedersenGens,
+    blstrs::{G1Projective, Scalar},
+    group::{ff::Field, Group},
+    mlsag::MlsagSignature,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Identifies a participant to the coordinator.  It is assigned by the
+/// transport, and is never revealed to other participants.
+pub type ParticipantId = u64;
+
+/// A message from the coordinator to a single participant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CoordinatorMessage {
+    /// invites the participant to the round.  The blinding offset must be
+    /// kept secret from the other participants.
+    Invite { blinding_offset: Scalar },
+    /// asks the participant to add its outputs to the proposal.
+    AddOutputs(TransactionProposal),
+    /// asks the participant to sign its inputs.
+    Sign(TransactionProposal),
+    /// the finalized transaction.
+    Complete(RingCtTransaction),
+}
+
+/// A message from a participant to the coordinator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ParticipantMessage {
+    /// the participant's unsigned inputs.
+    Inputs(Vec<MlsagSignature>),
+    /// the proposal, with the participant's outputs appended.
+    Outputs(TransactionProposal),
+    /// the participant's signed inputs.
+    Signatures(Vec<MlsagSignature>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Phase {
+    Inputs,
+    // index into output_order of the participant adding its outputs.
+    Outputs(usize),
+    Signing,
+    Complete,
+    Aborted,
+}
+
+#[derive(Debug, Clone)]
+struct Registration {
+    blinding_offset: Scalar,
+    inputs: Vec<MlsagSignature>,
+    signed: bool,
+}
+
+/// Coordinates a CoinJoin round, in which several participants combine
+/// their inputs and outputs into a single RingCtTransaction.
+///
+/// The round proceeds in phases:
+///   1. Inputs: each participant is invited with a secret blinding offset,
+///      and replies with its unsigned inputs.  The offsets sum to zero.
+///   2. Outputs: the coordinator shuffles all inputs into a proposal, and
+///      passes it to each participant in a random order to append its
+///      outputs.  This is sequential, as each output range proof is chained
+///      to the previous ones.
+///   3. Signing: all participants sign their inputs of the same proposal.
+///   4. Complete: the finalized transaction is sent to all participants.
+///
+/// Each message is checked as it arrives.  A participant that sends an
+/// invalid message is blamed and the round is aborted.  A participant that
+/// stops responding can be identified via ::blame(), eg after a timeout.
+/// Either way, a new round may be started without the blamed participants.
+///
+/// Participants do not learn each other's amounts, or which inputs belong
+/// to whom.  Each participant's pseudo-commitments balance its outputs only
+/// up to its secret offset, so the inputs cannot be matched to the outputs
+/// by trying subsets of the commitments.  However, a participant does see
+/// the outputs appended before its own as a group, though not who added
+/// them.
+///
+/// The coordinator learns which inputs and outputs belong to which
+/// participant, though not the amounts.  Messages must be sent over
+/// confidential channels, so that the offsets are not revealed.
+#[derive(Debug, Clone)]
+pub struct CoinJoinCoordinator {
+    registrations: BTreeMap<ParticipantId, Registration>,
+    output_order: Vec<ParticipantId>,
+    proposal: TransactionProposal,
+    phase: Phase,
+    blamed: BTreeSet<ParticipantId>,
+    transaction: Option<RingCtTransaction>,
+}
+
+impl CoinJoinCoordinator {
+    /// start a round with the given participants.
+    pub fn new(
+        participants: impl IntoIterator<Item = ParticipantId>,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Self> {
+        let mut output_order: Vec<ParticipantId> = participants
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if output_order.len() < 2 {
+            return Err(Error::CoinJoinTooFewParticipants);
+        }
+        output_order.shuffle(&mut rng);
+
+        // random offsets, which sum to zero.
+        let mut offsets: Vec<Scalar> = (1..output_order.len())
+            .map(|_| Scalar::random(&mut rng))
+            .collect();
+        offsets.push(-offsets.iter().fold(Scalar::zero(), |sum, o| sum + *o));
+
+        let registrations = output_order
+            .iter()
+            .zip(offsets)
+            .map(|(id, blinding_offset)| {
+                (
+                    *id,
+                    Registration {
+                        blinding_offset,
+                        inputs: Vec::new(),
+                        signed: false,
+                    },
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            registrations,
+            output_order,
+            proposal: Default::default(),
+            phase: Phase::Inputs,
+            blamed: Default::default(),
+            transaction: None,
+        })
+    }
+
+    /// returns the invitation for each participant, with which the round
+    /// begins.
+    pub fn invitations(&self) -> Vec<(ParticipantId, CoordinatorMessage)> {
+        self.registrations
+            .iter()
+            .map(|(id, r)| {
+                (
+                    *id,
+                    CoordinatorMessage::Invite {
+                        blinding_offset: r.blinding_offset,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// handle a message from a participant, returning any messages to be
+    /// sent in response.
+    pub fn handle_message(
+        &mut self,
+        from: ParticipantId,
+        msg: ParticipantMessage,
+        rng: impl RngCore + CryptoRng,
+    ) -> Result<Vec<(ParticipantId, CoordinatorMessage)>> {
+        if self.phase == Phase::Aborted {
+            return Err(Error::CoinJoinRoundAborted);
+        }
+        if !self.awaiting().contains(&from) {
+            return Err(Error::CoinJoinUnexpectedMessage);
+        }
+
+        match (&self.phase, msg) {
+            (Phase::Inputs, ParticipantMessage::Inputs(mlsags)) => {
+                self.handle_inputs(from, mlsags, rng)
+            }
+            (Phase::Outputs(_), ParticipantMessage::Outputs(proposal)) => {
+                self.handle_outputs(from, proposal)
+            }
+            (Phase::Signing, ParticipantMessage::Signatures(mlsags)) => {
+                self.handle_signatures(from, mlsags)
+            }
+            _ => Err(Error::CoinJoinUnexpectedMessage),
+        }
+    }
+
+    /// returns the participants to blame if the round fails: those that
+    /// misbehaved, or else those from which a message is awaited.
+    pub fn blame(&self) -> BTreeSet<ParticipantId> {
+        if self.blamed.is_empty() {
+            self.awaiting()
+        } else {
+            self.blamed.clone()
+        }
+    }
+
+    /// returns the finalized transaction, once the round is complete.
+    pub fn transaction(&self) -> Option<&RingCtTransaction> {
+        self.transaction.as_ref()
+    }
+
+    fn awaiting(&self) -> BTreeSet<ParticipantId> {
+        match self.phase {
+            Phase::Inputs => self
+                .registrations
+                .iter()
+                .filter(|(_, r)| r.inputs.is_empty())
+                .map(|(id, _)| *id)
+                .collect(),
+            Phase::Outputs(idx) => BTreeSet::from([self.output_order[idx]]),
+            Phase::Signing => self
+                .registrations
+                .iter()
+                .filter(|(_, r)| !r.signed)
+                .map(|(id, _)| *id)
+                .collect(),
+            Phase::Complete | Phase::Aborted => Default::default(),
+        }
+    }
+
+    // blames the participant for an invalid message, and aborts the round.
+    fn misbehaved<T>(&mut self, from: ParticipantId, err: Error) -> Result<T> {
+        self.blamed.insert(from);
+        self.phase = Phase::Aborted;
+        Err(Error::CoinJoinParticipantMisbehaved(Box::new(err)))
+    }
+
+    fn handle_inputs(
+        &mut self,
+        from: ParticipantId,
+        mlsags: Vec<MlsagSignature>,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Vec<(ParticipantId, CoordinatorMessage)>> {
+        if mlsags.is_empty() {
+            return self.misbehaved(from, bls_ringct::Error::TransactionMustHaveAnInput.into());
+        }
+
+        let (key_images_unique, public_keys_unique) = {
+            let all: Vec<&MlsagSignature> = self
+                .registrations
+                .values()
+                .flat_map(|r| r.inputs.iter())
+                .chain(mlsags.iter())
+                .collect();
+            let key_images: BTreeSet<[u8; 48]> =
+                all.iter().map(|m| m.key_image.to_compressed()).collect();
+            let public_keys: Vec<[u8; 48]> = all
+                .iter()
+                .flat_map(|m| m.public_keys())
+                .map(|pk| pk.to_compressed())
+                .collect();
+            (
+                key_images.len() == all.len(),
+                public_keys.iter().collect::<BTreeSet<_>>().len() == public_keys.len(),
+            )
+        };
+        if !key_images_unique {
+            return self.misbehaved(
+                from,
+                bls_ringct::Error::KeyImageNotUniqueAcrossInputs.into(),
+            );
+        }
+        // note: participants choose decoys independently, so they may collide.
+        // This is not misbehaviour, and the participant may resend its inputs
+        // with other decoys.
+        if !public_keys_unique {
+            return Err(bls_ringct::Error::PublicKeyNotUniqueAcrossInputs.into());
+        }
+
+        if let Some(r) = self.registrations.get_mut(&from) {
+            r.inputs = mlsags;
+        }
+        if !self.awaiting().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut mlsags: Vec<MlsagSignature> = self
+            .registrations
+            .values()
+            .flat_map(|r| r.inputs.iter().cloned())
+            .collect();
+        mlsags.shuffle(&mut rng);
+        self.proposal.mlsags = mlsags;
+        self.phase = Phase::Outputs(0);
+
+        Ok(vec![(
+            self.output_order[0],
+            CoordinatorMessage::AddOutputs(self.proposal.clone()),
+        )])
+    }
+
+    fn handle_outputs(
+        &mut self,
+        from: ParticipantId,
+        proposal: TransactionProposal,
+    ) -> Result<Vec<(ParticipantId, CoordinatorMessage)>> {
+        if let Err(err) = self.verify_outputs(from, &proposal) {
+            return self.misbehaved(from, err);
+        }
+        self.proposal = proposal;
+
+        let next = match self.phase {
+            Phase::Outputs(idx) => idx + 1,
+            _ => return Err(Error::CoinJoinUnexpectedMessage),
+        };
+        if let Some(id) = self.output_order.get(next) {
+            self.phase = Phase::Outputs(next);
+            return Ok(vec![(
+                *id,
+                CoordinatorMessage::AddOutputs(self.proposal.clone()),
+            )]);
+        }
+
+        self.phase = Phase::Signing;
+        Ok(self
+            .registrations
+            .keys()
+            .map(|id| (*id, CoordinatorMessage::Sign(self.proposal.clone())))
+            .collect())
+    }
+
+    // the participant must only append outputs, with valid range proofs,
+    // which balance its inputs up to its blinding offset.
+    fn verify_outputs(&self, from: ParticipantId, proposal: &TransactionProposal) -> Result<()> {
+        let prior = &self.proposal.outputs;
+        if proposal.mlsags != self.proposal.mlsags
+            || !proposal.signed.is_empty()
+            || proposal.outputs.len() <= prior.len()
+            || proposal.outputs[..prior.len()] != prior[..]
+        {
+            return Err(Error::TransactionProposalMismatch);
+        }
+
+        let public_keys: BTreeSet<[u8; 48]> = proposal
+            .outputs
+            .iter()
+            .map(|o| o.public_key().to_compressed())
+            .collect();
+        if public_keys.len() != proposal.outputs.len() {
+            return Err(Error::PublicKeyNotUniqueAcrossOutputs);
+        }
+
+        range_proof_transcript(&proposal.outputs)?;
+
+        let registration = &self.registrations[&from];
+        let output_sum = proposal.outputs[prior.len()..]
+            .iter()
+            .fold(G1Projective::identity(), |sum, o| sum + o.commitment());
+        let pseudo_sum = registration
+            .inputs
+            .iter()
+            .fold(G1Projective::identity(), |sum, m| {
+                sum + m.pseudo_commitment()
+            });
+        if output_sum - pseudo_sum
+            != PedersenGens::default().B_blinding * registration.blinding_offset
+        {
+            return Err(
+                bls_ringct::Error::InputPseudoCommitmentsDoNotSumToOutputCommitments.into(),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn handle_signatures(
+        &mut self,
+        from: ParticipantId,
+        mlsags: Vec<MlsagSignature>,
+    ) -> Result<Vec<(ParticipantId, CoordinatorMessage)>> {
+        let indexes = match self.verify_signatures(from, &mlsags) {
+            Ok(indexes) => indexes,
+            Err(err) => return self.misbehaved(from, err),
+        };
+        for (idx, mlsag) in indexes.into_iter().zip(mlsags) {
+            self.proposal.signed.insert(mlsag.key_image.into());
+            self.proposal.mlsags[idx] = mlsag;
+        }
+        if let Some(r) = self.registrations.get_mut(&from) {
+            r.signed = true;
+        }
+        if !self.awaiting().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let transaction = self.proposal.clone().finalize()?;
+        self.transaction = Some(transaction.clone());
+        self.phase = Phase::Complete;
+        Ok(self
+            .registrations
+            .keys()
+            .map(|id| (*id, CoordinatorMessage::Complete(transaction.clone())))
+            .collect())
+    }
+
+    // each of the participant's inputs must be signed exactly once, leaving
+    // the ring and pseudo-commitment unaltered.  Returns the index of each
+    // signed input in the proposal.
+    fn verify_signatures(
+        &self,
+        from: ParticipantId,
+        mlsags: &[MlsagSignature],
+    ) -> Result<Vec<usize>> {
+        let inputs = &self.registrations[&from].inputs;
+        let key_images: BTreeSet<[u8; 48]> =
+            mlsags.iter().map(|m| m.key_image.to_compressed()).collect();
+        if mlsags.len() != inputs.len() || key_images.len() != inputs.len() {
+            return Err(Error::TransactionProposalMismatch);
+        }
+
+        let msg = self.proposal.transaction().gen_message();
+        mlsags
+            .iter()
+            .map(|mlsag| {
+                if !inputs.iter().any(|i| i.key_image == mlsag.key_image) {
+                    return Err(Error::TransactionProposalMismatch);
+                }
+                let idx = self
+                    .proposal
+                    .mlsags
+                    .iter()
+                    .position(|m| m.key_image == mlsag.key_image)
+                    .ok_or(Error::TransactionProposalMismatch)?;
+                let proposed = &self.proposal.mlsags[idx];
+                if proposed.ring != mlsag.ring
+                    || proposed.pseudo_commitment != mlsag.pseudo_commitment
+                {
+                    return Err(Error::TransactionProposalMismatch);
+                }
+                mlsag.verify(&msg, &public_commitments(mlsag))?;
+                Ok(idx)
+            })
+            .collect()
+    }
+}
+
+/// A participant in a CoinJoin round.  see CoinJoinCoordinator.
+///
+/// The participant's inputs and outputs are built with a TransactionBuilder
+/// and TransactionBuilder::build_proposal_party().
+#[derive(Debug, Clone)]
+pub struct CoinJoinParticipant {
+    party: ProposalParty,
+    transaction: Option<RingCtTransaction>,
+}
+
+impl CoinJoinParticipant {
+    /// create a participant from its ProposalParty.
+    pub fn new(party: ProposalParty) -> Self {
+        Self {
+            party,
+            transaction: None,
+        }
+    }
+
+    /// handle a message from the coordinator, returning the reply, if any.
+    pub fn handle_message(
+        &mut self,
+        msg: CoordinatorMessage,
+        rng: impl RngCore + CryptoRng,
+    ) -> Result<Option<ParticipantMessage>> {
+        let reply = match msg {
+            CoordinatorMessage::Invite { blinding_offset } => {
+                self.party.set_blinding_offset(blinding_offset);
+                Some(ParticipantMessage::Inputs(
+                    self.party.unsigned_mlsags().to_vec(),
+                ))
+            }
+            CoordinatorMessage::AddOutputs(proposal) => Some(ParticipantMessage::Outputs(
+                self.party.add_outputs(proposal, rng)?,
+            )),
+            CoordinatorMessage::Sign(proposal) => {
+                let key_images = self.party.key_images();
+                let signatures = self
+                    .party
+                    .sign(proposal)?
+                    .mlsags
+                    .into_iter()
+                    .filter(|m| key_images.contains(&m.key_image.into()))
+                    .collect();
+                Some(ParticipantMessage::Signatures(signatures))
+            }
+            CoordinatorMessage::Complete(transaction) => {
+                self.transaction = Some(transaction);
+                None
+            }
+        };
+        Ok(reply)
+    }
+
+    /// returns a DbcBuilder for this participant's outputs, once the round
+    /// is complete.
+    pub fn dbc_builder(&self) -> Result<DbcBuilder> {
+        let transaction = self.transaction.clone().ok_or(Error::CoinJoinIncomplete)?;
+        Ok(self.party.dbc_builder(transaction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::spend_genesis,
+        mock,
+        rand::{distributions::Standard, Rng},
+        Dbc, Owner, OwnerOnce, Token, TransactionBuilder,
+    };
+    use blsttc::SecretKey;
+    use serde::de::DeserializeOwned;
+    use std::collections::VecDeque;
+
+    // messages are passed in serialized form.
+    fn pass<T: Serialize + DeserializeOwned>(msg: &T) -> Result<T> {
+        let bytes =
+            bincode::serialize(msg).map_err(|e| Error::HexSerializationFailed(e.to_string()))?;
+        bincode::deserialize(&bytes).map_err(|e| Error::HexDeserializationFailed(e.to_string()))
+    }
+
+    // delivers messages until the round completes or stalls.  Messages for
+    // which drop() returns true are lost, as if the participant aborted.
+    fn run_round(
+        coordinator: &mut CoinJoinCoordinator,
+        participants: &mut BTreeMap<ParticipantId, CoinJoinParticipant>,
+        drop: impl Fn(ParticipantId, &CoordinatorMessage) -> bool,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<()> {
+        let mut queue: VecDeque<_> = coordinator.invitations().into();
+        while let Some((id, msg)) = queue.pop_front() {
+            if drop(id, &msg) {
+                continue;
+            }
+            let participant = participants.get_mut(&id).ok_or(Error::PublicKeyNotFound)?;
+            if let Some(reply) = participant.handle_message(pass(&msg)?, &mut rng)? {
+                queue.extend(coordinator.handle_message(id, pass(&reply)?, &mut rng)?);
+            }
+        }
+        Ok(())
+    }
+
+    // gives each owner a Dbc of the given amount.
+    fn fund(
+        amounts: &[(u64, &SecretKey)],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(mock::SpentBookNode, Vec<Dbc>)> {
+        let (spentbook_node, outputs) = spend_genesis(rng, |mut tx_builder, rng| {
+            for (amount, sk) in amounts.iter() {
+                tx_builder = tx_builder.add_output_by_amount(
+                    Token::from_nano(*amount),
+                    OwnerOnce::from_owner_base(Owner::from(sk.public_key()), rng),
+                );
+            }
+            Ok(tx_builder)
+        })?;
+        let dbcs = outputs.into_iter().map(|(dbc, ..)| dbc).collect::<Vec<_>>();
+
+        let owned = amounts
+            .iter()
+            .map(|(_, sk)| {
+                dbcs.iter()
+                    .find(|dbc| dbc.owner_base().public_key() == sk.public_key())
+                    .cloned()
+                    .ok_or(Error::PublicKeyNotFound)
+            })
+            .collect::<Result<_>>()?;
+        Ok((spentbook_node, owned))
+    }
+
+    // each participant pays `payment` to a new owner, with change.
+    fn participant(
+        dbc: &Dbc,
+        sk: &SecretKey,
+        payee: &SecretKey,
+        payment: u64,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<CoinJoinParticipant> {
+        let party = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .add_input_dbc(dbc, sk)?
+            .add_output_by_amount(
+                Token::from_nano(payment),
+                OwnerOnce::from_owner_base(Owner::from(payee.public_key()), &mut *rng),
+            )
+            .set_change_owner(OwnerOnce::from_owner_base(
+                Owner::from(sk.public_key()),
+                &mut *rng,
+            ))
+            .build_proposal_party(&mut *rng)?;
+        Ok(CoinJoinParticipant::new(party))
+    }
+
+    #[test]
+    fn participants_join_inputs_and_outputs() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let sks: Vec<SecretKey> = (0..3).map(|_| rng.sample(Standard)).collect();
+        let payees: Vec<SecretKey> = (0..3).map(|_| rng.sample(Standard)).collect();
+        let (mut spentbook_node, dbcs) =
+            fund(&[(1000, &sks[0]), (700, &sks[1]), (300, &sks[2])], &mut rng)?;
+
+        let mut participants = BTreeMap::new();
+        for (i, payment) in [100, 100, 100].iter().enumerate() {
+            participants.insert(
+                i as ParticipantId,
+                participant(&dbcs[i], &sks[i], &payees[i], *payment, &mut rng)?,
+            );
+        }
+
+        let mut coordinator = CoinJoinCoordinator::new(participants.keys().copied(), &mut rng)?;
+        run_round(&mut coordinator, &mut participants, |_, _| false, &mut rng)?;
+        assert!(coordinator.blame().is_empty());
+
+        let transaction = coordinator
+            .transaction()
+            .cloned()
+            .ok_or(Error::CoinJoinIncomplete)?;
+        assert_eq!(transaction.mlsags.len(), 3);
+        assert_eq!(transaction.outputs.len(), 6);
+
+        let mut spent_proof_shares = Vec::new();
+        for key_image in transaction.mlsags.iter().map(|m| m.key_image.into()) {
+            spent_proof_shares.push(spentbook_node.log_spent(key_image, transaction.clone())?);
+        }
+        for (i, participant) in participants.values().enumerate() {
+            let outputs = participant
+                .dbc_builder()?
+                .add_spent_proof_shares(spent_proof_shares.clone())
+                .add_spent_transaction(transaction.clone())
+                .build(&spentbook_node.key_manager)?;
+            assert_eq!(outputs.len(), 2);
+
+            let (payment, ..) = outputs
+                .iter()
+                .find(|(dbc, ..)| dbc.owner_base().public_key() == payees[i].public_key())
+                .ok_or(Error::PublicKeyNotFound)?;
+            assert_eq!(
+                payment.amount_secrets(&payees[i])?.amount(),
+                Token::from_nano(100)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn participants_that_abort_are_blamed() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let sks: Vec<SecretKey> = (0..3).map(|_| rng.sample(Standard)).collect();
+        let payee: SecretKey = rng.sample(Standard);
+        let (_spentbook_node, dbcs) =
+            fund(&[(1000, &sks[0]), (700, &sks[1]), (300, &sks[2])], &mut rng)?;
+
+        let new_participants = |rng: &mut crate::rand::rngs::StdRng| {
+            (0..3)
+                .map(|i| {
+                    let party = participant(&dbcs[i], &sks[i], &payee, 100, rng)?;
+                    Ok((i as ParticipantId, party))
+                })
+                .collect::<Result<BTreeMap<_, _>>>()
+        };
+
+        assert_eq!(
+            CoinJoinCoordinator::new([7], &mut rng).err(),
+            Some(Error::CoinJoinTooFewParticipants)
+        );
+
+        // participant 2 goes silent when asked to sign.
+        let mut participants = new_participants(&mut rng)?;
+        let mut coordinator = CoinJoinCoordinator::new(participants.keys().copied(), &mut rng)?;
+        run_round(
+            &mut coordinator,
+            &mut participants,
+            |id, msg| id == 2 && matches!(msg, CoordinatorMessage::Sign(_)),
+            &mut rng,
+        )?;
+        assert!(coordinator.transaction().is_none());
+        assert_eq!(coordinator.blame(), BTreeSet::from([2]));
+        assert_eq!(
+            participants[&0].dbc_builder().err(),
+            Some(Error::CoinJoinIncomplete)
+        );
+
+        // participant 1 returns the proposal without its outputs.
+        let mut participants = new_participants(&mut rng)?;
+        let mut coordinator = CoinJoinCoordinator::new(participants.keys().copied(), &mut rng)?;
+        let mut queue: VecDeque<_> = coordinator.invitations().into();
+        let mut result = Ok(vec![]);
+        while let Some((id, msg)) = queue.pop_front() {
+            let reply = match (id, &msg) {
+                (1, CoordinatorMessage::AddOutputs(proposal)) => {
+                    Some(ParticipantMessage::Outputs(proposal.clone()))
+                }
+                _ => participants
+                    .get_mut(&id)
+                    .ok_or(Error::PublicKeyNotFound)?
+                    .handle_message(msg, &mut rng)?,
+            };
+            if let Some(reply) = reply {
+                result = coordinator.handle_message(id, reply, &mut rng);
+                match &result {
+                    Ok(msgs) => queue.extend(msgs.clone()),
+                    Err(_) => break,
+                }
+            }
+        }
+        assert_eq!(
+            result.err(),
+            Some(Error::CoinJoinParticipantMisbehaved(Box::new(
+                Error::TransactionProposalMismatch
+            )))
+        );
+        assert_eq!(coordinator.blame(), BTreeSet::from([1]));
+
+        // the round is over.
+        assert_eq!(
+            coordinator
+                .handle_message(0, ParticipantMessage::Inputs(vec![]), &mut rng)
+                .err(),
+            Some(Error::CoinJoinRoundAborted)
+        );
+
+        Ok(())
+    }
+}
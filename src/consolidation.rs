@@ -0,0 +1,288 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{CryptoRng, RngCore},
+    Dbc, KeyImage, Owner, OwnerOnce, Result, SpentBookClient, SpentProofKeyVerifier, Token,
+    TransactionBuilder, TransactionEstimate, Wallet,
+};
+use blsttc::SecretKey;
+
+/// Plans and performs the consolidation of many small Dbcs into fewer,
+/// larger ones.
+///
+/// The Dbcs are grouped, smallest first, into transactions that each have
+/// at most <max_inputs> inputs and, optionally, output Dbcs no larger than
+/// <max_dbc_size> bytes, as estimated by TransactionEstimate.  Each
+/// transaction pays the sum of its inputs, less the fee, to a single output.
+///
+/// Each round of consolidation reduces the number of Dbcs by up to a factor
+/// of <max_inputs>.  Consolidate again to merge the outputs of the last.
+#[derive(Debug, Clone)]
+pub struct ConsolidationPlanner {
+    max_inputs: usize,
+    max_dbc_size: Option<usize>,
+    decoys_per_input: usize,
+    fee: Token,
+}
+
+impl Default for ConsolidationPlanner {
+    fn default() -> Self {
+        Self {
+            max_inputs: 16,
+            max_dbc_size: None,
+            decoys_per_input: 10, // as TransactionBuilder.
+            fee: Token::zero(),
+        }
+    }
+}
+
+impl ConsolidationPlanner {
+    /// set max_inputs option.  The most inputs of each transaction.
+    pub fn set_max_inputs(mut self, max_inputs: usize) -> Self {
+        self.max_inputs = max_inputs;
+        self
+    }
+
+    /// set max_dbc_size option.  The largest estimated size of each
+    /// output Dbc, in bytes.
+    pub fn set_max_dbc_size(mut self, max_dbc_size: usize) -> Self {
+        self.max_dbc_size = Some(max_dbc_size);
+        self
+    }
+
+    /// set decoys_per_input option.
+    pub fn set_decoys_per_input(mut self, decoys_per_input: usize) -> Self {
+        self.decoys_per_input = decoys_per_input;
+        self
+    }
+
+    /// set fee option.  The fee paid by each transaction.
+    pub fn set_fee(mut self, fee: Token) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// group Dbcs into the inputs of each consolidation transaction, whose
+    /// output will be owned by owner_base.
+    ///
+    /// Dbcs that would be alone in a transaction, or whose transaction
+    /// would not cover the fee, are left out of the plan.
+    pub fn plan(
+        &self,
+        dbcs: Vec<(Dbc, SecretKey)>,
+        owner_base: &Owner,
+    ) -> Result<Vec<Vec<(Dbc, SecretKey)>>> {
+        let mut candidates = dbcs
+            .into_iter()
+            .map(|(dbc, base_sk)| Ok((dbc.amount_secrets(&base_sk)?.amount(), dbc, base_sk)))
+            .collect::<Result<Vec<_>>>()?;
+        candidates.sort_by_key(|(amount, ..)| *amount);
+
+        let mut batches = vec![];
+        let mut batch = vec![];
+        for candidate in candidates {
+            if !self.fits(batch.len() + 1, owner_base) {
+                batches.push(std::mem::take(&mut batch));
+            }
+            batch.push(candidate);
+        }
+        batches.push(batch);
+
+        Ok(batches
+            .into_iter()
+            .filter(|batch| {
+                let sum = batch
+                    .iter()
+                    .try_fold(Token::zero(), |sum, (amount, ..)| sum.checked_add(*amount));
+                // note: a batch whose sum overflows is kept, for the
+                // TransactionBuilder to report.
+                batch.len() > 1 && sum.map(|sum| sum > self.fee).unwrap_or(true)
+            })
+            .map(|batch| {
+                batch
+                    .into_iter()
+                    .map(|(_, dbc, base_sk)| (dbc, base_sk))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// consolidate the spendable Dbcs of a wallet, as planned by ::plan().
+    ///
+    /// Each transaction is built with decoys from the client, its inputs
+    /// are logged as spent with the client, and its output Dbc is verified
+    /// and added to the wallet, owned by base_sk.  The output Dbcs are
+    /// returned in the order of the transactions.
+    ///
+    /// If a transaction fails, the error is returned, and the outputs of
+    /// earlier transactions are kept.  Its inputs are returned to confirmed
+    /// if none was logged as spent, and are otherwise left pending.  If its
+    /// inputs were all logged but its output Dbc could not be built, the
+    /// DbcBuilder is kept in the wallet, see Wallet::take_unbuilt().
+    pub fn consolidate<C: SpentBookClient, K: SpentProofKeyVerifier>(
+        &self,
+        wallet: &mut Wallet,
+        base_sk: &SecretKey,
+        client: &mut C,
+        verifier: &K,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Vec<Dbc>> {
+        let owner_base = Owner::from(base_sk.public_key());
+
+        let mut consolidated = vec![];
+        for batch in self.plan(wallet.spendable_dbcs(), &owner_base)? {
+            let mut tx_builder = TransactionBuilder::default()
+                .set_decoys_per_input(self.decoys_per_input)
+                .set_fee(self.fee)
+                .set_change_owner(OwnerOnce::from_owner_base(owner_base.clone(), &mut rng));
+            let mut key_images: Vec<KeyImage> = vec![];
+            for (dbc, input_sk) in batch.iter() {
+                key_images.push(dbc.key_image(input_sk)?);
+                tx_builder = tx_builder.add_input_dbc(dbc, input_sk)?;
+            }
+
+            for (dbc, ..) in wallet.send(tx_builder, key_images, client, verifier, &mut rng)? {
+                wallet.add_dbc(dbc.clone(), base_sk)?;
+                consolidated.push(dbc);
+            }
+        }
+        Ok(consolidated)
+    }
+
+    // true if a transaction with num_inputs is within the limits.
+    fn fits(&self, num_inputs: usize, owner_base: &Owner) -> bool {
+        if num_inputs > self.max_inputs {
+            return false;
+        }
+        match self.max_dbc_size {
+            Some(max_dbc_size) => {
+                let ring_sizes = vec![1 + self.decoys_per_input; num_inputs];
+                let num_outputs = if self.fee > Token::zero() { 2 } else { 1 };
+                let estimate =
+                    TransactionEstimate::new(&ring_sizes, num_outputs, vec![(owner_base, None)]);
+                estimate.max_dbc_size() <= max_dbc_size
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::pay_from_genesis,
+        mock,
+        rand::{distributions::Standard, Rng},
+        DbcStatus,
+    };
+
+    // pays genesis to a wallet as Dbcs of the given amounts, owned by the
+    // returned SecretKey.  The genesis change is not added to the wallet.
+    fn wallet_of_amounts(
+        amounts: &[u64],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(mock::SpentBookNode, Wallet, SecretKey)> {
+        let base_sk: SecretKey = rng.sample(Standard);
+        let (spentbook_node, dbcs) =
+            pay_from_genesis(amounts, &Owner::from(base_sk.public_key()), rng)?;
+
+        let mut wallet = Wallet::default();
+        for dbc in dbcs {
+            wallet.add_dbc(dbc, &base_sk)?;
+        }
+        Ok((spentbook_node, wallet, base_sk))
+    }
+
+    #[test]
+    fn plan_respects_limits() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (_spentbook_node, wallet, base_sk) =
+            wallet_of_amounts(&[70, 10, 50, 20, 60, 30, 40], &mut rng)?;
+        let owner_base = Owner::from(base_sk.public_key());
+        let amounts = |plan: Vec<Vec<(Dbc, SecretKey)>>| -> Result<Vec<Vec<u64>>> {
+            plan.iter()
+                .map(|batch| {
+                    batch
+                        .iter()
+                        .map(|(dbc, sk)| Ok(dbc.amount_secrets(sk)?.amount().as_nano()))
+                        .collect()
+                })
+                .collect()
+        };
+
+        // smallest first, leaving out the last Dbc, which would be alone.
+        let planner = ConsolidationPlanner::default().set_max_inputs(3);
+        let plan = planner.plan(wallet.spendable_dbcs(), &owner_base)?;
+        assert_eq!(amounts(plan)?, vec![vec![10, 20, 30], vec![40, 50, 60]]);
+
+        // a size limit that allows 2 inputs, but not 3.
+        let two_inputs = TransactionEstimate::new(&[11, 11], 1, vec![(&owner_base, None)]);
+        let planner = ConsolidationPlanner::default().set_max_dbc_size(two_inputs.max_dbc_size());
+        let plan = planner.plan(wallet.spendable_dbcs(), &owner_base)?;
+        assert_eq!(
+            amounts(plan)?,
+            vec![vec![10, 20], vec![30, 40], vec![50, 60]]
+        );
+
+        // a transaction that would not cover the fee is left out.
+        let planner = ConsolidationPlanner::default()
+            .set_max_inputs(2)
+            .set_fee(Token::from_nano(30));
+        let plan = planner.plan(wallet.spendable_dbcs(), &owner_base)?;
+        assert_eq!(amounts(plan)?, vec![vec![30, 40], vec![50, 60]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn consolidate_wallet() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (mut spentbook_node, mut wallet, base_sk) =
+            wallet_of_amounts(&[70, 10, 50, 20, 60, 30, 40], &mut rng)?;
+        let verifier = spentbook_node.key_manager.clone();
+
+        let consolidated = ConsolidationPlanner::default()
+            .set_max_inputs(3)
+            .set_decoys_per_input(1)
+            .consolidate(
+                &mut wallet,
+                &base_sk,
+                &mut spentbook_node,
+                &verifier,
+                &mut rng,
+            )?;
+
+        let amounts: Vec<u64> = consolidated
+            .iter()
+            .map(|dbc| Ok(dbc.amount_secrets(&base_sk)?.amount().as_nano()))
+            .collect::<Result<_>>()?;
+        assert_eq!(amounts, vec![60, 150]);
+        assert_eq!(wallet.iter_by_status(DbcStatus::Spent).count(), 6);
+        assert_eq!(wallet.spendable_dbcs().len(), 3);
+        assert_eq!(wallet.balance_confirmed()?, Token::from_nano(280));
+
+        // a second round consolidates the rest.
+        let consolidated = ConsolidationPlanner::default()
+            .set_max_inputs(3)
+            .set_decoys_per_input(1)
+            .consolidate(
+                &mut wallet,
+                &base_sk,
+                &mut spentbook_node,
+                &verifier,
+                &mut rng,
+            )?;
+        assert_eq!(consolidated.len(), 1);
+        assert_eq!(wallet.spendable_dbcs().len(), 1);
+        assert_eq!(wallet.balance_confirmed()?, Token::from_nano(280));
+
+        Ok(())
+    }
+}
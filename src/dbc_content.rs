@@ -6,8 +6,13 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{AmountSecrets, DerivationIndex, Owner};
-use blsttc::{Ciphertext, SecretKey};
+use crate::{
+    rand::{rngs::OsRng, CryptoRng, RngCore},
+    AmountSecrets, DbcMetadata, DerivationIndex, Owner,
+};
+use blsttc::{Ciphertext, DecryptionShare, IntoFr, PublicKeySet, SecretKey};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use tiny_keccak::{Hasher, Sha3};
 
 #[cfg(feature = "serde")]
@@ -38,16 +43,22 @@ pub struct DbcContent {
     /// This indicates which index to use when deriving the "real" owner key from
     /// the owner_base.
     ///
-    /// This index is stored in encrypted form, and is encrypted to owner_base.public_key().
-    /// So the true owner is unknown to anyone not in posession of owner_base.secret_key().
+    /// This index is stored in encrypted form, and is encrypted to owner_base.view_public_key(),
+    /// which is owner_base.public_key() for all but SplitKey owners.  So the true owner is
+    /// unknown to anyone not in posession of the corresponding (view) secret key.
     pub owner_derivation_cipher: Ciphertext,
 
-    /// This is the AmountSecrets (aka RevealedCommitment) encypted to the derived public key,
-    /// which can be obtained via:
-    ///   self.owner_base.derive(
-    ///     self.owner_base.secret_key().decrypt(self.owner_derivation.cipher()
-    ///   ).public_key()
+    /// This is the AmountSecrets (aka RevealedCommitment) encypted to the derived view
+    /// public key, which can be obtained via:
+    ///   owner_base.view_public_key().derive_child(
+    ///     view_sk.decrypt(self.owner_derivation_cipher)
+    ///   )
+    /// For all but SplitKey owners, this is the derived (one-time-use) public key.
     pub amount_secrets_cipher: Ciphertext,
+
+    /// This is the optional DbcMetadata, encrypted to the derived view public key in the
+    /// same manner as amount_secrets_cipher.
+    pub metadata_cipher: Option<Ciphertext>,
 }
 
 /// Represents the content of a DBC.
@@ -59,6 +70,7 @@ impl From<(Owner, Ciphertext, Ciphertext)> for DbcContent {
             owner_base,
             owner_derivation_cipher,
             amount_secrets_cipher,
+            metadata_cipher: None,
         }
     }
 }
@@ -68,32 +80,103 @@ impl From<(Owner, DerivationIndex, AmountSecrets)> for DbcContent {
     // Create a new DbcContent for signing.
     fn from(params: (Owner, DerivationIndex, AmountSecrets)) -> Self {
         let (owner_base, derivation_index, amount_secrets) = params;
+        Self::from((owner_base, derivation_index, amount_secrets, None))
+    }
+}
+
+/// Represents the content of a DBC, with optional metadata.
+impl From<(Owner, DerivationIndex, AmountSecrets, Option<DbcMetadata>)> for DbcContent {
+    // Create a new DbcContent for signing.
+    fn from(params: (Owner, DerivationIndex, AmountSecrets, Option<DbcMetadata>)) -> Self {
+        let (owner_base, derivation_index, amount_secrets, metadata) = params;
+        Self::new(
+            owner_base,
+            derivation_index,
+            amount_secrets,
+            metadata,
+            &mut OsRng,
+        )
+    }
+}
 
-        let owner_derivation_cipher = owner_base.public_key().encrypt(&derivation_index);
-        let amount_secrets_cipher = owner_base
-            .derive(&derivation_index)
-            .public_key()
-            .encrypt(&amount_secrets.to_bytes());
+impl DbcContent {
+    /// Create a new DbcContent for signing, encrypting its secrets with
+    /// randomness from rng.
+    pub fn new(
+        owner_base: Owner,
+        derivation_index: DerivationIndex,
+        amount_secrets: AmountSecrets,
+        metadata: Option<DbcMetadata>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        // note: for all but SplitKey owners, the view key is the base key, so
+        // the amount secrets are encrypted to the one-time-use public key.
+        let view_pk = owner_base.view_public_key();
+        let owner_derivation_cipher = view_pk.encrypt_with_rng(rng, derivation_index);
+        let derived_view_pk = view_pk.derive_child(&derivation_index);
+        let amount_secrets_cipher =
+            derived_view_pk.encrypt_with_rng(rng, amount_secrets.to_bytes());
+        let metadata_cipher = metadata.map(|m| derived_view_pk.encrypt_with_rng(rng, m.to_bytes()));
 
         Self {
             owner_base,
             owner_derivation_cipher,
             amount_secrets_cipher,
+            metadata_cipher,
         }
     }
-}
 
-impl DbcContent {
-    pub(crate) fn derivation_index(&self, base_sk: &SecretKey) -> Result<DerivationIndex> {
-        let bytes = base_sk
+    // note: view_sk is the base SecretKey for all but SplitKey owners.
+    // see Owner::view_secret_key()
+    pub(crate) fn derivation_index(&self, view_sk: &SecretKey) -> Result<DerivationIndex> {
+        let bytes = view_sk
             .decrypt(&self.owner_derivation_cipher)
             .ok_or(Error::DecryptionBySecretKeyFailed)?;
 
-        assert_eq!(bytes.len(), 32);
+        derivation_index_from_bytes(&bytes)
+    }
+
+    // note: public_key_set is the base PublicKeySet of a Multisig owner.
+    pub(crate) fn derivation_index_by_shares<I: IntoFr + Ord>(
+        &self,
+        public_key_set: &PublicKeySet,
+        decryption_shares: &BTreeMap<I, DecryptionShare>,
+    ) -> Result<DerivationIndex> {
+        let bytes = public_key_set.decrypt(decryption_shares, &self.owner_derivation_cipher)?;
+        derivation_index_from_bytes(&bytes)
+    }
+
+    pub(crate) fn amount_secrets(&self, view_sk: &SecretKey) -> Result<AmountSecrets> {
+        let derivation_index = self.derivation_index(view_sk)?;
+        AmountSecrets::try_from((
+            &view_sk.derive_child(&derivation_index),
+            &self.amount_secrets_cipher,
+        ))
+    }
+
+    pub(crate) fn amount_secrets_by_shares<I: IntoFr + Ord>(
+        &self,
+        public_key_set: &PublicKeySet,
+        derivation_index: &DerivationIndex,
+        decryption_shares: &BTreeMap<I, DecryptionShare>,
+    ) -> Result<AmountSecrets> {
+        AmountSecrets::try_from((
+            &public_key_set.derive_child(derivation_index),
+            decryption_shares,
+            &self.amount_secrets_cipher,
+        ))
+    }
 
-        let mut idx = [0u8; 32];
-        idx.copy_from_slice(&bytes[0..32]);
-        Ok(idx)
+    pub(crate) fn metadata(&self, view_sk: &SecretKey) -> Result<Option<DbcMetadata>> {
+        match &self.metadata_cipher {
+            Some(cipher) => {
+                let derivation_index = self.derivation_index(view_sk)?;
+                let metadata =
+                    DbcMetadata::try_from((&view_sk.derive_child(&derivation_index), cipher))?;
+                Ok(Some(metadata))
+            }
+            None => Ok(None),
+        }
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
@@ -102,6 +185,11 @@ impl DbcContent {
         bytes.extend(&self.owner_base.to_bytes());
         bytes.extend(&self.owner_derivation_cipher.to_bytes());
         bytes.extend(&self.amount_secrets_cipher.to_bytes());
+        // note: absent metadata adds no bytes, so the hash of a DbcContent
+        // without metadata is unaffected by the field's existence.
+        if let Some(cipher) = &self.metadata_cipher {
+            bytes.extend(&cipher.to_bytes());
+        }
 
         bytes
     }
@@ -116,3 +204,15 @@ impl DbcContent {
         Hash::hash(&hash)
     }
 }
+
+// note: a cipher that decrypts to other than 32 bytes was not formed by
+// DbcContent::new(), so is treated as a failed decryption.
+fn derivation_index_from_bytes(bytes: &[u8]) -> Result<DerivationIndex> {
+    if bytes.len() != 32 {
+        return Err(Error::DecryptionBySecretKeyFailed);
+    }
+
+    let mut idx = [0u8; 32];
+    idx.copy_from_slice(&bytes[0..32]);
+    Ok(idx)
+}
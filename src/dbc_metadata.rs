@@ -0,0 +1,142 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, PublicKey, Result};
+use blsttc::{Ciphertext, SecretKey};
+use std::convert::TryFrom;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Optional sender-supplied metadata that accompanies a Dbc.
+///
+/// It is stored in DbcContent encrypted to the one-time-use (derived) owner
+/// view key, so only the recipient can read it.
+///
+/// important: the metadata is unauthenticated.  Neither the transaction nor
+/// the spent proofs commit to DbcContent, so anyone who handles the Dbc
+/// before the recipient (eg a relay) can replace the metadata cipher, and
+/// the Dbc still verifies.  Do not rely on it for anything that matters,
+/// eg matching a payment to an invoice, without confirming it out of band.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DbcMetadata {
+    /// a free-text note for the recipient
+    pub memo: String,
+    /// an invoice (or other payment) reference
+    pub invoice_id: String,
+    /// a base PublicKey of the sender, to which a refund may be sent
+    pub refund_to: Option<PublicKey>,
+}
+
+impl DbcMetadata {
+    /// create a DbcMetadata with the given memo and no other fields.
+    pub fn from_memo(memo: impl Into<String>) -> Self {
+        Self {
+            memo: memo.into(),
+            ..Default::default()
+        }
+    }
+
+    // strings are length prefixed, so that fields can not run into each other.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Default::default();
+        for s in [&self.memo, &self.invoice_id] {
+            bytes.extend((s.len() as u64).to_be_bytes());
+            bytes.extend(s.as_bytes());
+        }
+        if let Some(pk) = &self.refund_to {
+            bytes.extend(pk.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut rest = bytes;
+        let memo = Self::take_string(&mut rest)?;
+        let invoice_id = Self::take_string(&mut rest)?;
+        let refund_to = match rest.len() {
+            0 => None,
+            blsttc::PK_SIZE => {
+                let mut pk_bytes = [0u8; blsttc::PK_SIZE];
+                pk_bytes.copy_from_slice(rest);
+                Some(PublicKey::from_bytes(pk_bytes).map_err(|_| Error::DbcMetadataBytesInvalid)?)
+            }
+            _ => return Err(Error::DbcMetadataBytesInvalid),
+        };
+
+        Ok(Self {
+            memo,
+            invoice_id,
+            refund_to,
+        })
+    }
+
+    fn take_string(bytes: &mut &[u8]) -> Result<String> {
+        if bytes.len() < 8 {
+            return Err(Error::DbcMetadataBytesInvalid);
+        }
+        let (len_bytes, rest) = bytes.split_at(8);
+        let mut len = [0u8; 8];
+        len.copy_from_slice(len_bytes);
+        let len = u64::from_be_bytes(len) as usize;
+        if rest.len() < len {
+            return Err(Error::DbcMetadataBytesInvalid);
+        }
+        let (s, rest) = rest.split_at(len);
+        *bytes = rest;
+        String::from_utf8(s.to_vec()).map_err(|_| Error::DbcMetadataBytesInvalid)
+    }
+}
+
+/// Decrypt DbcMetadata ciphertext using the one-time-use (derived) SecretKey
+impl TryFrom<(&SecretKey, &Ciphertext)> for DbcMetadata {
+    type Error = Error;
+
+    fn try_from(params: (&SecretKey, &Ciphertext)) -> Result<Self> {
+        let (secret_key, cipher) = params;
+        let bytes = secret_key
+            .decrypt(cipher)
+            .ok_or(Error::DecryptionBySecretKeyFailed)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::{distributions::Standard, Rng};
+
+    #[test]
+    fn metadata_bytes_roundtrip() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let refund_sk: SecretKey = rng.sample(Standard);
+
+        let metadata = DbcMetadata {
+            memo: "thanks for the coffee".to_string(),
+            invoice_id: "inv-7".to_string(),
+            refund_to: Some(refund_sk.public_key()),
+        };
+        assert_eq!(DbcMetadata::from_bytes(&metadata.to_bytes())?, metadata);
+
+        let memo_only = DbcMetadata::from_memo("hi");
+        assert_eq!(DbcMetadata::from_bytes(&memo_only.to_bytes())?, memo_only);
+
+        let bytes = metadata.to_bytes();
+        assert_eq!(
+            DbcMetadata::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::DbcMetadataBytesInvalid)
+        );
+        assert_eq!(
+            DbcMetadata::from_bytes(&bytes[..4]),
+            Err(Error::DbcMetadataBytesInvalid)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,425 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{seq::SliceRandom, Rng, RngCore},
+    DecoyInput, Hash,
+};
+use std::collections::BTreeSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An output that may be used as a decoy, along with what is known
+/// of its history.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct DecoyCandidate {
+    /// the output's public key and commitment
+    pub decoy: DecoyInput,
+    /// the number of transactions logged since the output was created,
+    /// if known.  The newest outputs have age 0.
+    pub age: Option<u64>,
+    /// the hash of the transaction that created the output, if known.
+    pub transaction_hash: Option<Hash>,
+}
+
+impl From<DecoyInput> for DecoyCandidate {
+    fn from(decoy: DecoyInput) -> Self {
+        Self {
+            decoy,
+            age: None,
+            transaction_hash: None,
+        }
+    }
+}
+
+/// A strategy for choosing the decoys of each ring from the available
+/// candidates.
+///
+/// Implementations must return `num_rings` rings of at most
+/// `decoys_per_ring` decoys each, and must not use a candidate in more
+/// than one ring.  A ring may be short if there are too few suitable
+/// candidates.
+///
+/// note: the order of decoys within a ring is not significant.  The
+/// TransactionBuilder shuffles each ring, and places the true input at
+/// a random position.
+pub trait DecoySelector: std::fmt::Debug {
+    /// select decoys for `num_rings` rings from `candidates`.
+    fn select(
+        &self,
+        candidates: Vec<DecoyCandidate>,
+        num_rings: usize,
+        decoys_per_ring: usize,
+        rng: &mut dyn RngCore,
+    ) -> Vec<Vec<DecoyInput>>;
+}
+
+/// A source of outputs to use as decoys, such as a SpentBook.
+///
+/// TransactionBuilder::build_with_decoy_provider() fetches decoys from a
+/// provider, and fetches again to replace any that are true inputs,
+/// duplicates, or left out by its DecoySelector.
+pub trait DecoyProvider {
+    /// fetch up to `count` randomly chosen outputs.
+    fn fetch_decoys(&self, count: usize, rng: &mut dyn RngCore) -> Vec<DecoyCandidate>;
+}
+
+/// Selects decoys uniformly at random.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformDecoySelector;
+
+/// Selects decoys weighted by age according to a gamma distribution.
+///
+/// Real spends tend to be of recent outputs, so uniformly selected decoys
+/// are older on average than the true input, which gives it away.
+/// Weighting decoys to follow the age distribution of real spends defeats
+/// this.  Candidates of unknown age are weighted as if of the most likely
+/// age.
+#[derive(Debug, Clone, Copy)]
+pub struct AgeWeightedDecoySelector {
+    /// the shape (k) of the gamma distribution.  Must be at least 1.
+    pub shape: f64,
+    /// the scale (theta) of the gamma distribution, in transactions.
+    pub scale: f64,
+}
+
+impl Default for AgeWeightedDecoySelector {
+    fn default() -> Self {
+        Self {
+            shape: 2.0,
+            scale: 100.0,
+        }
+    }
+}
+
+/// Selects decoys uniformly at random, but never two from the same
+/// transaction in one ring.
+///
+/// A ring containing several outputs of one transaction suggests that
+/// at most one of them is the true input.  Candidates whose transaction
+/// is unknown are assumed to be from distinct transactions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DistinctTransactionDecoySelector;
+
+impl DecoySelector for UniformDecoySelector {
+    fn select(
+        &self,
+        mut candidates: Vec<DecoyCandidate>,
+        num_rings: usize,
+        decoys_per_ring: usize,
+        rng: &mut dyn RngCore,
+    ) -> Vec<Vec<DecoyInput>> {
+        candidates.shuffle(rng);
+        fill_rings(candidates, num_rings, decoys_per_ring, false)
+    }
+}
+
+impl DecoySelector for AgeWeightedDecoySelector {
+    fn select(
+        &self,
+        candidates: Vec<DecoyCandidate>,
+        num_rings: usize,
+        decoys_per_ring: usize,
+        rng: &mut dyn RngCore,
+    ) -> Vec<Vec<DecoyInput>> {
+        // weighted sampling without replacement: each candidate is keyed by
+        // ln(u)/w for uniform u, and the largest keys are chosen first.
+        // (Efraimidis and Spirakis)
+        let mut keyed: Vec<(f64, DecoyCandidate)> = candidates
+            .into_iter()
+            .map(|c| {
+                let weight = self.weight(c.age);
+                let u: f64 = rng.gen_range(f64::MIN_POSITIVE..1.0);
+                (u.ln() / weight, c)
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        let ordered = keyed.into_iter().map(|(_, c)| c).collect();
+        fill_rings(ordered, num_rings, decoys_per_ring, false)
+    }
+}
+
+impl AgeWeightedDecoySelector {
+    // the gamma density at age, relative to the density at the mode.
+    // note: age is offset by one, so that the newest outputs have some weight.
+    fn weight(&self, age: Option<u64>) -> f64 {
+        let shape = self.shape.max(1.0);
+        let scale = self.scale.max(f64::MIN_POSITIVE);
+        let mode = (shape - 1.0) * scale;
+        let log_density = |x: f64| (shape - 1.0) * x.ln() - x / scale;
+
+        match age {
+            Some(age) => {
+                let x = age as f64 + 1.0;
+                let log_weight = if mode > 0.0 {
+                    log_density(x) - log_density(mode)
+                } else {
+                    -x / scale
+                };
+                log_weight.exp().max(f64::MIN_POSITIVE)
+            }
+            None => 1.0,
+        }
+    }
+}
+
+impl DecoySelector for DistinctTransactionDecoySelector {
+    fn select(
+        &self,
+        mut candidates: Vec<DecoyCandidate>,
+        num_rings: usize,
+        decoys_per_ring: usize,
+        rng: &mut dyn RngCore,
+    ) -> Vec<Vec<DecoyInput>> {
+        candidates.shuffle(rng);
+        fill_rings(candidates, num_rings, decoys_per_ring, true)
+    }
+}
+
+// fills each ring in turn with candidates in the given order of preference.
+// If distinct_transactions, a candidate is skipped for a ring which already
+// has a decoy from the same transaction, but remains available to the next.
+fn fill_rings(
+    ordered: Vec<DecoyCandidate>,
+    num_rings: usize,
+    decoys_per_ring: usize,
+    distinct_transactions: bool,
+) -> Vec<Vec<DecoyInput>> {
+    let mut remaining = ordered;
+    let mut rings = Vec::with_capacity(num_rings);
+
+    for _ in 0..num_rings {
+        let mut ring = Vec::new();
+        let mut ring_txs: BTreeSet<Hash> = Default::default();
+        let mut skipped = Vec::new();
+
+        for candidate in remaining {
+            if ring.len() == decoys_per_ring {
+                skipped.push(candidate);
+                continue;
+            }
+            match candidate.transaction_hash {
+                Some(tx_hash) if distinct_transactions && ring_txs.contains(&tx_hash) => {
+                    skipped.push(candidate);
+                }
+                tx_hash => {
+                    ring_txs.extend(tx_hash);
+                    ring.push(candidate.decoy);
+                }
+            }
+        }
+
+        rings.push(ring);
+        remaining = skipped;
+    }
+
+    rings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, Error, Owner, OwnerOnce, Result, Token, TransactionBuilder};
+    use bls_ringct::{
+        blstrs::G1Projective,
+        group::{Curve, Group},
+    };
+    use std::cell::RefCell;
+
+    // provides the given candidates in order.
+    struct SequentialProvider {
+        candidates: Vec<DecoyCandidate>,
+        next: RefCell<usize>,
+    }
+
+    impl DecoyProvider for SequentialProvider {
+        fn fetch_decoys(&self, count: usize, _rng: &mut dyn RngCore) -> Vec<DecoyCandidate> {
+            let mut next = self.next.borrow_mut();
+            let end = (*next + count).min(self.candidates.len());
+            let fetched = self.candidates[*next..end].to_vec();
+            *next = end;
+            fetched
+        }
+    }
+
+    // candidates from num_txs transactions of outputs_per_tx outputs each,
+    // with the newest transaction first.
+    fn candidates(
+        num_txs: u64,
+        outputs_per_tx: usize,
+        rng: &mut impl RngCore,
+    ) -> Vec<DecoyCandidate> {
+        (0..num_txs)
+            .flat_map(|age| {
+                let mut bytes = [0u8; 32];
+                rng.fill_bytes(&mut bytes);
+                let tx_hash = Hash::from(bytes);
+                (0..outputs_per_tx)
+                    .map(|_| DecoyCandidate {
+                        decoy: DecoyInput {
+                            public_key: G1Projective::random(&mut *rng).to_affine(),
+                            commitment: G1Projective::random(&mut *rng).to_affine(),
+                        },
+                        age: Some(age),
+                        transaction_hash: Some(tx_hash),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn public_keys(rings: &[Vec<DecoyInput>]) -> BTreeSet<[u8; 48]> {
+        rings
+            .iter()
+            .flatten()
+            .map(|d| d.public_key().to_compressed())
+            .collect()
+    }
+
+    #[test]
+    fn selectors_fill_rings_without_reuse() {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let selectors: [&dyn DecoySelector; 3] = [
+            &UniformDecoySelector,
+            &AgeWeightedDecoySelector::default(),
+            &DistinctTransactionDecoySelector,
+        ];
+
+        for selector in selectors.iter() {
+            let rings = selector.select(candidates(10, 2, &mut rng), 3, 5, &mut rng);
+            assert_eq!(rings.len(), 3);
+            assert!(rings.iter().all(|r| r.len() == 5));
+            assert_eq!(public_keys(&rings).len(), 15);
+
+            // too few candidates leaves the last ring short.
+            let rings = selector.select(candidates(8, 1, &mut rng), 2, 5, &mut rng);
+            assert_eq!(
+                rings.iter().map(|r| r.len()).collect::<Vec<_>>(),
+                vec![5, 3]
+            );
+        }
+    }
+
+    #[test]
+    fn distinct_transaction_selector_avoids_siblings() {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let candidates = candidates(4, 3, &mut rng);
+
+        let tx_of = |decoy: &DecoyInput| {
+            candidates
+                .iter()
+                .find(|c| c.decoy.public_key() == decoy.public_key())
+                .and_then(|c| c.transaction_hash)
+        };
+
+        let rings = DistinctTransactionDecoySelector.select(candidates.clone(), 3, 4, &mut rng);
+        for ring in rings.iter() {
+            assert_eq!(ring.len(), 4);
+            let txs: BTreeSet<_> = ring.iter().map(&tx_of).collect();
+            assert_eq!(txs.len(), ring.len());
+        }
+    }
+
+    #[test]
+    fn age_weighted_selector_prefers_recent_outputs() {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let candidates = candidates(1000, 1, &mut rng);
+        let age_of = |decoy: &DecoyInput| {
+            candidates
+                .iter()
+                .find(|c| c.decoy.public_key() == decoy.public_key())
+                .and_then(|c| c.age)
+                .unwrap_or_default()
+        };
+        let mean_age = |rings: Vec<Vec<DecoyInput>>| {
+            let ages: Vec<u64> = rings.iter().flatten().map(&age_of).collect();
+            ages.iter().sum::<u64>() / ages.len() as u64
+        };
+
+        let selector = AgeWeightedDecoySelector {
+            shape: 2.0,
+            scale: 20.0,
+        };
+        let weighted = mean_age(selector.select(candidates.clone(), 10, 10, &mut rng));
+        let uniform = mean_age(UniformDecoySelector.select(candidates.clone(), 10, 10, &mut rng));
+
+        // the gamma distribution has mean shape * scale = 40.
+        assert!(weighted < 100, "mean age {}", weighted);
+        assert!(uniform > 300, "mean age {}", uniform);
+    }
+
+    #[test]
+    fn builder_replaces_unusable_provided_decoys() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (_spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+
+        // the provider first offers the true input, and a duplicate.
+        let true_input = DecoyCandidate::from(DecoyInput {
+            public_key: genesis_dbc.as_true_input_bearer()?.public_key().to_affine(),
+            commitment: G1Projective::random(&mut rng).to_affine(),
+        });
+        let decoys = candidates(5, 1, &mut rng);
+        let provider = SequentialProvider {
+            candidates: vec![true_input, decoys[0].clone(), decoys[0].clone()]
+                .into_iter()
+                .chain(decoys[1..].iter().cloned())
+                .collect(),
+            next: RefCell::new(0),
+        };
+
+        let build = |provider: &SequentialProvider, rng: &mut _| {
+            TransactionBuilder::default()
+                .set_decoys_per_input(4)
+                .add_input_dbc_bearer(&genesis_dbc)?
+                .add_output_by_amount(
+                    Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT),
+                    OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut *rng), &mut *rng),
+                )
+                .build_with_decoy_provider(provider, &mut *rng)
+        };
+
+        // 4 decoys are fetched, of which 2 are usable, then 2 more.
+        let dbc_builder = build(&provider, &mut rng)?;
+        assert_eq!(*provider.next.borrow(), 6);
+        let ring = &dbc_builder.transaction.mlsags[0].ring;
+        assert_eq!(ring.len(), 5);
+        assert!(decoys[..4]
+            .iter()
+            .all(|d| ring.iter().any(|(pk, _)| *pk == d.decoy.public_key())));
+
+        // only 1 remains, which is insufficient.
+        assert_eq!(
+            build(&provider, &mut rng).err(),
+            Some(Error::InsufficientDecoys)
+        );
+
+        // siblings left out by the DistinctTransactionDecoySelector are made
+        // up by fetching again.
+        let provider = SequentialProvider {
+            candidates: candidates(3, 2, &mut rng),
+            next: RefCell::new(0),
+        };
+        let dbc_builder = TransactionBuilder::default()
+            .set_decoys_per_input(3)
+            .set_decoy_selector(DistinctTransactionDecoySelector)
+            .add_input_dbc_bearer(&genesis_dbc)?
+            .add_output_by_amount(
+                Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT),
+                OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng),
+            )
+            .build_with_decoy_provider(&provider, &mut rng)?;
+        assert_eq!(*provider.next.borrow(), 5);
+        assert_eq!(dbc_builder.transaction.mlsags[0].ring.len(), 4);
+
+        Ok(())
+    }
+}
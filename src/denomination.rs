@@ -0,0 +1,211 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result, Token};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// the default most outputs of a split.  The 1-2-5 series splits any amount
+// into fewer than this.
+const DEFAULT_MAX_OUTPUTS: usize = 64;
+
+/// A schedule of standard denominations, used to split a payment into
+/// several outputs of common amounts.
+///
+/// An output of an odd amount can link a payment to its sender or
+/// recipient if the amount is ever revealed.  Outputs of standard
+/// amounts are indistinguishable from one another, and are also easier
+/// to combine when later selecting coins.
+///
+/// The default schedule is the 1-2-5 series: 1, 2, 5, 10, 20, 50, ...
+/// nanos, up to the largest that fits in a Token.
+///
+/// A split may have at most <max_outputs> outputs, so that a schedule of
+/// small denominations cannot be used to create a huge transaction.
+///
+/// See TransactionBuilder::add_outputs_by_denomination().
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Denominations {
+    denominations: Vec<Token>,
+    max_outputs: usize,
+}
+
+impl Default for Denominations {
+    fn default() -> Self {
+        Self::one_two_five()
+    }
+}
+
+impl Denominations {
+    /// create a schedule from a list of denominations.
+    ///
+    /// Zero amounts and duplicates are ignored.
+    pub fn new(denominations: impl IntoIterator<Item = Token>) -> Self {
+        let mut denominations: Vec<Token> = denominations
+            .into_iter()
+            .filter(|d| *d != Token::zero())
+            .collect();
+        // note: largest first, as split() is greedy.
+        denominations.sort_by(|a, b| b.cmp(a));
+        denominations.dedup();
+        Self {
+            denominations,
+            max_outputs: DEFAULT_MAX_OUTPUTS,
+        }
+    }
+
+    /// the 1-2-5 series of denominations: 1, 2, 5, 10, 20, 50, ... nanos.
+    pub fn one_two_five() -> Self {
+        let denominations = (0..)
+            .map_while(|exp| 10u64.checked_pow(exp))
+            .flat_map(|power| vec![1, 2, 5].into_iter().map(move |m| power.checked_mul(m)))
+            .flatten()
+            .map(Token::from_nano);
+        Self::new(denominations)
+    }
+
+    /// set max_outputs option.  The most outputs of a split.  Defaults to 64.
+    pub fn set_max_outputs(mut self, max_outputs: usize) -> Self {
+        self.max_outputs = max_outputs;
+        self
+    }
+
+    /// the denominations, largest first.
+    pub fn values(&self) -> &[Token] {
+        &self.denominations
+    }
+
+    /// split an amount into denominations, largest first.
+    ///
+    /// The split is greedy: it takes as many of the largest denomination
+    /// as fit, then of the next largest, and so on.  This always succeeds
+    /// for a schedule that includes 1 nano, and uses the fewest outputs
+    /// for the 1-2-5 series.  Otherwise, Error::AmountNotDenominable is
+    /// returned if an amount remains.  Error::TooManyDenominations is
+    /// returned if the split would have more than <max_outputs> outputs.
+    ///
+    /// A zero amount splits into no denominations.
+    pub fn split(&self, amount: Token) -> Result<Vec<Token>> {
+        let mut remaining = amount.as_nano();
+        let mut split = vec![];
+        for denomination in self.denominations.iter() {
+            let count = remaining / denomination.as_nano();
+            remaining -= count * denomination.as_nano();
+            if count > (self.max_outputs - split.len()) as u64 {
+                return Err(Error::TooManyDenominations {
+                    amount,
+                    max_outputs: self.max_outputs,
+                });
+            }
+            split.extend((0..count).map(|_| *denomination));
+        }
+
+        if remaining != 0 {
+            return Err(Error::AmountNotDenominable(amount));
+        }
+        Ok(split)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::spend_genesis,
+        rand::{distributions::Standard, Rng},
+        Owner,
+    };
+    use blsttc::SecretKey;
+    use std::collections::BTreeSet;
+
+    fn nanos(tokens: &[Token]) -> Vec<u64> {
+        tokens.iter().map(|t| t.as_nano()).collect()
+    }
+
+    #[test]
+    fn split_into_denominations() -> Result<()> {
+        let one_two_five = Denominations::default();
+        assert_eq!(
+            nanos(&one_two_five.values()[..6]),
+            vec![
+                10_000_000_000_000_000_000,
+                5_000_000_000_000_000_000,
+                2_000_000_000_000_000_000,
+                1_000_000_000_000_000_000,
+                500_000_000_000_000_000,
+                200_000_000_000_000_000,
+            ]
+        );
+        assert_eq!(
+            nanos(&one_two_five.split(Token::from_nano(1234))?),
+            vec![1000, 200, 20, 10, 2, 2]
+        );
+        assert_eq!(one_two_five.split(Token::zero())?, vec![]);
+        let split = one_two_five.split(Token::from_nano(u64::MAX))?;
+        assert_eq!(split.iter().map(|t| t.as_nano()).sum::<u64>(), u64::MAX);
+
+        // a custom schedule, given in any order, with a zero and a duplicate.
+        let custom = Denominations::new([0, 3, 10, 3].iter().map(|n| Token::from_nano(*n)));
+        assert_eq!(nanos(custom.values()), vec![10, 3]);
+        assert_eq!(
+            nanos(&custom.split(Token::from_nano(26))?),
+            vec![10, 10, 3, 3]
+        );
+        assert_eq!(
+            custom.split(Token::from_nano(25)),
+            Err(Error::AmountNotDenominable(Token::from_nano(25)))
+        );
+
+        // a split of many small denominations is refused, not allocated.
+        let ones = Denominations::new(vec![Token::from_nano(1)]);
+        assert_eq!(
+            ones.split(Token::from_nano(u64::MAX)),
+            Err(Error::TooManyDenominations {
+                amount: Token::from_nano(u64::MAX),
+                max_outputs: 64
+            })
+        );
+        let ones = ones.set_max_outputs(100);
+        assert_eq!(ones.split(Token::from_nano(100))?.len(), 100);
+        assert!(ones.split(Token::from_nano(101)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder_adds_denominated_outputs() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let recipient_sk: SecretKey = rng.sample(Standard);
+        let recipient = Owner::from(recipient_sk.public_key());
+        let (_spentbook_node, outputs) = spend_genesis(&mut rng, |tx_builder, rng| {
+            tx_builder.add_outputs_by_denomination(
+                Token::from_nano(1234),
+                recipient.clone(),
+                &Denominations::default(),
+                rng,
+            )
+        })?;
+
+        // each denomination is paid to its own one-time key of the recipient.
+        let mut amounts = vec![];
+        let mut public_keys = BTreeSet::new();
+        for (_, owner_once, amount_secrets) in outputs {
+            if owner_once.owner_base == recipient {
+                amounts.push(amount_secrets.amount().as_nano());
+                public_keys.insert(owner_once.as_owner().public_key());
+            }
+        }
+        amounts.sort_unstable();
+        assert_eq!(amounts, vec![2, 2, 10, 20, 200, 1000]);
+        assert_eq!(public_keys.len(), amounts.len());
+
+        Ok(())
+    }
+}
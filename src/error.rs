@@ -7,7 +7,7 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 use thiserror::Error;
 
-use crate::KeyImage;
+use crate::{KeyImage, Token};
 
 /// Specialisation of `std::Result`.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -45,6 +45,12 @@ pub enum Error {
     #[error("OutputProof not found in transaction outputs")]
     OutputProofNotFound,
 
+    #[error("No revealed commitment matches a transaction output")]
+    RevealedCommitmentNotFound,
+
+    #[error("Could not form an OutputProof from its parts: {0}")]
+    OutputProofLayout(String),
+
     #[error("Missing spent transaction for at least one of the spent proofs")]
     MissingSpentTransaction,
 
@@ -68,6 +74,9 @@ pub enum Error {
     #[error("Invalid AmountSecret bytes")]
     AmountSecretsBytesInvalid,
 
+    #[error("Invalid DbcMetadata bytes")]
+    DbcMetadataBytesInvalid,
+
     #[error("Amount Commitments do not match")]
     AmountCommitmentsDoNotMatch,
 
@@ -92,6 +101,177 @@ pub enum Error {
     #[error("Could not convert owned DBC to bearer: {0}")]
     DbcBearerConversionFailed(String),
 
+    #[error("Insufficient balance: {available} available but {requested} requested")]
+    InsufficientBalance { available: Token, requested: Token },
+
+    #[error("The transaction has no output matching the fee")]
+    FeeOutputNotFound,
+
+    #[error("The fee output commitment does not match the fee")]
+    FeeCommitmentMismatch,
+
+    #[error("Outputs exceed inputs: {inputs} in inputs but {outputs} in outputs")]
+    OutputsExceedInputs { inputs: Token, outputs: Token },
+
+    #[error("Inputs exceed outputs: {inputs} in inputs but {outputs} in outputs")]
+    InputsExceedOutputs { inputs: Token, outputs: Token },
+
+    #[error("Output {0} has the same public key as an earlier output")]
+    OutputPublicKeyNotUnique(usize),
+
+    #[error("Input {0} is the same as an earlier input")]
+    InputNotUnique(usize),
+
+    #[error("Output {0} has a zero amount")]
+    OutputAmountZero(usize),
+
+    #[error("Output {0} amount is below the dust threshold")]
+    OutputAmountDust(usize),
+
+    #[error("The sum of inputs overflows at input {0}")]
+    InputsAmountOverflow(usize),
+
+    #[error("The sum of outputs and fee overflows at output {0}")]
+    OutputsAmountOverflow(usize),
+
+    #[error("No combination of Dbcs matches the target amount")]
+    CoinSelectionNotFound,
+
+    #[error("{0} cannot be split into the denominations")]
+    AmountNotDenominable(Token),
+
+    #[error("{amount} splits into more than {max_outputs} denominations")]
+    TooManyDenominations { amount: Token, max_outputs: usize },
+
+    #[error("Invalid payout CSV at line {line}: {reason}")]
+    PayoutCsvInvalid { line: usize, reason: String },
+
+    #[error("Payout row {0} has a zero amount")]
+    PayoutRowAmountZero(usize),
+
+    #[error("Payout row {0} amount is below the dust threshold")]
+    PayoutRowAmountDust(usize),
+
+    #[error("Hardened keys cannot be derived without the parent SecretKey")]
+    HardenedDerivationRequiresSecretKey,
+
+    #[error("Mnemonic must have 12, 15, 18, 21 or 24 words, but has {0}")]
+    MnemonicInvalidWordCount(usize),
+
+    #[error("Mnemonic entropy must be 16, 20, 24, 28 or 32 bytes, but is {0}")]
+    MnemonicInvalidEntropyLength(usize),
+
+    #[error("Unknown mnemonic word: {0}")]
+    MnemonicUnknownWord(String),
+
+    #[error("Mnemonic checksum is invalid")]
+    MnemonicInvalidChecksum,
+
+    #[error("ViewKey does not match the Dbc owner's view key")]
+    ViewKeyDoesNotMatchOwner,
+
+    #[error("Dbc not found in wallet: {0:?}")]
+    DbcNotFoundInWallet(KeyImage),
+
+    #[error("Owner is not a Multisig owner")]
+    NotMultisigOwner,
+
+    #[error("Multisig requires {required} shares, but {provided} were provided")]
+    MultisigNotEnoughShares { required: usize, provided: usize },
+
+    #[error("Multisig share does not match the signing session")]
+    MultisigShareMismatch,
+
+    #[error("Transaction has Multisig inputs, which must be signed via build_multisig()")]
+    MultisigSigningRequired,
+
+    #[error("Multisig key image share of party {0} does not match its proof")]
+    MultisigKeyImageShareInvalid(usize),
+
+    #[error("Multisig signature share of party {0} is invalid")]
+    MultisigSignatureShareInvalid(usize),
+
+    #[error("Multisig challenge does not match its transaction")]
+    MultisigChallengeInvalid,
+
+    #[error("Multisig transaction outputs or fee are not those approved")]
+    MultisigOutputsNotApproved,
+
+    #[error("The transaction must have at least one output")]
+    TransactionMustHaveAnOutput,
+
+    #[error("Not a valid wallet file")]
+    WalletFileInvalid,
+
+    #[error("Unsupported wallet file version: {0}")]
+    WalletFileUnsupportedVersion(u16),
+
+    #[error("Wallet file decryption failed.  The password is wrong or the file is corrupt")]
+    WalletFileDecryptionFailed,
+
+    #[error("Wallet file key derivation failed: {0}")]
+    WalletFileKdf(String),
+
+    #[error("Wallet file serialization failed: {0}")]
+    WalletFileSerialization(String),
+
+    #[error("Wallet file I/O error: {0}")]
+    WalletFileIo(String),
+
+    #[error("Payment request expired at {expiry}, and it is now {now}")]
+    PaymentRequestExpired { expiry: u64, now: u64 },
+
+    #[error("The PaymentProof is for a different recipient")]
+    PaymentProofRecipientMismatch,
+
+    #[error("A Dbc is included more than once in the ReservesProof")]
+    ReservesProofDuplicateDbc,
+
+    #[error("The TransactionProposal already has signed inputs")]
+    TransactionProposalAlreadySigned,
+
+    #[error("The TransactionProposal has unsigned inputs: {0:?}")]
+    TransactionProposalIncomplete(Vec<KeyImage>),
+
+    #[error("The TransactionProposal does not contain the party's inputs and outputs")]
+    TransactionProposalMismatch,
+
+    #[error("A TransactionProposal party's inputs ({inputs}) must equal its outputs ({outputs})")]
+    TransactionProposalUnbalanced { inputs: Token, outputs: Token },
+
+    #[error("Fees are not supported in a TransactionProposal")]
+    TransactionProposalFeeUnsupported,
+
+    #[error("A CoinJoin round requires at least two participants")]
+    CoinJoinTooFewParticipants,
+
+    #[error("Unexpected CoinJoin message for the current phase of the round")]
+    CoinJoinUnexpectedMessage,
+
+    #[error("CoinJoin participant misbehaved: {0}")]
+    CoinJoinParticipantMisbehaved(Box<Error>),
+
+    #[error("The CoinJoin round was aborted")]
+    CoinJoinRoundAborted,
+
+    #[error("The CoinJoin round is not yet complete")]
+    CoinJoinIncomplete,
+
+    #[error("The OfflineSigningRequest does not match its checksum")]
+    OfflineSigningRequestCorrupt,
+
+    #[error("The OfflineSigningRequest outputs do not match the approved payees")]
+    OfflineSigningOutputsNotApproved,
+
+    #[error("The OfflineSigningRequest fee does not match the approved fee")]
+    OfflineSigningFeeNotApproved,
+
+    #[error("The OfflineSigningRequest change owner is not one of the signing keys")]
+    OfflineSigningChangeNotOwned,
+
+    #[error("The signed DbcBuilder does not match the OfflineSigningRequest")]
+    OfflineSigningPayloadMismatch,
+
     #[error("Bls error: {0}")]
     Blsttc(#[from] blsttc::error::Error),
 
@@ -0,0 +1,220 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{DbcMetadata, Owner};
+
+// serialized sizes in bytes, as encoded by bincode.  See Dbc::to_hex().
+const G1_SIZE: usize = 48;
+const G2_SIZE: usize = 96;
+const SCALAR_SIZE: usize = 32;
+const HASH_SIZE: usize = 32;
+const LEN_SIZE: usize = 8; // length prefix of a Vec or set.
+const ENUM_TAG_SIZE: usize = 4;
+const OPTION_TAG_SIZE: usize = 1;
+const CIPHERTEXT_OVERHEAD: usize = G1_SIZE + LEN_SIZE + G2_SIZE;
+const DERIVATION_INDEX_SIZE: usize = 32;
+const AMOUNT_SECRETS_SIZE: usize = 8 + SCALAR_SIZE;
+const FEE_SIZE: usize = 8 + SCALAR_SIZE;
+
+// outputs have a bulletproof that the amount fits in 64 bits, whose inner
+// product argument takes log2(64) rounds.
+const RANGE_PROOF_BITS: usize = 64;
+const RANGE_PROOF_ROUNDS: usize = 6;
+const RANGE_PROOF_SIZE: usize = LEN_SIZE + (4 + 2 * RANGE_PROOF_ROUNDS) * G1_SIZE + 5 * SCALAR_SIZE;
+const OUTPUT_PROOF_SIZE: usize = G1_SIZE + RANGE_PROOF_SIZE + G1_SIZE;
+
+// verification costs, counted in elliptic curve scalar multiplications.
+//
+// each ring member of an MLSAG requires 6, plus hashing its public key
+// to the curve.  A range proof requires a multiscalar multiplication of
+// 2n + 2log2(n) + 7 points, each counted as one.
+const MLSAG_COST_PER_RING_MEMBER: u64 = 7;
+const RANGE_PROOF_COST: u64 = (2 * RANGE_PROOF_BITS + 2 * RANGE_PROOF_ROUNDS + 7) as u64;
+
+/// An estimate of the size of a transaction and its output Dbcs, and of
+/// the cost to verify it, made before it is built.
+///
+/// Each output Dbc carries the whole transaction, so Dbc sizes grow with
+/// the number of inputs, decoys and outputs.  A wallet can use this to
+/// split a payment into several transactions, or to use fewer outputs.
+///
+/// See TransactionBuilder::estimate().
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionEstimate {
+    /// the serialized size of the RingCtTransaction, in bytes.
+    pub transaction_size: usize,
+    /// the serialized size of each output Dbc, in bytes, in the order of
+    /// TransactionBuilder::outputs(), followed by the change Dbc if any.
+    pub dbc_sizes: Vec<usize>,
+    /// the relative cost to verify the transaction, in approximate
+    /// elliptic curve scalar multiplications.
+    pub verification_cost: u64,
+}
+
+impl TransactionEstimate {
+    // ring_sizes has the number of ring members of each input, and
+    // num_outputs includes outputs that do not become a Dbc, eg the fee.
+    pub(crate) fn new<'a>(
+        ring_sizes: &[usize],
+        num_outputs: usize,
+        dbc_owners: impl IntoIterator<Item = (&'a Owner, Option<&'a DbcMetadata>)>,
+    ) -> Self {
+        let transaction_size = LEN_SIZE
+            + ring_sizes.iter().map(|r| mlsag_size(*r)).sum::<usize>()
+            + LEN_SIZE
+            + num_outputs * OUTPUT_PROOF_SIZE;
+
+        // a Dbc has the transaction and its fee, and also a spent proof for
+        // each input and the spent transaction, which is the same transaction.
+        let spent_proofs_size = LEN_SIZE
+            + ring_sizes
+                .iter()
+                .map(|r| spent_proof_size(*r))
+                .sum::<usize>();
+        let dbc_sizes = dbc_owners
+            .into_iter()
+            .map(|(owner, metadata)| {
+                dbc_content_size(owner, metadata)
+                    + transaction_size
+                    + FEE_SIZE
+                    + spent_proofs_size
+                    + LEN_SIZE
+                    + transaction_size
+            })
+            .collect();
+
+        let ring_members: usize = ring_sizes.iter().sum();
+        let verification_cost = ring_members as u64 * MLSAG_COST_PER_RING_MEMBER
+            + num_outputs as u64 * RANGE_PROOF_COST;
+
+        Self {
+            transaction_size,
+            dbc_sizes,
+            verification_cost,
+        }
+    }
+
+    /// the largest of the output Dbc sizes, in bytes.
+    pub fn max_dbc_size(&self) -> usize {
+        self.dbc_sizes.iter().copied().max().unwrap_or_default()
+    }
+}
+
+fn mlsag_size(ring_size: usize) -> usize {
+    // c0, r, key_image, ring, and pseudo_commitment.
+    SCALAR_SIZE
+        + LEN_SIZE
+        + ring_size * 2 * SCALAR_SIZE
+        + G1_SIZE
+        + LEN_SIZE
+        + ring_size * 2 * G1_SIZE
+        + G1_SIZE
+}
+
+fn spent_proof_size(ring_size: usize) -> usize {
+    // key_image, transaction_hash, public_commitments, and the
+    // spentbook's public key and signature.
+    G1_SIZE + HASH_SIZE + LEN_SIZE + ring_size * G1_SIZE + G1_SIZE + G2_SIZE
+}
+
+fn dbc_content_size(owner: &Owner, metadata: Option<&DbcMetadata>) -> usize {
+    let owner_size = match owner {
+        Owner::SecretKey(_) => SCALAR_SIZE,
+        Owner::PublicKey(_) => G1_SIZE,
+        Owner::SplitKey { .. } => 2 * G1_SIZE,
+        Owner::Multisig(pks) => LEN_SIZE + (pks.threshold() + 1) * G1_SIZE,
+    };
+    let metadata_size = metadata
+        .map(|m| CIPHERTEXT_OVERHEAD + m.to_bytes().len())
+        .unwrap_or_default();
+
+    ENUM_TAG_SIZE
+        + owner_size
+        + CIPHERTEXT_OVERHEAD
+        + DERIVATION_INDEX_SIZE
+        + CIPHERTEXT_OVERHEAD
+        + AMOUNT_SECRETS_SIZE
+        + OPTION_TAG_SIZE
+        + metadata_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::pay_from_genesis,
+        rand::{distributions::Standard, Rng},
+        OwnerOnce, Result, Token, TransactionBuilder,
+    };
+    use blsttc::{PublicKey, SecretKey};
+
+    #[test]
+    fn estimate_matches_serialized_sizes() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        // split genesis, so there are outputs to spend and to use as decoys.
+        let sk: SecretKey = rng.sample(Standard);
+        let (mut spentbook_node, dbcs) =
+            pay_from_genesis(&[1000; 6], &Owner::from(sk.public_key()), &mut rng)?;
+
+        // 2 inputs with 2 decoys each, and outputs of each kind of owner,
+        // with metadata, change and a fee.
+        let builder = TransactionBuilder::default()
+            .set_decoys_per_input(2)
+            .add_decoy_inputs(spentbook_node.random_decoys(100, &mut rng))
+            .add_input_dbc(&dbcs[0], &sk)?
+            .add_input_dbc(&dbcs[1], &sk)?
+            .add_output_by_amount(
+                Token::from_nano(500),
+                OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng),
+            )
+            .add_output_by_amount_with_metadata(
+                Token::from_nano(700),
+                OwnerOnce::from_owner_base(Owner::from(sk.public_key()), &mut rng),
+                DbcMetadata::from_memo("for the estimate"),
+            )
+            .set_fee(Token::from_nano(10))
+            .set_change_owner(OwnerOnce::from_owner_base(
+                Owner::from(sk.public_key()),
+                &mut rng,
+            ));
+        let estimate = builder.estimate();
+
+        let mut dbc_builder = builder.build(&mut rng)?;
+        assert_eq!(
+            estimate.transaction_size,
+            bincode::serialize(&dbc_builder.transaction).unwrap().len()
+        );
+        for (key_image, tx) in dbc_builder.inputs() {
+            let spent_proof_share =
+                spentbook_node.log_spent_with_fee(key_image, tx.clone(), &dbc_builder.fee)?;
+            dbc_builder = dbc_builder
+                .add_spent_proof_share(spent_proof_share)
+                .add_spent_transaction(tx);
+        }
+
+        // the output Dbcs are in order of the transaction outputs.
+        let tx_outputs = dbc_builder.transaction.outputs.clone();
+        let mut output_dbcs = dbc_builder.build(&spentbook_node.key_manager)?;
+        output_dbcs.sort_by_key(|(_, owner_once, _)| {
+            tx_outputs.iter().position(|o| {
+                PublicKey::from(*o.public_key()) == owner_once.as_owner().public_key()
+            })
+        });
+        let dbc_sizes: Vec<usize> = output_dbcs
+            .iter()
+            .map(|(dbc, ..)| bincode::serialize(dbc).unwrap().len())
+            .collect();
+        assert_eq!(estimate.dbc_sizes, dbc_sizes);
+        assert_eq!(estimate.max_dbc_size(), *dbc_sizes.iter().max().unwrap());
+
+        // verification cost grows with decoys and outputs.
+        assert_eq!(estimate.verification_cost, 6 * 7 + 4 * RANGE_PROOF_COST);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{BlindingFactor, Commitment, Error, KeyImage, Result, Token};
+use bls_ringct::{
+    bls_bulletproofs::PedersenGens,
+    blstrs::G1Projective,
+    group::{Curve, Group},
+    ringct::RingCtTransaction,
+    RevealedCommitment,
+};
+use blsttc::PublicKey;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A publicly visible transaction fee.
+///
+/// RingCt requires that input commitments sum exactly to output commitments,
+/// so a fee is paid as an additional transaction output whose amount and
+/// blinding factor are revealed.  The output commitment is included in the
+/// transaction hash, so the fee is committed to by the transaction and its
+/// SpentProofs.
+///
+/// The fee output is sent to a public key derived from the key images of
+/// the transaction inputs and a base point for which nobody knows the
+/// secret key.  Thus the fee output can be identified by anyone, and can
+/// never be spent by anyone.
+///
+/// A zero fee has no output.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Fee(RevealedCommitment);
+
+impl Default for Fee {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl Fee {
+    /// a zero fee.  A transaction with zero fee has no fee output.
+    pub fn zero() -> Self {
+        Self(RevealedCommitment {
+            value: 0,
+            blinding: BlindingFactor::default(),
+        })
+    }
+
+    /// amount getter
+    pub fn amount(&self) -> Token {
+        Token::from_nano(self.0.value)
+    }
+
+    /// blinding factor getter
+    pub fn blinding_factor(&self) -> BlindingFactor {
+        self.0.blinding
+    }
+
+    /// returns the commitment to the fee amount
+    pub fn commitment(&self) -> Commitment {
+        self.0.commit(&PedersenGens::default()).to_affine()
+    }
+
+    /// returns the public key of the fee output for a transaction
+    /// spending the given key images.
+    pub fn output_public_key<'a>(key_images: impl IntoIterator<Item = &'a KeyImage>) -> PublicKey {
+        let mut key_images: Vec<[u8; 48]> = key_images.into_iter().map(|k| k.to_bytes()).collect();
+        key_images.sort_unstable();
+
+        let base: PublicKey = bls_ringct::hash_to_curve(G1Projective::generator())
+            .to_affine()
+            .into();
+        base.derive_child(&crate::sha3_256(&key_images.concat()))
+    }
+
+    /// returns the public key of the fee output of a transaction.
+    pub fn public_key(transaction: &RingCtTransaction) -> PublicKey {
+        let key_images: Vec<KeyImage> = transaction
+            .mlsags
+            .iter()
+            .map(|m| m.key_image.into())
+            .collect();
+        Self::output_public_key(key_images.iter())
+    }
+
+    /// Verifies that the transaction pays exactly this fee.
+    ///
+    /// Given that RingCtTransaction::verify() ensures inputs sum to outputs,
+    /// this ensures that inputs sum to the non-fee outputs plus the fee.
+    pub fn verify(&self, transaction: &RingCtTransaction) -> Result<()> {
+        let fee_pk = Self::public_key(transaction);
+        let fee_output = transaction
+            .outputs
+            .iter()
+            .find(|o| PublicKey::from(*o.public_key()) == fee_pk);
+
+        match fee_output {
+            None if self.amount() == Token::zero() => Ok(()),
+            None => Err(Error::FeeOutputNotFound),
+            Some(o) if o.commitment() == self.commitment() => Ok(()),
+            Some(_) => Err(Error::FeeCommitmentMismatch),
+        }
+    }
+}
+
+impl From<RevealedCommitment> for Fee {
+    fn from(revealed_commitment: RevealedCommitment) -> Self {
+        Self(revealed_commitment)
+    }
+}
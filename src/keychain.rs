@@ -0,0 +1,257 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{distributions::Standard, Rng},
+    rng, DerivationIndex, Error, Owner, PublicKey, Result,
+};
+use blsttc::{serde_impl::SerdeSecret, SecretKey};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A ChainCode is mixed into every child derivation, so that knowledge
+/// of a public key alone is not sufficient to derive its children.
+pub type ChainCode = [u8; 32];
+
+const KEY_TAG: &[u8] = b"sn_dbc keychain key";
+const CHAIN_CODE_TAG: &[u8] = b"sn_dbc keychain chain code";
+const MASTER_TAG: &[u8] = b"sn_dbc keychain master";
+
+/// A single step in a derivation path.
+///
+/// Normal children can be derived from either a Keychain or a
+/// PublicKeychain, which enables a watch-only party to derive the
+/// public keys of an account.
+///
+/// Hardened children can only be derived from a Keychain, ie the
+/// parent SecretKey is required.  With normal derivation, a leaked child
+/// SecretKey plus the parent PublicKeychain reveals the parent SecretKey,
+/// and thus every sibling.  This is not possible with hardened derivation.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChildNumber {
+    Normal(u32),
+    Hardened(u32),
+}
+
+/// A hierarchical deterministic keychain.
+///
+/// A tree of base Owner keys is derived from a single master seed,
+/// so that only the seed need be backed up.  Derivation paths are
+/// sequences of ChildNumber.  By convention, the keys for an account
+/// are at the path [Hardened(account), Normal(index)].  See
+/// Keychain::account_owner().
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Keychain {
+    secret_key: SerdeSecret<SecretKey>,
+    chain_code: ChainCode,
+}
+
+/// The public half of a Keychain.  This can derive normal (but not
+/// hardened) children.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKeychain {
+    public_key: PublicKey,
+    chain_code: ChainCode,
+}
+
+impl Keychain {
+    /// create the master Keychain from a seed.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let mut rng = rng::from_seed(tagged_hash(MASTER_TAG, &[], seed));
+        let secret_key: SecretKey = rng.sample(Standard);
+        let chain_code = tagged_hash(CHAIN_CODE_TAG, &[], seed);
+        Self::new(secret_key, chain_code)
+    }
+
+    /// create a Keychain from a SecretKey and ChainCode
+    pub fn new(secret_key: SecretKey, chain_code: ChainCode) -> Self {
+        Self {
+            secret_key: SerdeSecret(secret_key),
+            chain_code,
+        }
+    }
+
+    /// returns the SecretKey
+    pub fn secret_key(&self) -> &SecretKey {
+        self.secret_key.inner()
+    }
+
+    /// returns the PublicKey
+    pub fn public_key(&self) -> PublicKey {
+        self.secret_key.public_key()
+    }
+
+    /// returns the ChainCode
+    pub fn chain_code(&self) -> ChainCode {
+        self.chain_code
+    }
+
+    /// returns the Owner of this key, for use as a base Owner.
+    pub fn owner(&self) -> Owner {
+        Owner::from(self.secret_key().clone())
+    }
+
+    /// returns the public half of this Keychain
+    pub fn to_public(&self) -> PublicKeychain {
+        PublicKeychain {
+            public_key: self.public_key(),
+            chain_code: self.chain_code,
+        }
+    }
+
+    /// derive a child Keychain
+    pub fn derive_child(&self, child: ChildNumber) -> Self {
+        let (index, chain_code) = match child {
+            ChildNumber::Normal(i) => {
+                child_derivation(&self.chain_code, &self.public_key().to_bytes(), 0, i)
+            }
+            ChildNumber::Hardened(i) => {
+                child_derivation(&self.chain_code, &self.secret_key().to_bytes(), 1, i)
+            }
+        };
+        Self::new(self.secret_key().derive_child(&index), chain_code)
+    }
+
+    /// derive a descendant Keychain by following a path of ChildNumber
+    pub fn derive_path(&self, path: &[ChildNumber]) -> Self {
+        path.iter().fold(self.clone(), |keychain, child| {
+            keychain.derive_child(*child)
+        })
+    }
+
+    /// returns the Keychain for an account.  This is a hardened child.
+    pub fn account(&self, account: u32) -> Self {
+        self.derive_child(ChildNumber::Hardened(account))
+    }
+
+    /// returns the base Owner at the given index of the given account.
+    pub fn account_owner(&self, account: u32, index: u32) -> Owner {
+        self.account(account)
+            .derive_child(ChildNumber::Normal(index))
+            .owner()
+    }
+}
+
+impl PublicKeychain {
+    /// create a PublicKeychain from a PublicKey and ChainCode
+    pub fn new(public_key: PublicKey, chain_code: ChainCode) -> Self {
+        Self {
+            public_key,
+            chain_code,
+        }
+    }
+
+    /// returns the PublicKey
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    /// returns the ChainCode
+    pub fn chain_code(&self) -> ChainCode {
+        self.chain_code
+    }
+
+    /// returns the Owner of this key, for use as a base Owner.
+    pub fn owner(&self) -> Owner {
+        Owner::from(self.public_key)
+    }
+
+    /// derive a normal child PublicKeychain.
+    ///
+    /// returns an error if the child is hardened.
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Self> {
+        match child {
+            ChildNumber::Normal(i) => {
+                let (index, chain_code) =
+                    child_derivation(&self.chain_code, &self.public_key.to_bytes(), 0, i);
+                Ok(Self::new(self.public_key.derive_child(&index), chain_code))
+            }
+            ChildNumber::Hardened(_) => Err(Error::HardenedDerivationRequiresSecretKey),
+        }
+    }
+
+    /// derive a descendant PublicKeychain by following a path of ChildNumber
+    pub fn derive_path(&self, path: &[ChildNumber]) -> Result<Self> {
+        path.iter().try_fold(self.clone(), |keychain, child| {
+            keychain.derive_child(*child)
+        })
+    }
+}
+
+// returns the derivation index for the child key, and the child ChainCode.
+//
+// `key_bytes` is the parent public key for normal children and the parent
+// secret key for hardened children.  The prefix keeps the two apart.
+fn child_derivation(
+    chain_code: &ChainCode,
+    key_bytes: &[u8],
+    prefix: u8,
+    i: u32,
+) -> (DerivationIndex, ChainCode) {
+    let data: Vec<u8> = [&[prefix], key_bytes, &i.to_be_bytes()].concat();
+    (
+        tagged_hash(KEY_TAG, chain_code, &data),
+        tagged_hash(CHAIN_CODE_TAG, chain_code, &data),
+    )
+}
+
+fn tagged_hash(tag: &[u8], chain_code: &[u8], data: &[u8]) -> [u8; 32] {
+    crate::sha3_256(&[tag, chain_code, data].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keychain_is_deterministic() {
+        let a = Keychain::from_seed(b"seed");
+        let b = Keychain::from_seed(b"seed");
+        let c = Keychain::from_seed(b"another seed");
+
+        assert_eq!(a.public_key(), b.public_key());
+        assert_eq!(a.chain_code(), b.chain_code());
+        assert_ne!(a.public_key(), c.public_key());
+
+        assert_eq!(a.account_owner(0, 5), b.account_owner(0, 5));
+        assert_ne!(a.account_owner(0, 5), a.account_owner(0, 6));
+        assert_ne!(a.account_owner(0, 5), a.account_owner(1, 5));
+    }
+
+    #[test]
+    fn public_keychain_derives_normal_children_only() -> Result<()> {
+        let account = Keychain::from_seed(b"seed").account(3);
+        let account_public = account.to_public();
+
+        let path = [ChildNumber::Normal(7), ChildNumber::Normal(1)];
+        assert_eq!(
+            account.derive_path(&path).to_public(),
+            account_public.derive_path(&path)?
+        );
+        assert_eq!(
+            account.derive_path(&path).owner().public_key(),
+            account_public.derive_path(&path)?.owner().public_key()
+        );
+
+        assert_eq!(
+            account_public.derive_child(ChildNumber::Hardened(7)),
+            Err(Error::HardenedDerivationRequiresSecretKey)
+        );
+
+        // hardened and normal children at the same index are distinct.
+        assert_ne!(
+            account.derive_child(ChildNumber::Hardened(7)).public_key(),
+            account.derive_child(ChildNumber::Normal(7)).public_key()
+        );
+        Ok(())
+    }
+}
@@ -6,19 +6,47 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+// Error contains bls_ringct::Error and blsttc::Error, which are large.
+#![allow(clippy::result_large_err)]
+
 use std::fmt;
 
 mod amount_secrets;
+mod audit;
 mod blst;
 mod builder;
+mod coin_selector;
+#[cfg(feature = "serde")]
+mod coinjoin;
+mod consolidation;
 mod dbc;
 mod dbc_content;
+mod dbc_metadata;
+mod decoy_selector;
+mod denomination;
 mod error;
+mod estimate;
+mod fee;
+mod keychain;
 mod mint;
+mod mnemonic;
+mod multisig;
+#[cfg(feature = "serde")]
+mod offline;
 mod owner;
+mod payment_proof;
+mod payment_request;
+mod payout;
+#[cfg(feature = "serde")]
+mod proposal;
+mod reserves;
 mod spent_proof;
 mod token;
 mod verification;
+mod view_key;
+mod wallet;
+#[cfg(feature = "wallet-file")]
+mod wallet_file;
 
 #[cfg(feature = "mock")]
 pub mod mock;
@@ -38,23 +66,61 @@ pub use blsttc::{PublicKey, PublicKeySet, Signature, SignatureShare};
 
 pub use crate::{
     amount_secrets::AmountSecrets,
+    audit::{AuditBundle, AuditEntry},
     blst::{BlindingFactor, Commitment, KeyImage},
     builder::{
-        DbcBuilder, DecoyInput, MlsagMaterial, Output, OutputOwnerMap, RevealedCommitment,
-        RingCtMaterial, RingCtTransaction, TransactionBuilder, TrueInput,
+        DbcBuilder, DecoyInput, MlsagMaterial, Output, OutputMetadataMap, OutputOwnerMap,
+        RevealedCommitment, RingCtMaterial, RingCtTransaction, TransactionBuilder, TrueInput,
     },
+    coin_selector::{
+        BranchAndBound, CoinSelection, CoinSelector, LargestFirst, RandomSelector, SmallestFirst,
+    },
+    consolidation::ConsolidationPlanner,
     dbc::Dbc,
     dbc_content::DbcContent,
+    dbc_metadata::DbcMetadata,
+    decoy_selector::{
+        AgeWeightedDecoySelector, DecoyCandidate, DecoyProvider, DecoySelector,
+        DistinctTransactionDecoySelector, UniformDecoySelector,
+    },
+    denomination::Denominations,
     error::{Error, Result},
+    estimate::TransactionEstimate,
+    fee::Fee,
+    keychain::{ChainCode, ChildNumber, Keychain, PublicKeychain},
+    mnemonic::Mnemonic,
+    multisig::{
+        DleqProof, MlsagChallenge, MlsagCommitmentShare, MlsagNonce, MlsagSignatureShare,
+        MultisigInput, MultisigOwnerShare, MultisigSigningSession,
+    },
     owner::{DerivationIndex, Owner, OwnerOnce},
+    payment_proof::PaymentProof,
+    payment_request::PaymentRequest,
+    payout::{BatchPayout, PayoutReceipt, PayoutReport, PayoutRow},
+    reserves::{ReservesEntry, ReservesProof},
     spent_proof::{
-        IndexedSignatureShare, SpentProof, SpentProofContent, SpentProofKeyVerifier,
-        SpentProofShare,
+        IndexedSignatureShare, SpentBookClient, SpentProof, SpentProofContent,
+        SpentProofKeyVerifier, SpentProofShare,
     },
     token::Token,
     verification::TransactionVerifier,
+    view_key::ViewKey,
+    wallet::{DbcStatus, Wallet, WalletDbc},
 };
 
+#[cfg(feature = "serde")]
+pub use crate::{
+    coinjoin::{
+        CoinJoinCoordinator, CoinJoinParticipant, CoordinatorMessage, ParticipantId,
+        ParticipantMessage,
+    },
+    offline::OfflineSigningRequest,
+    proposal::{ProposalParty, TransactionProposal},
+};
+
+#[cfg(feature = "wallet-file")]
+pub use crate::wallet_file::{WalletFile, WalletFileKdf};
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -160,7 +226,7 @@ mod tests {
         }
 
         fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
-            Box::new((0..(self.0)).into_iter().rev().map(Self))
+            Box::new((0..(self.0)).rev().map(Self))
         }
     }
 
@@ -189,7 +255,6 @@ mod tests {
         fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
             Box::new(
                 (1..(self.0.get()))
-                    .into_iter()
                     .rev()
                     .filter_map(NonZeroU8::new)
                     .map(Self),
@@ -10,14 +10,17 @@
 mod tests {
 
     use crate::tests::{TinyInt, TinyVec, STD_DECOYS_TO_FETCH};
-    use blsttc::{SecretKey, SecretKeySet};
+    use blsttc::{PublicKey, SecretKey, SecretKeySet};
     use quickcheck_macros::quickcheck;
     use std::collections::BTreeSet;
     use std::iter::FromIterator;
 
     use crate::{
-        mock, AmountSecrets, Dbc, DbcContent, Error, IndexedSignatureShare, KeyImage, Owner,
-        OwnerOnce, Result, SpentProofContent, SpentProofShare, Token, TransactionBuilder,
+        mock,
+        rand::{distributions::Standard, Rng},
+        AmountSecrets, Dbc, DbcContent, Error, Fee, IndexedSignatureShare, KeyImage, Output, Owner,
+        OwnerOnce, Result, RingCtMaterial, SpentProofContent, SpentProofShare, Token,
+        TransactionBuilder,
     };
 
     #[test]
@@ -36,12 +39,261 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn change_output_pays_remainder() -> Result<(), Error> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+
+        let (mut spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+
+        let recipient =
+            OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
+        let change = OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
+
+        let mut dbc_builder = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .set_change_owner(change.clone())
+            .add_input_dbc_bearer(&genesis_dbc)?
+            .add_output_by_amount(Token::from_nano(100), recipient)
+            .build(&mut rng)?;
+
+        for (key_image, tx) in dbc_builder.inputs() {
+            dbc_builder = dbc_builder
+                .add_spent_proof_share(spentbook_node.log_spent(key_image, tx.clone())?)
+                .add_spent_transaction(tx);
+        }
+        let output_dbcs = dbc_builder.build(&spentbook_node.key_manager)?;
+        assert_eq!(output_dbcs.len(), 2);
+
+        let (_dbc, _owner_once, amount_secrets) = output_dbcs
+            .iter()
+            .find(|(_, owner_once, _)| *owner_once == change)
+            .expect("change output should be present");
+        assert_eq!(
+            amount_secrets.amount(),
+            Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT - 100)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn change_output_omitted_when_zero_and_overspend_rejected() -> Result<(), Error> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+
+        let (_spentbook_node, _genesis_dbc, starting_dbc, _change_dbc) =
+            crate::dbc::tests::generate_bearer_dbc_of_value(100, &mut rng)?;
+
+        let recipient =
+            OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
+        let change = OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
+
+        let dbc_builder = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .set_change_owner(change.clone())
+            .add_input_dbc_bearer(&starting_dbc)?
+            .add_output_by_amount(Token::from_nano(100), recipient.clone())
+            .build(&mut rng)?;
+        assert_eq!(dbc_builder.transaction.outputs.len(), 1);
+        assert!(!dbc_builder
+            .output_owner_map
+            .values()
+            .any(|owner_once| *owner_once == change));
+
+        let result = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .set_change_owner(change)
+            .add_input_dbc_bearer(&starting_dbc)?
+            .add_output_by_amount(Token::from_nano(101), recipient)
+            .build(&mut rng);
+        assert_eq!(
+            result.err(),
+            Some(Error::OutputsExceedInputs {
+                inputs: Token::from_nano(100),
+                outputs: Token::from_nano(101),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fee_is_paid_and_enforced() -> Result<(), Error> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+
+        let (mut spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+        spentbook_node.set_min_fee(Token::from_nano(10));
+
+        let recipient =
+            OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
+        let change = OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
+
+        let mut dbc_builder = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .set_change_owner(change)
+            .set_fee(Token::from_nano(10))
+            .add_input_dbc_bearer(&genesis_dbc)?
+            .add_output_by_amount(Token::from_nano(100), recipient)
+            .build(&mut rng)?;
+
+        assert_eq!(dbc_builder.fee.amount(), Token::from_nano(10));
+        assert_eq!(dbc_builder.transaction.outputs.len(), 3);
+
+        for (key_image, tx) in dbc_builder.inputs() {
+            // the spentbook rejects a tx that does not declare its fee.
+            assert_eq!(
+                spentbook_node.log_spent(key_image, tx.clone()).err(),
+                Some(Error::Mock(mock::Error::FeeNotDeclared))
+            );
+
+            // or that declares an insufficient fee.
+            assert_eq!(
+                spentbook_node
+                    .log_spent_with_fee(key_image, tx.clone(), &Fee::zero())
+                    .err(),
+                Some(Error::Mock(mock::Error::FeeTooLow {
+                    fee: Token::zero(),
+                    min_fee: Token::from_nano(10),
+                }))
+            );
+
+            // or that declares a fee it does not pay.
+            let wrong_fee = Fee::from(bls_ringct::RevealedCommitment {
+                value: 11,
+                blinding: dbc_builder.fee.blinding_factor(),
+            });
+            assert_eq!(
+                spentbook_node
+                    .log_spent_with_fee(key_image, tx.clone(), &wrong_fee)
+                    .err(),
+                Some(Error::FeeCommitmentMismatch)
+            );
+
+            let spent_proof_share =
+                spentbook_node.log_spent_with_fee(key_image, tx.clone(), &dbc_builder.fee)?;
+            dbc_builder = dbc_builder
+                .add_spent_proof_share(spent_proof_share)
+                .add_spent_transaction(tx);
+        }
+
+        // the fee output is not offered as a decoy.
+        let fee_pk = Fee::public_key(&dbc_builder.transaction);
+        assert!(spentbook_node
+            .random_decoys(STD_DECOYS_TO_FETCH, &mut rng)
+            .iter()
+            .all(|d| PublicKey::from(d.public_key()) != fee_pk));
+
+        // the fee output does not become a Dbc.
+        let output_dbcs = dbc_builder.build(&spentbook_node.key_manager)?;
+        assert_eq!(output_dbcs.len(), 2);
+        let outputs_sum: u64 = output_dbcs
+            .iter()
+            .map(|(_, _, amount_secrets)| amount_secrets.amount().as_nano())
+            .sum();
+        assert_eq!(outputs_sum + 10, mock::GenesisMaterial::GENESIS_AMOUNT);
+
+        // each output Dbc carries the fee, which its recipient verifies.
+        let (dbc, owner_once, _) = &output_dbcs[0];
+        let base_sk = owner_once.owner_base().secret_key()?;
+        assert_eq!(dbc.fee.amount(), Token::from_nano(10));
+        dbc.verify(&base_sk, &spentbook_node.key_manager)?;
+
+        let mut tampered = dbc.clone();
+        tampered.fee = Fee::from(bls_ringct::RevealedCommitment {
+            value: 11,
+            blinding: dbc.fee.blinding_factor(),
+        });
+        assert_eq!(
+            tampered.verify(&base_sk, &spentbook_node.key_manager),
+            Err(Error::FeeCommitmentMismatch)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder_validation_reports_offending_index() -> Result<(), Error> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+
+        let (_spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+        let genesis_amount = mock::GenesisMaterial::GENESIS_AMOUNT;
+
+        let owners: Vec<OwnerOnce> = (0..2)
+            .map(|_| OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng))
+            .collect();
+        let builder = |amounts: &[u64]| -> Result<TransactionBuilder> {
+            Ok(TransactionBuilder::default()
+                .set_require_all_decoys(false)
+                .add_input_dbc_bearer(&genesis_dbc)?
+                .add_outputs_by_amount(
+                    amounts
+                        .iter()
+                        .zip(owners.iter())
+                        .map(|(a, o)| (Token::from_nano(*a), o.clone())),
+                ))
+        };
+
+        // ::build() validates before signing.
+        let result = builder(&[1, genesis_amount - 1])?
+            .add_output_by_amount(Token::from_nano(1), owners[0].clone())
+            .build(&mut rng);
+        assert_eq!(result.err(), Some(Error::OutputPublicKeyNotUnique(2)));
+
+        let result = builder(&[genesis_amount])?
+            .add_input_dbc_bearer(&genesis_dbc)?
+            .validate();
+        assert_eq!(result.err(), Some(Error::InputNotUnique(1)));
+
+        let result = builder(&[0, genesis_amount])?.validate();
+        assert_eq!(result.err(), Some(Error::OutputAmountZero(0)));
+
+        let result = builder(&[genesis_amount - 10, 10])?
+            .set_dust_threshold(Token::from_nano(100))
+            .validate();
+        assert_eq!(result.err(), Some(Error::OutputAmountDust(1)));
+
+        let secret_key: SecretKey = rng.sample(Standard);
+        let result = builder(&[genesis_amount])?
+            .add_input_by_secrets(secret_key.clone(), AmountSecrets::from_amount(1, &mut rng))
+            .validate();
+        assert_eq!(result.err(), Some(Error::InputsAmountOverflow(1)));
+
+        let result = builder(&[genesis_amount, 1])?.validate();
+        assert_eq!(result.err(), Some(Error::OutputsAmountOverflow(1)));
+
+        // the sums agree with ::validate().
+        let result = builder(&[u64::MAX, u64::MAX])?.outputs_amount_sum();
+        assert_eq!(result.err(), Some(Error::OutputsAmountOverflow(1)));
+        let result = builder(&[genesis_amount])?
+            .add_input_by_secrets(secret_key, AmountSecrets::from_amount(1, &mut rng))
+            .inputs_amount_sum();
+        assert_eq!(result.err(), Some(Error::InputsAmountOverflow(1)));
+
+        let result = builder(&[1])?.validate();
+        assert_eq!(
+            result.err(),
+            Some(Error::InputsExceedOutputs {
+                inputs: Token::from_nano(genesis_amount),
+                outputs: Token::from_nano(1),
+            })
+        );
+
+        Ok(())
+    }
+
     #[quickcheck]
     fn prop_splitting_the_genesis_dbc(output_amounts: TinyVec<TinyInt>) -> Result<(), Error> {
         let mut rng = crate::rng::from_seed([0u8; 32]);
 
-        let mut output_amounts =
-            Vec::from_iter(output_amounts.into_iter().map(TinyInt::coerce::<u64>));
+        // note: the TransactionBuilder rejects zero amount outputs.
+        let mut output_amounts = Vec::from_iter(
+            output_amounts
+                .into_iter()
+                .map(TinyInt::coerce::<u64>)
+                .filter(|a| *a > 0),
+        );
         output_amounts
             .push(mock::GenesisMaterial::GENESIS_AMOUNT - output_amounts.iter().sum::<u64>());
 
@@ -146,13 +398,23 @@ mod tests {
         // let invalid_spent_proofs = TinyVec(vec![TinyInt(0)]);
         // let num_decoy_inputs = TinyInt(0);
 
-        let mut input_amounts =
-            Vec::from_iter(input_amounts.into_iter().map(TinyInt::coerce::<u64>));
+        // note: the TransactionBuilder rejects zero amount outputs.
+        let mut input_amounts = Vec::from_iter(
+            input_amounts
+                .into_iter()
+                .map(TinyInt::coerce::<u64>)
+                .filter(|a| *a > 0),
+        );
         input_amounts
             .push(mock::GenesisMaterial::GENESIS_AMOUNT - input_amounts.iter().sum::<u64>());
 
-        let mut output_amounts =
-            Vec::from_iter(output_amounts.into_iter().map(TinyInt::coerce::<u64>));
+        // note: the TransactionBuilder rejects zero amount outputs.
+        let mut output_amounts = Vec::from_iter(
+            output_amounts
+                .into_iter()
+                .map(TinyInt::coerce::<u64>)
+                .filter(|a| *a > 0),
+        );
         output_amounts
             .push(mock::GenesisMaterial::GENESIS_AMOUNT - output_amounts.iter().sum::<u64>());
 
@@ -318,7 +580,7 @@ mod tests {
                             0,
                             SecretKeySet::random(1, &mut rng)
                                 .secret_key_share(1)
-                                .sign(&[0u8; 32]),
+                                .sign([0u8; 32]),
                         ),
                     }
                 }
@@ -393,11 +655,26 @@ mod tests {
         let output1_owner =
             OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
 
-        let dbc_builder = TransactionBuilder::default()
+        // the TransactionBuilder refuses to create an output from nothing.
+        let result = TransactionBuilder::default()
             .add_output_by_amount(Token::from_nano(100), output1_owner.clone())
-            .build(&mut rng)?;
+            .build(&mut rng);
+        assert_eq!(
+            result.err(),
+            Some(Error::OutputsExceedInputs {
+                inputs: Token::zero(),
+                outputs: Token::from_nano(100),
+            })
+        );
+
+        // so we sign the RingCtMaterial ourselves.
+        let (_transaction, revealed_commitments) = RingCtMaterial {
+            inputs: vec![],
+            outputs: vec![Output::new(output1_owner.as_owner().public_key(), 100)],
+        }
+        .sign(&mut rng)?;
 
-        let amount_secrets = AmountSecrets::from(dbc_builder.revealed_commitments[0]);
+        let amount_secrets = AmountSecrets::from(revealed_commitments[0]);
         let secret_key = output1_owner.as_owner().secret_key()?;
 
         let output2_owner =
@@ -439,7 +716,7 @@ mod tests {
     /// 3. modify b's amount secrets.amount to 2000, thereby creating b_fudged
     ///    (which a bad actor could pass to innocent recipient).
     /// 4. Check if the amounts match, using the provided API.
-    ///      assert that APIs report they do not match.
+    ///    assert that APIs report they do not match.
     /// 5. create a tx with (b_fudged) as input, and Dbc (c) with amount 2000 as output.
     /// 6. Attempt to write this tx to the spentbook.
     ///    This will fail because the input and output commitments do not match.
@@ -0,0 +1,302 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{rand::RngCore, Error, Keychain, Owner, Result};
+use hmac::Hmac;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+
+// The BIP39 English wordlist, one word per line, in sorted order.
+const ENGLISH: &str = include_str!("english.txt");
+
+const WORDLIST_LEN: usize = 2048;
+const BITS_PER_WORD: usize = 11;
+const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+const ENTROPY_LENS: [usize; 5] = [16, 20, 24, 28, 32];
+const SEED_SALT_PREFIX: &str = "mnemonic";
+const SEED_ROUNDS: u32 = 2048;
+const SEED_LEN: usize = 64;
+
+/// A mnemonic phrase encoding a master seed, for paper backups of
+/// the keys that own a wallet's Dbcs.
+///
+/// This is BIP39-style: entropy of 128 to 256 bits, plus a checksum of
+/// entropy_bits / 32 bits, is encoded as 12 to 24 words from the BIP39
+/// English wordlist.  As in BIP39, the checksum is derived with sha-256,
+/// so phrases are interchangeable with BIP39 wallets.  The seed is derived
+/// from the phrase as in BIP39.
+///
+/// A Mnemonic is restored into a Keychain (or Owner) via
+/// Mnemonic::to_keychain().
+#[derive(Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    entropy: Vec<u8>,
+}
+
+// do not reveal the entropy in Debug output.
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mnemonic")
+            .field("word_count", &self.word_count())
+            .finish()
+    }
+}
+
+impl Mnemonic {
+    /// generate a Mnemonic with the given number of words from random entropy.
+    ///
+    /// word_count must be one of 12, 15, 18, 21, 24.
+    pub fn generate(word_count: usize, rng: &mut impl RngCore) -> Result<Self> {
+        if !WORD_COUNTS.contains(&word_count) {
+            return Err(Error::MnemonicInvalidWordCount(word_count));
+        }
+        // each 3 words encode 32 bits of entropy plus 1 checksum bit.
+        let mut entropy = vec![0u8; word_count / 3 * 4];
+        rng.fill_bytes(&mut entropy);
+        Self::from_entropy(&entropy)
+    }
+
+    /// create a Mnemonic from entropy of 16, 20, 24, 28 or 32 bytes.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self> {
+        if !ENTROPY_LENS.contains(&entropy.len()) {
+            return Err(Error::MnemonicInvalidEntropyLength(entropy.len()));
+        }
+        Ok(Self {
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    /// parse a Mnemonic from a phrase of whitespace separated words.
+    ///
+    /// Words are matched case-insensitively.
+    pub fn from_phrase(phrase: &str) -> Result<Self> {
+        let wordlist = wordlist();
+        let indexes = phrase
+            .split_whitespace()
+            .map(|word| {
+                let word = word.to_lowercase();
+                wordlist
+                    .binary_search(&word.as_str())
+                    .map_err(|_| Error::MnemonicUnknownWord(word))
+            })
+            .collect::<Result<Vec<usize>>>()?;
+
+        let word_count = indexes.len();
+        if !WORD_COUNTS.contains(&word_count) {
+            return Err(Error::MnemonicInvalidWordCount(word_count));
+        }
+
+        // unpack 11 bits per word into entropy bytes followed by checksum bits.
+        let bits: Vec<bool> = indexes
+            .iter()
+            .flat_map(|i| (0..BITS_PER_WORD).rev().map(move |b| (i >> b) & 1 == 1))
+            .collect();
+        let checksum_len = word_count / 3;
+        let (entropy_bits, checksum_bits) = bits.split_at(bits.len() - checksum_len);
+
+        let entropy: Vec<u8> = entropy_bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, b| (acc << 1) | *b as u8))
+            .collect();
+
+        let mnemonic = Self::from_entropy(&entropy)?;
+        if mnemonic.checksum_bits() != checksum_bits {
+            return Err(Error::MnemonicInvalidChecksum);
+        }
+        Ok(mnemonic)
+    }
+
+    /// returns the words of the phrase
+    pub fn words(&self) -> Vec<&'static str> {
+        let wordlist = wordlist();
+        let bits: Vec<bool> = self
+            .entropy
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |b| (byte >> b) & 1 == 1))
+            .chain(self.checksum_bits())
+            .collect();
+
+        bits.chunks(BITS_PER_WORD)
+            .map(|word| word.iter().fold(0usize, |acc, b| (acc << 1) | *b as usize))
+            .map(|i| wordlist[i])
+            .collect()
+    }
+
+    /// returns the phrase, with words separated by a single space
+    pub fn phrase(&self) -> String {
+        self.words().join(" ")
+    }
+
+    /// returns the number of words in the phrase
+    pub fn word_count(&self) -> usize {
+        self.entropy.len() / 4 * 3
+    }
+
+    /// returns the entropy encoded by the phrase
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    /// returns the master seed.
+    ///
+    /// An optional passphrase (which may be empty) is mixed in, such that
+    /// the phrase alone is not sufficient to restore the keys.
+    ///
+    /// As in BIP39, the seed is derived with PBKDF2-HMAC-SHA512 and 2048
+    /// rounds, from the phrase and a salt of "mnemonic" + passphrase.
+    /// Unlike BIP39, the passphrase is not NFKD normalized, so a non-ascii
+    /// passphrase must be given in the same form each time.
+    pub fn to_seed(&self, passphrase: &str) -> [u8; SEED_LEN] {
+        seed_from_phrase(&self.phrase(), passphrase)
+    }
+
+    /// restore the master Keychain
+    pub fn to_keychain(&self, passphrase: &str) -> Keychain {
+        Keychain::from_seed(&self.to_seed(passphrase))
+    }
+
+    /// restore the master Owner, ie the Owner of the master Keychain.
+    pub fn to_owner(&self, passphrase: &str) -> Owner {
+        self.to_keychain(passphrase).owner()
+    }
+
+    // the first entropy_bits / 32 bits of the sha-256 hash of the entropy.
+    fn checksum_bits(&self) -> Vec<bool> {
+        let hash = Sha256::digest(&self.entropy);
+        (0..self.entropy.len() / 4)
+            .map(|i| (hash[i / 8] >> (7 - i % 8)) & 1 == 1)
+            .collect()
+    }
+}
+
+fn seed_from_phrase(phrase: &str, passphrase: &str) -> [u8; SEED_LEN] {
+    let salt = format!("{}{}", SEED_SALT_PREFIX, passphrase);
+    let mut seed = [0u8; SEED_LEN];
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(phrase.as_bytes(), salt.as_bytes(), SEED_ROUNDS, &mut seed);
+    seed
+}
+
+fn wordlist() -> Vec<&'static str> {
+    let wordlist: Vec<&'static str> = ENGLISH.lines().collect();
+    debug_assert_eq!(wordlist.len(), WORDLIST_LEN);
+    wordlist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phrase_round_trip() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+
+        for word_count in [12, 15, 18, 21, 24] {
+            let mnemonic = Mnemonic::generate(word_count, &mut rng)?;
+            assert_eq!(mnemonic.words().len(), word_count);
+
+            let restored = Mnemonic::from_phrase(&mnemonic.phrase().to_uppercase())?;
+            assert_eq!(restored, mnemonic);
+            assert_eq!(
+                restored.to_owner("passphrase"),
+                mnemonic.to_owner("passphrase")
+            );
+            assert_ne!(restored.to_owner(""), mnemonic.to_owner("passphrase"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn seed_matches_bip39_test_vector() {
+        // from the BIP39 test vectors, which use the passphrase "TREZOR".
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        assert_eq!(
+            hex::encode(seed_from_phrase(phrase, "TREZOR")),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+    }
+
+    #[test]
+    fn zero_entropy_encodes_to_first_word() -> Result<()> {
+        let mnemonic = Mnemonic::from_entropy(&[0u8; 16])?;
+        let words = mnemonic.words();
+        assert!(words[..11].iter().all(|w| *w == "abandon"));
+        assert_eq!(words[11], "about");
+        Ok(())
+    }
+
+    #[test]
+    fn bip39_test_vectors_restore() -> Result<()> {
+        // (entropy, phrase, seed) from the BIP39 test vectors, which use the
+        // passphrase "TREZOR".
+        let vectors = [
+            (
+                "00000000000000000000000000000000",
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            ),
+            (
+                "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+                "legal winner thank year wave sausage worth useful legal winner thank yellow",
+                "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+            ),
+            (
+                "ffffffffffffffffffffffffffffffff",
+                "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+                "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+            ),
+            (
+                "9e885d952ad362caeb4efe34a8e91bd2",
+                "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+                "274ddc525802f7c828d8ef7ddbcdc5304e87ac3535913611fbbfa986d0c9e5476c91689f9c8a54fd55bd38606aa6a8595ad213d4c9c9f9aca3fb217069a41028",
+            ),
+        ];
+
+        for (entropy, phrase, seed) in vectors.iter() {
+            let mnemonic = Mnemonic::from_phrase(phrase)?;
+            assert_eq!(hex::encode(mnemonic.entropy()), *entropy);
+            assert_eq!(hex::encode(mnemonic.to_seed("TREZOR")), *seed);
+            assert_eq!(mnemonic.phrase(), *phrase);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_phrases_are_rejected() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let mnemonic = Mnemonic::generate(12, &mut rng)?;
+        let mut words = mnemonic.words();
+
+        assert_eq!(
+            Mnemonic::from_phrase(&words[..11].join(" ")),
+            Err(Error::MnemonicInvalidWordCount(11))
+        );
+
+        let mut unknown = words.clone();
+        unknown[3] = "notaword";
+        assert_eq!(
+            Mnemonic::from_phrase(&unknown.join(" ")),
+            Err(Error::MnemonicUnknownWord("notaword".to_string()))
+        );
+
+        // the low bits of the last word are checksum bits, so flipping
+        // the lowest bit changes the checksum but not the entropy.
+        let wordlist = wordlist();
+        let last = wordlist.iter().position(|w| *w == words[11]).unwrap();
+        words[11] = wordlist[last ^ 1];
+        assert_eq!(
+            Mnemonic::from_phrase(&words.join(" ")),
+            Err(Error::MnemonicInvalidChecksum)
+        );
+
+        assert_eq!(
+            Mnemonic::generate(13, &mut rng),
+            Err(Error::MnemonicInvalidWordCount(13))
+        );
+        Ok(())
+    }
+}
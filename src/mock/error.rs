@@ -5,6 +5,7 @@
 // under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
+use crate::Token;
 use thiserror::Error;
 
 #[cfg(feature = "serde")]
@@ -19,4 +20,10 @@ pub enum Error {
 
     #[error("The transaction input has {0:?} public keys but found {1:?} matching outputs in spentbook.")]
     RingSizeMismatch(usize, usize),
+
+    #[error("The transaction fee {fee} is less than the minimum fee {min_fee}")]
+    FeeTooLow { fee: Token, min_fee: Token },
+
+    #[error("The transaction pays a fee that was not declared")]
+    FeeNotDeclared,
 }
@@ -80,14 +80,14 @@ impl GenesisBuilder {
     )> {
         // note: rng is necessary for RingCtMaterial::sign().
 
-        let genesis_material = GenesisMaterial::default();
+        let genesis_material = GenesisMaterial::new(rng);
         let mut dbc_builder = TransactionBuilder::default()
             .add_input(genesis_material.ringct_material.inputs[0].clone())
             .add_output(
                 genesis_material.ringct_material.outputs[0].clone(),
                 genesis_material.owner_once.clone(),
             )
-            .build(rng)?;
+            .build(&mut *rng)?;
 
         for (key_image, tx) in dbc_builder.inputs() {
             for spentbook_node in self.spentbook_nodes.iter_mut() {
@@ -102,7 +102,7 @@ impl GenesisBuilder {
         let spentbook_node_arbitrary = &self.spentbook_nodes[0];
 
         let (genesis_dbc, _owner_once, amount_secrets) = dbc_builder
-            .build(&spentbook_node_arbitrary.key_manager)?
+            .build_with_rng(&spentbook_node_arbitrary.key_manager, rng)?
             .into_iter()
             .next()
             .unwrap();
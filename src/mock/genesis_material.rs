@@ -6,7 +6,10 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{KeyImage, Owner, OwnerOnce};
+use crate::{
+    rand::{distributions::Standard, Rng, RngCore},
+    KeyImage, Owner, OwnerOnce,
+};
 use bls_ringct::{
     blstrs::Scalar,
     group::Curve,
@@ -33,6 +36,15 @@ impl Default for GenesisMaterial {
     ///
     /// It uses GenesisMaterial::GENESIS_AMOUNT by default
     fn default() -> Self {
+        Self::new(&mut crate::rng::thread_rng())
+    }
+}
+
+impl GenesisMaterial {
+    /// generate the GenesisMaterial, with the Genesis Dbc owner key from rng.
+    ///
+    /// It uses GenesisMaterial::GENESIS_AMOUNT
+    pub fn new(rng: &mut impl RngCore) -> Self {
         // Make a secret key for the input of Genesis Tx. (fictional Dbc)
         // note that this represents the one-time-use key.
         // (we have no need for the base key)
@@ -41,7 +53,7 @@ impl Default for GenesisMaterial {
 
         // Make a secret key for the output of Genesis Tx. (The Genesis Dbc)
         // note that this represents the base key, from which one-time-use key is derived.
-        let output_sk = blsttc::SecretKey::random();
+        let output_sk: blsttc::SecretKey = rng.sample(Standard);
 
         // OwnerOnce ties together the base key and one-time-use key.
         let output_owner_once = OwnerOnce {
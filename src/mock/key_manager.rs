@@ -8,6 +8,8 @@
 
 use crate::{Error, Hash, IndexedSignatureShare, Result};
 use blsttc::{serde_impl::SerdeSecret, SecretKeyShare};
+// note: unused within the crate, but kept as part of the mock API.
+#[allow(unused_imports)]
 pub use blsttc::{PublicKey, PublicKeySet, Signature};
 use std::collections::HashSet;
 
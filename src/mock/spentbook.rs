@@ -19,7 +19,8 @@ use super::GenesisMaterial;
 use crate::{
     mock,
     rand::{prelude::IteratorRandom, RngCore},
-    Commitment, Hash, KeyImage, Result, SpentProofContent, SpentProofShare,
+    Commitment, DecoyCandidate, DecoyProvider, Fee, Hash, KeyImage, Result, SpentBookClient,
+    SpentProofContent, SpentProofShare, Token,
 };
 
 /// This is a mock SpentBook used for our test cases. A proper implementation
@@ -49,10 +50,13 @@ pub struct SpentBookNode {
     pub key_manager: mock::KeyManager,
 
     pub transactions: HashMap<Hash, RingCtTransaction>,
+    pub transaction_order: Vec<Hash>, // tx_hash of each tx, in the order logged.
     pub key_images: BTreeMap<KeyImage, Hash>,
     pub outputs: BTreeMap<PublicKey, OutputProof>,
 
     pub genesis: (KeyImage, Commitment), // genesis input (keyimage, public_commitment)
+
+    pub min_fee: Token, // minimum fee a tx must pay in order to be logged.
 }
 
 impl From<mock::KeyManager> for SpentBookNode {
@@ -67,9 +71,11 @@ impl From<mock::KeyManager> for SpentBookNode {
         Self {
             key_manager,
             transactions: Default::default(),
+            transaction_order: Default::default(),
             key_images: Default::default(),
             outputs: Default::default(),
             genesis: (genesis_material.input_key_image, public_commitment),
+            min_fee: Token::zero(),
         }
     }
 }
@@ -92,11 +98,47 @@ impl SpentBookNode {
         self.key_images.contains_key(key_image)
     }
 
+    /// set the minimum fee that a tx must pay in order to be logged.
+    pub fn set_min_fee(&mut self, min_fee: Token) {
+        self.min_fee = min_fee;
+    }
+
+    /// log a tx that pays no fee.
+    ///
+    /// The fee amount cannot be inferred from a fee output's commitment, so
+    /// a tx that has a fee output is rejected with Error::FeeNotDeclared.
+    /// Such a tx must be logged with ::log_spent_with_fee() instead.
     pub fn log_spent(
         &mut self,
         key_image: KeyImage,
         tx: RingCtTransaction,
     ) -> Result<SpentProofShare> {
+        let fee_pk = Fee::public_key(&tx);
+        if tx
+            .outputs
+            .iter()
+            .any(|o| PublicKey::from(*o.public_key()) == fee_pk)
+        {
+            return Err(crate::mock::Error::FeeNotDeclared.into());
+        }
+        self.log_spent_with_fee(key_image, tx, &Fee::zero())
+    }
+
+    /// log a tx that pays the given fee.
+    pub fn log_spent_with_fee(
+        &mut self,
+        key_image: KeyImage,
+        tx: RingCtTransaction,
+        fee: &Fee,
+    ) -> Result<SpentProofShare> {
+        if fee.amount() < self.min_fee {
+            return Err(crate::mock::Error::FeeTooLow {
+                fee: fee.amount(),
+                min_fee: self.min_fee,
+            }
+            .into());
+        }
+        fee.verify(&tx)?;
         self.log_spent_worker(key_image, tx, true)
     }
 
@@ -185,6 +227,9 @@ impl SpentBookNode {
 
         if *existing_tx_hash == tx_hash {
             // Add tx_hash:tx to transaction entries. (primary data store)
+            if !self.transactions.contains_key(&tx_hash) {
+                self.transaction_order.push(tx_hash);
+            }
             let existing_tx = self.transactions.entry(tx_hash).or_insert_with(|| tx);
 
             // Add public_key:output_proof to public_key index.
@@ -215,18 +260,43 @@ impl SpentBookNode {
     // return a list of DecoyInput built from randomly
     // selected OutputProof, from set of all OutputProof in Spentbook.
     pub fn random_decoys(&self, target_num: usize, rng: &mut impl RngCore) -> Vec<DecoyInput> {
-        // Get a unique list of all OutputProof
-        // note: Tx are duplicated in Spentbook. We use a BTreeMap
-        //       with KeyImage to dedup.
-        // note: Once we refactor to avoid Tx duplication, this
-        //       map can go away.
-        let outputs_unique: BTreeMap<PublicKey, OutputProof> = self
-            .transactions
-            .values()
-            .flat_map(|tx| {
+        self.random_decoy_candidates(target_num, rng)
+            .into_iter()
+            .map(|c| c.decoy)
+            .collect()
+    }
+
+    // as ::random_decoys(), but with the age and transaction of each output,
+    // for use by a DecoySelector.
+    pub fn random_decoy_candidates(
+        &self,
+        target_num: usize,
+        rng: &mut impl RngCore,
+    ) -> Vec<DecoyCandidate> {
+        // note: fee outputs can never be spent, so are excluded.
+        let num_txs = self.transaction_order.len();
+        let outputs_unique: BTreeMap<PublicKey, DecoyCandidate> = self
+            .transaction_order
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, tx_hash)| {
+                let tx = &self.transactions[tx_hash];
+                let fee_pk = Fee::public_key(tx);
                 tx.outputs
                     .iter()
-                    .map(|o| ((*o.public_key()).into(), o.clone()))
+                    .map(|o| (PublicKey::from(*o.public_key()), o))
+                    .filter(move |(pk, _)| *pk != fee_pk)
+                    .map(move |(pk, o)| {
+                        let candidate = DecoyCandidate {
+                            decoy: DecoyInput {
+                                public_key: *o.public_key(),
+                                commitment: o.commitment(),
+                            },
+                            age: Some((num_txs - idx - 1) as u64),
+                            transaction_hash: Some(*tx_hash),
+                        };
+                        (pk, candidate)
+                    })
             })
             .collect();
 
@@ -236,13 +306,24 @@ impl SpentBookNode {
             outputs_unique.len()
         };
         outputs_unique
-            .into_iter()
+            .into_values()
             .choose_multiple(rng, num_choose)
-            .into_iter()
-            .map(|(_, o)| DecoyInput {
-                public_key: *o.public_key(),
-                commitment: o.commitment(),
-            })
-            .collect()
+    }
+}
+
+impl DecoyProvider for SpentBookNode {
+    fn fetch_decoys(&self, count: usize, mut rng: &mut dyn RngCore) -> Vec<DecoyCandidate> {
+        self.random_decoy_candidates(count, &mut rng)
+    }
+}
+
+impl SpentBookClient for SpentBookNode {
+    fn log_spent_shares(
+        &mut self,
+        key_image: KeyImage,
+        tx: RingCtTransaction,
+        fee: &Fee,
+    ) -> Result<Vec<SpentProofShare>> {
+        Ok(vec![self.log_spent_with_fee(key_image, tx, fee)?])
     }
 }
@@ -0,0 +1,1232 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{CryptoRng, RngCore},
+    AmountSecrets, Dbc, DbcBuilder, DecoyInput, DerivationIndex, Error, Fee, KeyImage,
+    MlsagMaterial, OutputMetadataMap, OutputOwnerMap, PublicKey, Result, RevealedCommitment,
+    RingCtMaterial, RingCtTransaction, Token, TrueInput,
+};
+use bls_ringct::{
+    bls_bulletproofs::PedersenGens,
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    mlsag::MlsagSignature,
+};
+use blsttc::{serde_impl::SerdeSecret, DecryptionShare, PublicKeySet, SecretKeyShare};
+use std::collections::BTreeMap;
+use tiny_keccak::{Hasher, Sha3};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const DLEQ_TAG: &[u8] = b"sn_dbc multisig key image share";
+const BINDING_TAG: &[u8] = b"sn_dbc multisig nonce binding";
+
+/// One party's share of the secret key of a Multisig Owner.
+///
+/// A Multisig Owner is a PublicKeySet.  Any threshold+1 parties holding
+/// SecretKeyShares can together decrypt the derivation index and
+/// AmountSecrets of a Dbc, and sign to spend it.  No party ever learns
+/// the secret key.
+///
+/// Spending takes two rounds, coordinated by any party (or none):
+///   1. each signing party calls ::commit() and sends the resulting
+///      MlsagCommitmentShare to the coordinator, who combines them
+///      into a MultisigInput for TransactionBuilder::add_multisig_input().
+///   2. TransactionBuilder::build_multisig() returns a MultisigSigningSession,
+///      whose MlsagChallenges are sent to the same parties.  Each party
+///      calls ::sign() and returns an MlsagSignatureShare.
+///
+/// An MlsagChallenge carries the whole transaction.  Before responding,
+/// each party checks that the transaction pays only the outputs and fee it
+/// approves, and recomputes its challenge from the ring.  As in FROST, each
+/// party commits to two nonces, which are bound to the transaction and to
+/// the commitments of all signing parties, so that a coordinator cannot
+/// combine commitments from concurrent sessions to forge a signature.
+///
+/// Once all shares are added, MultisigSigningSession::finalize() yields
+/// the usual DbcBuilder.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct MultisigOwnerShare {
+    index: usize,
+    secret_key_share: SerdeSecret<SecretKeyShare>,
+}
+
+impl MultisigOwnerShare {
+    /// create a MultisigOwnerShare from the SecretKeyShare with the given index.
+    pub fn new(index: usize, secret_key_share: SecretKeyShare) -> Self {
+        Self {
+            index,
+            secret_key_share: SerdeSecret(secret_key_share),
+        }
+    }
+
+    /// returns the share index
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// returns this party's DecryptionShare of the derivation index of a Dbc.
+    ///
+    /// See Dbc::derivation_index_by_shares()
+    pub fn derivation_index_share(&self, dbc: &Dbc) -> Result<DecryptionShare> {
+        self.check_owner(dbc)?;
+        self.secret_key_share
+            .decrypt_share(&dbc.content.owner_derivation_cipher)
+            .ok_or(Error::DecryptionBySecretKeyFailed)
+    }
+
+    /// returns this party's DecryptionShare of the AmountSecrets of a Dbc.
+    ///
+    /// See Dbc::amount_secrets_by_shares()
+    pub fn amount_secrets_share(
+        &self,
+        dbc: &Dbc,
+        derivation_index: &DerivationIndex,
+    ) -> Result<DecryptionShare> {
+        self.check_owner(dbc)?;
+        self.secret_key_share
+            .derive_child(derivation_index)
+            .decrypt_share(&dbc.content.amount_secrets_cipher)
+            .ok_or(Error::DecryptionBySecretKeyFailed)
+    }
+
+    /// Round 1 of signing: generates secret nonces and a commitment share
+    /// for spending a Dbc.
+    ///
+    /// The commitment share includes this party's share of the key image,
+    /// with a proof that it was formed with this party's secret key share.
+    ///
+    /// The nonce must be kept by this party and used exactly once, in ::sign().
+    pub fn commit(
+        &self,
+        dbc: &Dbc,
+        derivation_index: &DerivationIndex,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<(MlsagNonce, MlsagCommitmentShare)> {
+        self.check_owner(dbc)?;
+        let public_key = dbc.owner_base().derive(derivation_index).public_key();
+        let secret = self.one_time_secret(derivation_index);
+
+        let nonce = MlsagNonce {
+            index: self.index,
+            public_key,
+            derivation_index: *derivation_index,
+            hiding: Scalar::random(&mut rng),
+            binding: Scalar::random(&mut rng),
+        };
+        let commitment_share = MlsagCommitmentShare {
+            index: self.index,
+            public_key,
+            key_image_share: (hashed_public_key(&public_key) * secret).to_affine(),
+            key_image_proof: DleqProof::new(&public_key, secret, &mut rng),
+            hiding_commitment: nonce_commitment(&public_key, nonce.hiding),
+            binding_commitment: nonce_commitment(&public_key, nonce.binding),
+        };
+        Ok((nonce, commitment_share))
+    }
+
+    /// Round 2 of signing: responds to a challenge for the input committed to
+    /// with the given nonce.  The nonce is consumed.
+    ///
+    /// approved_outputs are the (one-time) PublicKey and amount of each
+    /// output, excluding any fee output, as approved by this party.  The
+    /// outputs of the transaction must match them exactly (in any order),
+    /// and the transaction must pay exactly approved_fee.
+    ///
+    /// The challenge is recomputed from the transaction and the commitment
+    /// shares of the signing parties, so a share is only ever given for
+    /// a transaction that this party has checked.
+    pub fn sign(
+        &self,
+        nonce: MlsagNonce,
+        challenge: &MlsagChallenge,
+        approved_outputs: &[(PublicKey, Token)],
+        approved_fee: Token,
+    ) -> Result<MlsagSignatureShare> {
+        let public_key = nonce.public_key;
+        let secret = self.one_time_secret(&nonce.derivation_index);
+
+        let commitment_shares: BTreeMap<usize, &MlsagCommitmentShare> = challenge
+            .commitment_shares
+            .iter()
+            .map(|s| (s.index, s))
+            .collect();
+        let own_share_matches = match commitment_shares.get(&self.index) {
+            Some(share) => {
+                share.hiding_commitment == nonce_commitment(&public_key, nonce.hiding)
+                    && share.binding_commitment == nonce_commitment(&public_key, nonce.binding)
+                    && share.key_image_share
+                        == (hashed_public_key(&public_key) * secret).to_affine()
+            }
+            None => false,
+        };
+        if nonce.index != self.index
+            || !own_share_matches
+            || commitment_shares.len() != challenge.commitment_shares.len()
+            || commitment_shares
+                .values()
+                .any(|s| s.public_key != public_key)
+        {
+            return Err(Error::MultisigShareMismatch);
+        }
+
+        let transaction = &challenge.transaction;
+        let mlsag = transaction
+            .mlsags
+            .get(challenge.mlsag_idx)
+            .ok_or(Error::MultisigChallengeInvalid)?;
+        let pi = mlsag
+            .ring
+            .iter()
+            .position(|(pk, _)| PublicKey::from(*pk) == public_key)
+            .ok_or(Error::MultisigShareMismatch)?;
+
+        challenge.check_outputs(approved_outputs, approved_fee)?;
+
+        let lagrange_coefficients = lagrange_coefficients(commitment_shares.keys().copied());
+        let key_image = commitment_shares
+            .values()
+            .map(|s| G1Projective::from(s.key_image_share) * lagrange_coefficients[&s.index])
+            .fold(G1Projective::identity(), |sum, p| sum + p);
+        if key_image.to_affine() != mlsag.key_image {
+            return Err(Error::MultisigChallengeInvalid);
+        }
+
+        let msg = transaction.gen_message();
+        let binding_factors =
+            binding_factors(&msg, &public_key, commitment_shares.values().copied());
+        let group_commitment =
+            group_commitment(commitment_shares.values().copied(), &binding_factors);
+        let c_pi = ring_challenge(mlsag, &msg, pi, group_commitment)?;
+
+        Ok(MlsagSignatureShare {
+            index: self.index,
+            public_key,
+            response: nonce.hiding + binding_factors[&self.index] * nonce.binding
+                - lagrange_coefficients[&self.index] * c_pi * secret,
+        })
+    }
+
+    // returns the share of the one-time-use secret key as a Scalar.
+    fn one_time_secret(&self, derivation_index: &DerivationIndex) -> Scalar {
+        let bytes = self
+            .secret_key_share
+            .derive_child(derivation_index)
+            .to_bytes();
+        // a SecretKeyShare is always a valid (big endian) Scalar.
+        Scalar::from_bytes_be(&bytes).unwrap()
+    }
+
+    fn check_owner(&self, dbc: &Dbc) -> Result<()> {
+        let public_key_set = dbc.owner_base().public_key_set()?;
+        if public_key_set.public_key_share(self.index)
+            != self.secret_key_share.inner().public_key_share()
+        {
+            return Err(Error::SecretKeyDoesNotMatchPublicKey);
+        }
+        Ok(())
+    }
+}
+
+/// A party's secret nonces for one input, from MultisigOwnerShare::commit().
+pub struct MlsagNonce {
+    index: usize,
+    public_key: PublicKey,
+    derivation_index: DerivationIndex,
+    hiding: Scalar,
+    binding: Scalar,
+}
+
+/// A proof that two points have the same discrete log, with respect to the
+/// generator and to the hashed public key of an input.
+///
+/// This proves that a key image share was formed with the secret key share
+/// of the party's public key share.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DleqProof {
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+impl DleqProof {
+    fn new(public_key: &PublicKey, secret: Scalar, mut rng: impl RngCore + CryptoRng) -> Self {
+        let hashed_pk = hashed_public_key(public_key);
+        let k = Scalar::random(&mut rng);
+        let challenge = dleq_hash(
+            public_key,
+            G1Projective::generator() * secret,
+            hashed_pk * secret,
+            G1Projective::generator() * k,
+            hashed_pk * k,
+        );
+        Self {
+            challenge,
+            response: k - challenge * secret,
+        }
+    }
+
+    /// verify that key_image_share is formed with the secret key of
+    /// public_key_share, for the input with the given public key.
+    pub fn verify(
+        &self,
+        public_key: &PublicKey,
+        public_key_share: G1Affine,
+        key_image_share: G1Affine,
+    ) -> bool {
+        let public_key_share = G1Projective::from(public_key_share);
+        let key_image_share = G1Projective::from(key_image_share);
+        let challenge = dleq_hash(
+            public_key,
+            public_key_share,
+            key_image_share,
+            G1Projective::generator() * self.response + public_key_share * self.challenge,
+            hashed_public_key(public_key) * self.response + key_image_share * self.challenge,
+        );
+        challenge == self.challenge
+    }
+}
+
+/// A party's key image share and nonce commitments for one input.
+///
+/// Each nonce commitment is the nonce times the generator and times the
+/// hashed public key of the input.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsagCommitmentShare {
+    pub index: usize,
+    pub public_key: PublicKey,
+    pub key_image_share: G1Affine,
+    pub key_image_proof: DleqProof,
+    pub hiding_commitment: (G1Affine, G1Affine),
+    pub binding_commitment: (G1Affine, G1Affine),
+}
+
+/// The challenge that each signing party must respond to, for one input.
+///
+/// It carries the transaction, whose ring signature for the input is
+/// complete but for the response of the true input, and the openings of
+/// the output commitments, so that each party can check what it signs.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct MlsagChallenge {
+    pub transaction: RingCtTransaction,
+    pub mlsag_idx: usize,
+    pub commitment_shares: Vec<MlsagCommitmentShare>,
+    pub output_openings: Vec<RevealedCommitment>,
+}
+
+impl MlsagChallenge {
+    /// returns the message signed by the ring signatures of the transaction
+    pub fn message(&self) -> Vec<u8> {
+        self.transaction.gen_message()
+    }
+
+    /// returns the (one-time) PublicKey and amount of each output, excluding
+    /// any fee output, and the fee.
+    ///
+    /// Each amount is checked against the output commitment.
+    pub fn outputs(&self) -> Result<(Vec<(PublicKey, Token)>, Token)> {
+        let pc_gens = PedersenGens::default();
+        let fee_pk = Fee::public_key(&self.transaction);
+
+        let mut outputs = vec![];
+        let mut fee = Token::zero();
+        for output in self.transaction.outputs.iter() {
+            let opening = self
+                .output_openings
+                .iter()
+                .find(|r| r.commit(&pc_gens).to_affine() == output.commitment())
+                .ok_or(Error::MultisigOutputsNotApproved)?;
+            let pk = PublicKey::from(*output.public_key());
+            if pk == fee_pk {
+                fee = Token::from_nano(opening.value);
+            } else {
+                outputs.push((pk, Token::from_nano(opening.value)));
+            }
+        }
+        Ok((outputs, fee))
+    }
+
+    fn check_outputs(
+        &self,
+        approved_outputs: &[(PublicKey, Token)],
+        approved_fee: Token,
+    ) -> Result<()> {
+        let (mut outputs, fee) = self.outputs()?;
+        let mut approved = approved_outputs.to_vec();
+        outputs.sort();
+        approved.sort();
+        if outputs != approved || fee != approved_fee {
+            return Err(Error::MultisigOutputsNotApproved);
+        }
+        Ok(())
+    }
+}
+
+/// A party's response to an MlsagChallenge.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsagSignatureShare {
+    pub index: usize,
+    pub public_key: PublicKey,
+    pub response: Scalar,
+}
+
+/// A Multisig owned Dbc to be spent, with the commitments of the signing
+/// parties.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct MultisigInput {
+    public_key_set: PublicKeySet,
+    revealed_commitment: RevealedCommitment,
+    key_image: G1Affine,
+    commitment_shares: BTreeMap<usize, MlsagCommitmentShare>,
+}
+
+impl MultisigInput {
+    /// create a MultisigInput from a Dbc, its derivation index and AmountSecrets,
+    /// and threshold+1 MlsagCommitmentShares.
+    ///
+    /// The parties whose commitment shares are used here must sign the
+    /// resulting challenge.  Any shares beyond threshold+1 are ignored.
+    ///
+    /// The key image share of each party is verified against its proof.
+    pub fn new(
+        dbc: &Dbc,
+        derivation_index: &DerivationIndex,
+        amount_secrets: AmountSecrets,
+        commitment_shares: impl IntoIterator<Item = MlsagCommitmentShare>,
+    ) -> Result<Self> {
+        let public_key_set = dbc
+            .owner_base()
+            .public_key_set()?
+            .derive_child(derivation_index);
+        let public_key = public_key_set.public_key();
+
+        let commitment_shares: BTreeMap<usize, MlsagCommitmentShare> = commitment_shares
+            .into_iter()
+            .map(|s| (s.index, s))
+            .collect();
+        if commitment_shares
+            .values()
+            .any(|s| s.public_key != public_key)
+        {
+            return Err(Error::MultisigShareMismatch);
+        }
+
+        let required = public_key_set.threshold() + 1;
+        if commitment_shares.len() < required {
+            return Err(Error::MultisigNotEnoughShares {
+                required,
+                provided: commitment_shares.len(),
+            });
+        }
+        let commitment_shares: BTreeMap<usize, MlsagCommitmentShare> =
+            commitment_shares.into_iter().take(required).collect();
+
+        for share in commitment_shares.values() {
+            let public_key_share = public_key_share(&public_key_set, share.index)?;
+            if !share
+                .key_image_proof
+                .verify(&public_key, public_key_share, share.key_image_share)
+            {
+                return Err(Error::MultisigKeyImageShareInvalid(share.index));
+            }
+        }
+
+        let lagrange_coefficients = lagrange_coefficients(commitment_shares.keys().copied());
+        let key_image = commitment_shares
+            .values()
+            .map(|s| G1Projective::from(s.key_image_share) * lagrange_coefficients[&s.index])
+            .fold(G1Projective::identity(), |sum, p| sum + p)
+            .to_affine();
+
+        Ok(Self {
+            public_key_set,
+            revealed_commitment: amount_secrets.into(),
+            key_image,
+            commitment_shares,
+        })
+    }
+
+    /// returns the one-time-use PublicKey of the input
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key_set.public_key()
+    }
+
+    /// returns the KeyImage of the input
+    pub fn key_image(&self) -> KeyImage {
+        self.key_image.into()
+    }
+
+    /// returns the amount of the input
+    pub fn amount(&self) -> Token {
+        Token::from_nano(self.revealed_commitment.value)
+    }
+
+    /// returns the indexes of the parties that must sign
+    pub fn signers(&self) -> impl Iterator<Item = &usize> {
+        self.commitment_shares.keys()
+    }
+
+    pub(crate) fn revealed_commitment(&self) -> &RevealedCommitment {
+        &self.revealed_commitment
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+struct PendingMlsag {
+    mlsag_idx: usize,
+    pi: usize,
+    public_key_set: PublicKeySet,
+    challenge: Scalar,
+    commitment_shares: BTreeMap<usize, MlsagCommitmentShare>,
+    binding_factors: BTreeMap<usize, Scalar>,
+    responses: BTreeMap<usize, Scalar>,
+}
+
+/// A transaction with Multisig inputs, awaiting signature shares.
+///
+/// All other parts of the transaction, including the ring signatures of
+/// any non-multisig inputs, are final.  See MultisigOwnerShare for the
+/// signing protocol.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct MultisigSigningSession {
+    dbc_builder: DbcBuilder,
+    pending: Vec<PendingMlsag>,
+}
+
+impl MultisigSigningSession {
+    // Signs a transaction whose inputs are the ringct_material inputs followed
+    // by the multisig inputs, leaving the multisig ring signatures incomplete.
+    //
+    // RingCtMaterial::sign() requires the secret keys of all inputs.  So the
+    // outputs are instead signed against a single placeholder input of the
+    // total amount, which yields the output range proofs and output blinding
+    // factors.  The pseudo-commitments of the real inputs are then chosen
+    // to have the same blinding factor sum, so that inputs sum to outputs.
+    pub(crate) fn new(
+        ringct_material: RingCtMaterial,
+        multisig_inputs: Vec<(MultisigInput, Vec<DecoyInput>)>,
+        output_owner_map: OutputOwnerMap,
+        output_metadata: OutputMetadataMap,
+        fee_idx: Option<usize>,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Self> {
+        if ringct_material.outputs.is_empty() {
+            return Err(Error::TransactionMustHaveAnOutput);
+        }
+        let pc_gens = PedersenGens::default();
+
+        let input_commitments: Vec<&RevealedCommitment> = ringct_material
+            .inputs
+            .iter()
+            .map(|m| m.true_input.revealed_commitment())
+            .chain(multisig_inputs.iter().map(|(i, _)| i.revealed_commitment()))
+            .collect();
+        let total_amount = input_commitments
+            .iter()
+            .try_fold(0u64, |sum, r| sum.checked_add(r.value))
+            .ok_or(Error::ExcessiveTokenValue)?;
+
+        let placeholder = MlsagMaterial::new(
+            TrueInput::new(
+                Scalar::random(&mut rng),
+                RevealedCommitment::from_value(total_amount, &mut rng),
+            ),
+            vec![],
+            &mut rng,
+        );
+        let (placeholder_tx, revealed_commitments) = RingCtMaterial {
+            inputs: vec![placeholder],
+            outputs: ringct_material.outputs.clone(),
+        }
+        .sign(&mut rng)?;
+
+        let mut revealed_pseudo_commitments: Vec<RevealedCommitment> = input_commitments
+            .iter()
+            .map(|r| RevealedCommitment::from_value(r.value, &mut rng))
+            .collect();
+        let output_blinding_sum = sum_blindings(&revealed_commitments);
+        match revealed_pseudo_commitments.split_last_mut() {
+            Some((last, others)) => {
+                last.blinding = output_blinding_sum - sum_blindings(others);
+            }
+            None => return Err(bls_ringct::Error::TransactionMustHaveAnInput.into()),
+        }
+
+        // form the rings, with the true input of each multisig ring placed
+        // at a random index.
+        let mut mlsags: Vec<MlsagSignature> = Vec::new();
+        for m in ringct_material.inputs.iter() {
+            mlsags.push(unsigned_mlsag(
+                m.public_keys(),
+                m.commitments(&pc_gens),
+                m.true_input.key_image().to_affine(),
+                revealed_pseudo_commitments[mlsags.len()].commit(&pc_gens),
+            ));
+        }
+        let mut pis: Vec<usize> = Vec::new();
+        for (input, decoy_inputs) in multisig_inputs.iter() {
+            let pi = rng.next_u32() as usize % (decoy_inputs.len() + 1);
+            let mut public_keys: Vec<G1Affine> =
+                decoy_inputs.iter().map(DecoyInput::public_key).collect();
+            let mut commitments: Vec<G1Affine> =
+                decoy_inputs.iter().map(DecoyInput::commitment).collect();
+            public_keys.insert(pi, input.public_key().into());
+            commitments.insert(pi, input.revealed_commitment.commit(&pc_gens).to_affine());
+
+            mlsags.push(unsigned_mlsag(
+                public_keys,
+                commitments,
+                input.key_image,
+                revealed_pseudo_commitments[mlsags.len()].commit(&pc_gens),
+            ));
+            pis.push(pi);
+        }
+
+        let mut transaction = RingCtTransaction {
+            mlsags,
+            outputs: placeholder_tx.outputs,
+        };
+        let msg = transaction.gen_message();
+
+        for (idx, m) in ringct_material.inputs.iter().enumerate() {
+            transaction.mlsags[idx] = m.sign(&msg, &revealed_pseudo_commitments[idx], &pc_gens);
+        }
+
+        // sign the multisig rings, except for the response of the true input's
+        // public key, which requires the signature shares.
+        #[allow(non_snake_case)]
+        let G1 = G1Projective::generator();
+        let mut pending = Vec::new();
+        for (k, ((input, _), pi)) in multisig_inputs.into_iter().zip(pis).enumerate() {
+            let mlsag_idx = ringct_material.inputs.len() + k;
+            let mlsag = &mut transaction.mlsags[mlsag_idx];
+            let ring_len = mlsag.ring.len();
+            let key_image = G1Projective::from(mlsag.key_image);
+
+            let public_key = input.public_key();
+            let binding_factors =
+                binding_factors(&msg, &public_key, input.commitment_shares.values());
+            let (nonce_commitment_g, nonce_commitment_h) =
+                group_commitment(input.commitment_shares.values(), &binding_factors);
+
+            let alpha = Scalar::random(&mut rng);
+            let mut r: Vec<(Scalar, Scalar)> = (0..ring_len)
+                .map(|_| (Scalar::random(&mut rng), Scalar::random(&mut rng)))
+                .collect();
+            let mut c: Vec<Scalar> = vec![Scalar::zero(); ring_len];
+
+            c[(pi + 1) % ring_len] =
+                c_hash(&msg, nonce_commitment_g, G1 * alpha, nonce_commitment_h);
+            for offset in 1..ring_len {
+                let n = (pi + offset) % ring_len;
+                let (pk, hidden_commitment) = mlsag.ring[n];
+                c[(n + 1) % ring_len] = c_hash(
+                    &msg,
+                    G1 * r[n].0 + pk * c[n],
+                    G1 * r[n].1 + hidden_commitment * c[n],
+                    bls_ringct::hash_to_curve(pk.into()) * r[n].0 + key_image * c[n],
+                );
+            }
+
+            let blinding_delta = input.revealed_commitment.blinding
+                - revealed_pseudo_commitments[mlsag_idx].blinding;
+            r[pi].1 = alpha - c[pi] * blinding_delta;
+
+            mlsag.c0 = c[0];
+            mlsag.r = r;
+            pending.push(PendingMlsag {
+                mlsag_idx,
+                pi,
+                public_key_set: input.public_key_set,
+                challenge: c[pi],
+                commitment_shares: input.commitment_shares,
+                binding_factors,
+                responses: Default::default(),
+            });
+        }
+
+        let fee = match fee_idx {
+            Some(idx) => Fee::from(revealed_commitments[idx]),
+            None => Fee::zero(),
+        };
+
+        Ok(Self {
+            dbc_builder: DbcBuilder {
+                fee,
+                output_metadata,
+                ..DbcBuilder::new(
+                    transaction,
+                    revealed_commitments,
+                    output_owner_map,
+                    ringct_material,
+                )
+            },
+            pending,
+        })
+    }
+
+    /// returns the challenges to be signed, one per Multisig input.
+    pub fn challenges(&self) -> Vec<MlsagChallenge> {
+        self.pending
+            .iter()
+            .map(|p| MlsagChallenge {
+                transaction: self.dbc_builder.transaction.clone(),
+                mlsag_idx: p.mlsag_idx,
+                commitment_shares: p.commitment_shares.values().cloned().collect(),
+                output_openings: self.dbc_builder.revealed_commitments.clone(),
+            })
+            .collect()
+    }
+
+    /// add a signature share from one of the signing parties
+    ///
+    /// The share is verified against the party's commitment share, so an
+    /// invalid share is reported here, with the index of its party.
+    pub fn add_signature_share(mut self, share: MlsagSignatureShare) -> Result<Self> {
+        let pending = self
+            .pending
+            .iter_mut()
+            .find(|p| p.public_key_set.public_key() == share.public_key)
+            .ok_or(Error::PublicKeyNotFound)?;
+        let commitment_share = pending
+            .commitment_shares
+            .get(&share.index)
+            .ok_or(Error::MultisigShareMismatch)?;
+
+        // note: response = hiding + binding_factor * binding - lagrange * c * secret
+        let lagrange_coefficients =
+            lagrange_coefficients(pending.commitment_shares.keys().copied());
+        let public_key_share = public_key_share(&pending.public_key_set, share.index)?;
+        let expected = G1Projective::from(commitment_share.hiding_commitment.0)
+            + G1Projective::from(commitment_share.binding_commitment.0)
+                * pending.binding_factors[&share.index]
+            - G1Projective::from(public_key_share)
+                * (lagrange_coefficients[&share.index] * pending.challenge);
+        if G1Projective::generator() * share.response != expected {
+            return Err(Error::MultisigSignatureShareInvalid(share.index));
+        }
+
+        pending.responses.insert(share.index, share.response);
+        Ok(self)
+    }
+
+    /// add a list of signature shares
+    pub fn add_signature_shares(
+        mut self,
+        shares: impl IntoIterator<Item = MlsagSignatureShare>,
+    ) -> Result<Self> {
+        for share in shares.into_iter() {
+            self = self.add_signature_share(share)?;
+        }
+        Ok(self)
+    }
+
+    /// combine the signature shares to complete the transaction, and
+    /// return a DbcBuilder.
+    ///
+    /// Each completed ring signature is verified, so an invalid share
+    /// results in an error here.
+    pub fn finalize(self) -> Result<DbcBuilder> {
+        let mut dbc_builder = self.dbc_builder;
+        let msg = dbc_builder.transaction.gen_message();
+
+        for pending in self.pending {
+            if pending.responses.len() < pending.commitment_shares.len() {
+                return Err(Error::MultisigNotEnoughShares {
+                    required: pending.commitment_shares.len(),
+                    provided: pending.responses.len(),
+                });
+            }
+            let response = pending
+                .responses
+                .values()
+                .fold(Scalar::zero(), |sum, s| sum + s);
+
+            let mlsag = &mut dbc_builder.transaction.mlsags[pending.mlsag_idx];
+            mlsag.r[pending.pi].0 = response;
+
+            let public_commitments: Vec<G1Affine> = mlsag
+                .ring
+                .iter()
+                .map(|(_, hidden)| {
+                    (*hidden + G1Projective::from(mlsag.pseudo_commitment)).to_affine()
+                })
+                .collect();
+            mlsag.verify(&msg, &public_commitments)?;
+        }
+
+        Ok(dbc_builder)
+    }
+}
+
+pub(crate) fn unsigned_mlsag(
+    public_keys: Vec<G1Affine>,
+    commitments: Vec<G1Affine>,
+    key_image: G1Affine,
+    pseudo_commitment: G1Projective,
+) -> MlsagSignature {
+    let ring: Vec<(G1Affine, G1Affine)> = public_keys
+        .into_iter()
+        .zip(commitments)
+        .map(|(pk, commitment)| (pk, (commitment - pseudo_commitment).to_affine()))
+        .collect();
+    MlsagSignature {
+        c0: Scalar::zero(),
+        r: vec![(Scalar::zero(), Scalar::zero()); ring.len()],
+        key_image,
+        ring,
+        pseudo_commitment: pseudo_commitment.to_affine(),
+    }
+}
+
+pub(crate) fn sum_blindings(revealed_commitments: &[RevealedCommitment]) -> Scalar {
+    revealed_commitments
+        .iter()
+        .fold(Scalar::zero(), |sum, r| sum + r.blinding)
+}
+
+// Lagrange coefficients at x = 0 for the shares with the given indexes.
+// note: blsttc evaluates share i at x = i + 1.
+fn lagrange_coefficients(indexes: impl Iterator<Item = usize>) -> BTreeMap<usize, Scalar> {
+    let indexes: Vec<usize> = indexes.collect();
+    let x = |i: usize| Scalar::from(i as u64 + 1);
+    indexes
+        .iter()
+        .map(|j| {
+            let (num, den) = indexes
+                .iter()
+                .filter(|m| *m != j)
+                .fold((Scalar::one(), Scalar::one()), |(num, den), m| {
+                    (num * x(*m), den * (x(*m) - x(*j)))
+                });
+            // indexes are distinct, so den is non-zero.
+            (*j, num * den.invert().unwrap())
+        })
+        .collect()
+}
+
+fn public_key_share(public_key_set: &PublicKeySet, index: usize) -> Result<G1Affine> {
+    let public_key = PublicKey::from_bytes(public_key_set.public_key_share(index).to_bytes())?;
+    Ok(public_key.into())
+}
+
+fn hashed_public_key(public_key: &PublicKey) -> G1Projective {
+    bls_ringct::hash_to_curve(G1Projective::from(G1Affine::from(*public_key)))
+}
+
+fn nonce_commitment(public_key: &PublicKey, nonce: Scalar) -> (G1Affine, G1Affine) {
+    (
+        (G1Projective::generator() * nonce).to_affine(),
+        (hashed_public_key(public_key) * nonce).to_affine(),
+    )
+}
+
+// the binding factor of each party, over the message and the commitments
+// of all signing parties, as in FROST.
+fn binding_factors<'a>(
+    msg: &[u8],
+    public_key: &PublicKey,
+    commitment_shares: impl Iterator<Item = &'a MlsagCommitmentShare>,
+) -> BTreeMap<usize, Scalar> {
+    let commitment_shares: Vec<&MlsagCommitmentShare> = commitment_shares.collect();
+    let mut encoded_shares = vec![];
+    for share in commitment_shares.iter() {
+        encoded_shares.extend_from_slice(&(share.index as u64).to_le_bytes());
+        for point in [
+            share.hiding_commitment.0,
+            share.hiding_commitment.1,
+            share.binding_commitment.0,
+            share.binding_commitment.1,
+        ] {
+            encoded_shares.extend_from_slice(&point.to_compressed());
+        }
+    }
+
+    commitment_shares
+        .iter()
+        .map(|share| {
+            let factor = hash_to_scalar(&[
+                BINDING_TAG,
+                &(share.index as u64).to_le_bytes(),
+                &public_key.to_bytes(),
+                msg,
+                &encoded_shares,
+            ]);
+            (share.index, factor)
+        })
+        .collect()
+}
+
+// the group nonce commitment: the sum of each party's hiding commitment
+// plus its binding commitment times its binding factor.
+fn group_commitment<'a>(
+    commitment_shares: impl Iterator<Item = &'a MlsagCommitmentShare>,
+    binding_factors: &BTreeMap<usize, Scalar>,
+) -> (G1Projective, G1Projective) {
+    commitment_shares.fold(
+        (G1Projective::identity(), G1Projective::identity()),
+        |(sum_g, sum_h), s| {
+            let factor = binding_factors[&s.index];
+            (
+                sum_g
+                    + G1Projective::from(s.hiding_commitment.0)
+                    + G1Projective::from(s.binding_commitment.0) * factor,
+                sum_h
+                    + G1Projective::from(s.hiding_commitment.1)
+                    + G1Projective::from(s.binding_commitment.1) * factor,
+            )
+        },
+    )
+}
+
+// recomputes the challenge of the true input at pi, by walking the ring
+// from c0, and checks that the ring closes given the group commitment.
+fn ring_challenge(
+    mlsag: &MlsagSignature,
+    msg: &[u8],
+    pi: usize,
+    group_commitment: (G1Projective, G1Projective),
+) -> Result<Scalar> {
+    #[allow(non_snake_case)]
+    let G1 = G1Projective::generator();
+    let key_image = G1Projective::from(mlsag.key_image);
+    if mlsag.r.len() != mlsag.ring.len() {
+        return Err(Error::MultisigChallengeInvalid);
+    }
+
+    let mut c = mlsag.c0;
+    let mut c_pi = c;
+    for (n, &(pk, hidden_commitment)) in mlsag.ring.iter().enumerate() {
+        let r = mlsag.r[n];
+        c = if n == pi {
+            c_pi = c;
+            c_hash(
+                msg,
+                group_commitment.0,
+                G1 * r.1 + hidden_commitment * c,
+                group_commitment.1,
+            )
+        } else {
+            c_hash(
+                msg,
+                G1 * r.0 + pk * c,
+                G1 * r.1 + hidden_commitment * c,
+                bls_ringct::hash_to_curve(pk.into()) * r.0 + key_image * c,
+            )
+        };
+    }
+
+    match c == mlsag.c0 {
+        true => Ok(c_pi),
+        false => Err(Error::MultisigChallengeInvalid),
+    }
+}
+
+fn dleq_hash(
+    public_key: &PublicKey,
+    public_key_share: G1Projective,
+    key_image_share: G1Projective,
+    nonce_g: G1Projective,
+    nonce_h: G1Projective,
+) -> Scalar {
+    hash_to_scalar(&[
+        DLEQ_TAG,
+        &public_key.to_bytes(),
+        &public_key_share.to_compressed(),
+        &key_image_share.to_compressed(),
+        &nonce_g.to_compressed(),
+        &nonce_h.to_compressed(),
+    ])
+}
+
+// note: must match the challenge hash of bls_ringct::MlsagSignature::verify()
+fn c_hash(msg: &[u8], l1: G1Projective, l2: G1Projective, r1: G1Projective) -> Scalar {
+    hash_to_scalar(&[
+        msg,
+        &l1.to_compressed(),
+        &l2.to_compressed(),
+        &r1.to_compressed(),
+    ])
+}
+
+fn hash_to_scalar(chunks: &[&[u8]]) -> Scalar {
+    let mut sha3 = Sha3::v256();
+    for chunk in chunks {
+        sha3.update(chunk);
+    }
+    let mut hash = [0u8; 32];
+    sha3.finalize(&mut hash);
+
+    // rehash until the hash is a valid Scalar.
+    loop {
+        let s_opt = Scalar::from_bytes_le(&hash);
+        if bool::from(s_opt.is_some()) {
+            return s_opt.unwrap();
+        }
+        let mut sha3 = Sha3::v256();
+        sha3.update(&hash);
+        sha3.finalize(&mut hash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::spend_genesis,
+        mock,
+        rand::{distributions::Standard, Rng},
+        tests::STD_DECOYS_TO_FETCH,
+        Owner, OwnerOnce, TransactionBuilder,
+    };
+    use blsttc::{SecretKey, SecretKeySet};
+
+    // note: a nonce must only be used once, so is not Clone.  This copy is
+    // for checking that a party refuses to sign.
+    fn copy(nonce: &MlsagNonce) -> MlsagNonce {
+        MlsagNonce {
+            index: nonce.index,
+            public_key: nonce.public_key,
+            derivation_index: nonce.derivation_index,
+            hiding: nonce.hiding,
+            binding: nonce.binding,
+        }
+    }
+
+    #[test]
+    fn multisig_dbc_can_be_decrypted_and_spent_by_shares() -> Result<(), Error> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+
+        // a 2-of-3 multisig owner, and a single key owner.
+        let secret_key_set = SecretKeySet::random(1, &mut rng);
+        let parties: Vec<MultisigOwnerShare> = (0..3)
+            .map(|i| MultisigOwnerShare::new(i, secret_key_set.secret_key_share(i)))
+            .collect();
+        let multisig_owner = OwnerOnce::from_owner_base(
+            Owner::from_public_key_set(secret_key_set.public_keys()),
+            &mut rng,
+        );
+        let single_sk: SecretKey = rng.sample(Standard);
+        let single_owner = OwnerOnce::from_owner_base(Owner::from(single_sk.clone()), &mut rng);
+
+        let (mut spentbook_node, dbcs) = spend_genesis(&mut rng, |tx_builder, _| {
+            Ok(tx_builder.add_outputs_by_amount([
+                (Token::from_nano(700), multisig_owner.clone()),
+                (Token::from_nano(300), single_owner.clone()),
+            ]))
+        })?;
+        let find = |owner_once: &OwnerOnce| -> Result<&Dbc> {
+            dbcs.iter()
+                .find(|(_, o, _)| o.owner_base == owner_once.owner_base)
+                .map(|(dbc, ..)| dbc)
+                .ok_or(Error::PublicKeyNotFound)
+        };
+        let (multisig_dbc, single_dbc) = (find(&multisig_owner)?, find(&single_owner)?);
+
+        // parties 0 and 2 decrypt the derivation index and amount.
+        let signers = [&parties[0], &parties[2]];
+        let derivation_index_shares = signers
+            .iter()
+            .map(|p| Ok((p.index(), p.derivation_index_share(multisig_dbc)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let derivation_index = multisig_dbc.derivation_index_by_shares(&derivation_index_shares)?;
+        assert_eq!(derivation_index, multisig_owner.derivation_index);
+
+        let amount_secrets_shares = signers
+            .iter()
+            .map(|p| {
+                Ok((
+                    p.index(),
+                    p.amount_secrets_share(multisig_dbc, &derivation_index)?,
+                ))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let amount_secrets =
+            multisig_dbc.amount_secrets_by_shares(&derivation_index, &amount_secrets_shares)?;
+        assert_eq!(amount_secrets.amount(), Token::from_nano(700));
+
+        // round 1: commit.
+        let mut nonces = Vec::new();
+        let mut commitment_shares = Vec::new();
+        for party in signers {
+            let (nonce, share) = party.commit(multisig_dbc, &derivation_index, &mut rng)?;
+            nonces.push(nonce);
+            commitment_shares.push(share);
+        }
+        assert_eq!(
+            MultisigInput::new(
+                multisig_dbc,
+                &derivation_index,
+                amount_secrets.clone(),
+                commitment_shares[..1].to_vec(),
+            )
+            .err(),
+            Some(Error::MultisigNotEnoughShares {
+                required: 2,
+                provided: 1
+            })
+        );
+
+        // a key image share that does not match its proof is rejected.
+        let mut forged_shares = commitment_shares.clone();
+        forged_shares[1].key_image_share = forged_shares[0].key_image_share;
+        assert_eq!(
+            MultisigInput::new(
+                multisig_dbc,
+                &derivation_index,
+                amount_secrets.clone(),
+                forged_shares,
+            )
+            .err(),
+            Some(Error::MultisigKeyImageShareInvalid(2))
+        );
+
+        let multisig_input = MultisigInput::new(
+            multisig_dbc,
+            &derivation_index,
+            amount_secrets,
+            commitment_shares,
+        )?;
+
+        // spend the multisig dbc together with a single key dbc, paying a fee.
+        let fee = Token::from_nano(10);
+        let recipient =
+            OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng);
+        let builder = TransactionBuilder::default()
+            // the spentbook has only 2 outputs besides our inputs, so
+            // each ring has 1 decoy.
+            .set_decoys_per_input(1)
+            .add_decoy_inputs(spentbook_node.random_decoys(STD_DECOYS_TO_FETCH, &mut rng))
+            .add_input_dbc(single_dbc, &single_sk)?
+            .add_multisig_input(multisig_input)
+            .set_fee(fee)
+            .add_output_by_amount(Token::from_nano(990), recipient.clone());
+        assert_eq!(builder.inputs_amount_sum()?, Token::from_nano(1000));
+
+        let session = builder.build_multisig(&mut rng)?;
+        let challenges = session.challenges();
+        assert_eq!(challenges.len(), 1);
+
+        // round 2: sign, approving the outputs and fee.
+        let approved = vec![(recipient.as_owner().public_key(), Token::from_nano(990))];
+        let (outputs, tx_fee) = challenges[0].outputs()?;
+        assert_eq!((outputs, tx_fee), (approved.clone(), fee));
+
+        // a party does not sign outputs or a fee it has not approved.
+        let not_approved = vec![(recipient.as_owner().public_key(), Token::from_nano(991))];
+        assert_eq!(
+            signers[0]
+                .sign(copy(&nonces[0]), &challenges[0], &not_approved, fee)
+                .err(),
+            Some(Error::MultisigOutputsNotApproved)
+        );
+        assert_eq!(
+            signers[0]
+                .sign(copy(&nonces[0]), &challenges[0], &approved, Token::zero())
+                .err(),
+            Some(Error::MultisigOutputsNotApproved)
+        );
+
+        // nor a challenge that does not match its transaction.
+        let mut tampered = challenges[0].clone();
+        let mlsag = &mut tampered.transaction.mlsags[tampered.mlsag_idx];
+        mlsag.c0 += Scalar::one();
+        assert_eq!(
+            signers[0]
+                .sign(copy(&nonces[0]), &tampered, &approved, fee)
+                .err(),
+            Some(Error::MultisigChallengeInvalid)
+        );
+
+        let signature_shares = signers
+            .iter()
+            .zip(nonces)
+            .map(|(party, nonce)| party.sign(nonce, &challenges[0], &approved, fee))
+            .collect::<Result<Vec<_>>>()?;
+
+        // a party that did not commit can not sign.
+        let (nonce, _) = parties[1].commit(multisig_dbc, &derivation_index, &mut rng)?;
+        assert_eq!(
+            parties[1].sign(nonce, &challenges[0], &approved, fee).err(),
+            Some(Error::MultisigShareMismatch)
+        );
+
+        // an invalid signature share is reported with its party.
+        let mut invalid_share = signature_shares[1].clone();
+        invalid_share.response += Scalar::one();
+        assert!(matches!(
+            session.clone().add_signature_share(invalid_share),
+            Err(Error::MultisigSignatureShareInvalid(2))
+        ));
+        assert!(matches!(
+            session
+                .clone()
+                .add_signature_shares(signature_shares[..1].to_vec())?
+                .finalize(),
+            Err(Error::MultisigNotEnoughShares { .. })
+        ));
+
+        let mut dbc_builder = session.add_signature_shares(signature_shares)?.finalize()?;
+        assert!(dbc_builder
+            .transaction
+            .mlsags
+            .iter()
+            .all(|m| m.ring.len() == 2));
+        let tx_fee = dbc_builder.fee.clone();
+        assert_eq!(tx_fee.amount(), fee);
+
+        for (key_image, tx) in dbc_builder.inputs() {
+            dbc_builder = dbc_builder
+                .add_spent_proof_share(spentbook_node.log_spent_with_fee(
+                    key_image,
+                    tx.clone(),
+                    &tx_fee,
+                )?)
+                .add_spent_transaction(tx);
+        }
+        let output_dbcs = dbc_builder.build(&spentbook_node.key_manager)?;
+        assert_eq!(output_dbcs.len(), 1);
+        assert_eq!(output_dbcs[0].2.amount(), Token::from_nano(990));
+
+        Ok(())
+    }
+
+    #[test]
+    fn multisig_misuse_is_reported() -> Result<(), Error> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (_spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+
+        let secret_key_set = SecretKeySet::random(0, &mut rng);
+        let party = MultisigOwnerShare::new(0, secret_key_set.secret_key_share(0));
+
+        // a bearer genesis dbc is not multisig owned.
+        assert_eq!(
+            party.derivation_index_share(&genesis_dbc).err(),
+            Some(Error::NotMultisigOwner)
+        );
+        assert_eq!(
+            TransactionBuilder::default()
+                .set_require_all_decoys(false)
+                .add_input_dbc_bearer(&genesis_dbc)?
+                .build_multisig(&mut rng)
+                .err(),
+            Some(Error::TransactionMustHaveAnOutput)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,410 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{CryptoRng, RngCore},
+    Dbc, DbcBuilder, DecoyInput, Error, Hash, OwnerOnce, PublicKey, Result, Token,
+    TransactionBuilder,
+};
+use bls_ringct::{bls_bulletproofs::PedersenGens, blstrs::G1Affine, group::Curve};
+use blsttc::SecretKey;
+use serde::{Deserialize, Serialize};
+
+/// An unsigned transaction, created on a networked (online) machine, to be
+/// signed on an air-gapped (offline) machine which holds the SecretKeys.
+///
+/// The online machine has the Dbcs to spend, and gathers decoys from the
+/// spentbook.  The offline machine signs via ::sign(), and returns the
+/// resulting DbcBuilder, which the online machine checks via
+/// ::verify_payload() before logging the inputs as spent.
+///
+/// The request includes a checksum, which detects corruption in transfer.
+/// It cannot authenticate the request, as the online machine computes it.
+/// Instead, ::sign() checks that the request pays exactly the payees that
+/// the offline operator approved, and that any change is returned to one
+/// of the signing keys, so that a compromised online machine cannot
+/// redirect funds unnoticed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineSigningRequest {
+    inputs: Vec<Dbc>,
+    decoys: Vec<DecoyInput>,
+    decoys_per_input: Option<usize>,
+    require_all_decoys: Option<bool>,
+    outputs: Vec<(Token, OwnerOnce)>,
+    change_owner: OwnerOnce,
+    fee: Token,
+    checksum: Hash,
+}
+
+impl OfflineSigningRequest {
+    /// create a request to spend the given Dbcs to the given outputs,
+    /// returning any change to the change owner.
+    pub fn new(
+        inputs: Vec<Dbc>,
+        outputs: Vec<(Token, OwnerOnce)>,
+        change_owner: OwnerOnce,
+    ) -> Result<Self> {
+        let mut request = Self {
+            inputs,
+            decoys: Default::default(),
+            decoys_per_input: None,
+            require_all_decoys: None,
+            outputs,
+            change_owner,
+            fee: Token::zero(),
+            checksum: Hash::from([0u8; 32]),
+        };
+        request.checksum = request.content_hash()?;
+        Ok(request)
+    }
+
+    /// add decoys, eg as fetched from the spentbook.
+    ///
+    /// see TransactionBuilder::add_decoy_inputs().
+    pub fn add_decoy_inputs(mut self, decoys: Vec<DecoyInput>) -> Result<Self> {
+        for decoy in decoys {
+            if !self
+                .decoys
+                .iter()
+                .any(|d| d.public_key() == decoy.public_key())
+            {
+                self.decoys.push(decoy);
+            }
+        }
+        self.checksum = self.content_hash()?;
+        Ok(self)
+    }
+
+    /// set decoys_per_input option.
+    pub fn set_decoys_per_input(mut self, decoys_per_input: usize) -> Result<Self> {
+        self.decoys_per_input = Some(decoys_per_input);
+        self.checksum = self.content_hash()?;
+        Ok(self)
+    }
+
+    /// set require_all_decoys option.
+    pub fn set_require_all_decoys(mut self, require_all_decoys: bool) -> Result<Self> {
+        self.require_all_decoys = Some(require_all_decoys);
+        self.checksum = self.content_hash()?;
+        Ok(self)
+    }
+
+    /// set the fee.
+    pub fn set_fee(mut self, fee: Token) -> Result<Self> {
+        self.fee = fee;
+        self.checksum = self.content_hash()?;
+        Ok(self)
+    }
+
+    /// returns the Dbcs to be spent
+    pub fn inputs(&self) -> &[Dbc] {
+        &self.inputs
+    }
+
+    /// returns the outputs, excluding change
+    pub fn outputs(&self) -> &[(Token, OwnerOnce)] {
+        &self.outputs
+    }
+
+    /// returns the change owner
+    pub fn change_owner(&self) -> &OwnerOnce {
+        &self.change_owner
+    }
+
+    /// returns the fee
+    pub fn fee(&self) -> Token {
+        self.fee
+    }
+
+    /// returns the checksum of the request
+    pub fn checksum(&self) -> Hash {
+        self.checksum
+    }
+
+    /// Sign the request, on the offline machine.
+    ///
+    /// approved_payees are the base PublicKey and amount of each output, as
+    /// approved by the offline operator.  The outputs of the request must
+    /// match them exactly (in any order).  approved_fee is the fee approved
+    /// by the offline operator, which must equal the fee of the request.
+    ///
+    /// The returned DbcBuilder holds no secret keys, so may be passed back
+    /// to the online machine.
+    pub fn sign(
+        &self,
+        secret_keys: &[SecretKey],
+        approved_payees: &[(PublicKey, Token)],
+        approved_fee: Token,
+        rng: impl RngCore + CryptoRng,
+    ) -> Result<DbcBuilder> {
+        if self.content_hash()? != self.checksum {
+            return Err(Error::OfflineSigningRequestCorrupt);
+        }
+
+        let mut payees: Vec<(PublicKey, Token)> = self
+            .outputs
+            .iter()
+            .map(|(amount, owner)| (owner.owner_base().public_key(), *amount))
+            .collect();
+        let mut approved = approved_payees.to_vec();
+        payees.sort();
+        approved.sort();
+        if payees != approved {
+            return Err(Error::OfflineSigningOutputsNotApproved);
+        }
+        if self.fee != approved_fee {
+            return Err(Error::OfflineSigningFeeNotApproved);
+        }
+
+        let change_pk = self.change_owner.owner_base().public_key();
+        if !secret_keys.iter().any(|sk| sk.public_key() == change_pk) {
+            return Err(Error::OfflineSigningChangeNotOwned);
+        }
+
+        let mut tx_builder = TransactionBuilder::default();
+        if let Some(decoys_per_input) = self.decoys_per_input {
+            tx_builder = tx_builder.set_decoys_per_input(decoys_per_input);
+        }
+        if let Some(require_all_decoys) = self.require_all_decoys {
+            tx_builder = tx_builder.set_require_all_decoys(require_all_decoys);
+        }
+        tx_builder = tx_builder
+            .add_decoy_inputs(self.decoys.clone())
+            .set_change_owner(self.change_owner.clone())
+            .set_fee(self.fee);
+        for dbc in self.inputs.iter() {
+            let sk = secret_keys
+                .iter()
+                .find(|sk| dbc.owner_base().public_key() == sk.public_key())
+                .ok_or(Error::SecretKeyDoesNotMatchPublicKey)?;
+            tx_builder = tx_builder.add_input_dbc(dbc, sk)?;
+        }
+        for (amount, owner) in self.outputs.iter() {
+            tx_builder = tx_builder.add_output_by_amount(*amount, owner.clone());
+        }
+
+        // note: the RingCtMaterial holds the input secret keys, and is not
+        // needed to build the output Dbcs.
+        Ok(DbcBuilder {
+            ringct_material: Default::default(),
+            ..tx_builder.build(rng)?
+        })
+    }
+
+    /// Verify the DbcBuilder returned by ::sign(), on the online machine.
+    ///
+    /// The transaction must be validly signed over the rings of this request,
+    /// and pay each of its outputs with the requested owner and amount.
+    pub fn verify_payload(&self, dbc_builder: &DbcBuilder) -> Result<()> {
+        let transaction = &dbc_builder.transaction;
+
+        let public_commitments: Vec<Vec<G1Affine>> = transaction
+            .mlsags
+            .iter()
+            .map(|mlsag| {
+                mlsag
+                    .public_keys()
+                    .iter()
+                    .map(|pk| {
+                        self.commitment(pk)
+                            .ok_or(Error::OfflineSigningPayloadMismatch)
+                    })
+                    .collect()
+            })
+            .collect::<Result<_>>()?;
+        transaction.verify(&public_commitments)?;
+
+        let pc_gens = PedersenGens::default();
+        for (amount, owner) in self.outputs.iter() {
+            let pk = owner.as_owner().public_key();
+            let output = transaction
+                .outputs
+                .iter()
+                .find(|o| pk.eq(o.public_key()))
+                .ok_or(Error::OfflineSigningPayloadMismatch)?;
+            let paid = dbc_builder.revealed_commitments.iter().any(|r| {
+                r.value == amount.as_nano() && r.commit(&pc_gens).to_affine() == output.commitment()
+            });
+            if !paid || dbc_builder.output_owner_map.get(&pk) != Some(owner) {
+                return Err(Error::OfflineSigningPayloadMismatch);
+            }
+        }
+
+        Ok(())
+    }
+
+    // the commitment of a ring member, which is either a decoy or an
+    // output of the transaction of an input Dbc.
+    fn commitment(&self, public_key: &G1Affine) -> Option<G1Affine> {
+        self.decoys
+            .iter()
+            .find(|d| d.public_key() == *public_key)
+            .map(|d| d.commitment())
+            .or_else(|| {
+                self.inputs
+                    .iter()
+                    .flat_map(|dbc| dbc.transaction.outputs.iter())
+                    .find(|o| o.public_key() == public_key)
+                    .map(|o| o.commitment())
+            })
+    }
+
+    fn content_hash(&self) -> Result<Hash> {
+        let bytes = bincode::serialize(&(
+            &self.inputs,
+            &self.decoys,
+            self.decoys_per_input,
+            self.require_all_decoys,
+            &self.outputs,
+            &self.change_owner,
+            self.fee,
+        ))
+        .map_err(|e| Error::HexSerializationFailed(e.to_string()))?;
+        Ok(Hash::hash(&bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::pay_from_genesis,
+        rand::{distributions::Standard, Rng},
+        Owner,
+    };
+
+    #[test]
+    fn cold_keys_sign_offline() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        // fund cold storage with two Dbcs.
+        let cold_sk: SecretKey = rng.sample(Standard);
+        let (mut spentbook_node, cold_dbcs) =
+            pay_from_genesis(&[600, 400], &Owner::from(cold_sk.public_key()), &mut rng)?;
+
+        // online: pay 700, with change back to cold storage.
+        let payee_sk: SecretKey = rng.sample(Standard);
+        let payee = payee_sk.public_key();
+        let request = OfflineSigningRequest::new(
+            cold_dbcs,
+            vec![(
+                Token::from_nano(700),
+                OwnerOnce::from_owner_base(Owner::from(payee), &mut rng),
+            )],
+            OwnerOnce::from_owner_base(Owner::from(cold_sk.public_key()), &mut rng),
+        )?
+        .set_decoys_per_input(1)?
+        .add_decoy_inputs(spentbook_node.random_decoys(100, &mut rng))?;
+
+        let bytes = bincode::serialize(&request)
+            .map_err(|e| Error::HexSerializationFailed(e.to_string()))?;
+
+        // offline: the operator approves the payment.
+        let request: OfflineSigningRequest = bincode::deserialize(&bytes)
+            .map_err(|e| Error::HexDeserializationFailed(e.to_string()))?;
+        let approved = [(payee, Token::from_nano(700))];
+        let payload = request.sign(
+            std::slice::from_ref(&cold_sk),
+            &approved,
+            Token::zero(),
+            &mut rng,
+        )?;
+        assert!(payload.ringct_material.inputs.is_empty());
+
+        // online: check the payload, then log the inputs as spent.
+        request.verify_payload(&payload)?;
+        let mut dbc_builder = payload.clone();
+        for (key_image, tx) in dbc_builder.inputs() {
+            dbc_builder = dbc_builder
+                .add_spent_proof_share(spentbook_node.log_spent(key_image, tx.clone())?)
+                .add_spent_transaction(tx);
+        }
+        let outputs = dbc_builder.build(&spentbook_node.key_manager)?;
+        let (paid, ..) = outputs
+            .iter()
+            .find(|(dbc, ..)| dbc.owner_base().public_key() == payee)
+            .ok_or(Error::PublicKeyNotFound)?;
+        assert_eq!(
+            paid.amount_secrets(&payee_sk)?.amount(),
+            Token::from_nano(700)
+        );
+
+        // a swapped payee is not approved, even with a fixed up checksum.
+        let thief = Owner::from_random_secret_key(&mut rng);
+        let mut swapped = request.clone();
+        swapped.outputs[0].1 = OwnerOnce::from_owner_base(thief.clone(), &mut rng);
+        swapped.checksum = swapped.content_hash()?;
+        assert_eq!(
+            swapped
+                .sign(
+                    std::slice::from_ref(&cold_sk),
+                    &approved,
+                    Token::zero(),
+                    &mut rng
+                )
+                .err(),
+            Some(Error::OfflineSigningOutputsNotApproved)
+        );
+
+        // nor is the change redirected.
+        let mut swapped = request.clone();
+        swapped.change_owner = OwnerOnce::from_owner_base(thief, &mut rng);
+        swapped.checksum = swapped.content_hash()?;
+        assert_eq!(
+            swapped
+                .sign(
+                    std::slice::from_ref(&cold_sk),
+                    &approved,
+                    Token::zero(),
+                    &mut rng
+                )
+                .err(),
+            Some(Error::OfflineSigningChangeNotOwned)
+        );
+
+        // a raised fee is not approved, even with a fixed up checksum.
+        let mut tampered = request.clone();
+        tampered.fee = Token::from_nano(50);
+        tampered.checksum = tampered.content_hash()?;
+        assert_eq!(
+            tampered
+                .sign(
+                    std::slice::from_ref(&cold_sk),
+                    &approved,
+                    Token::zero(),
+                    &mut rng
+                )
+                .err(),
+            Some(Error::OfflineSigningFeeNotApproved)
+        );
+
+        // corruption in transfer is detected.
+        let mut corrupt = request.clone();
+        corrupt.outputs[0].0 = Token::from_nano(7000);
+        assert_eq!(
+            corrupt
+                .sign(
+                    std::slice::from_ref(&cold_sk),
+                    &approved,
+                    Token::zero(),
+                    &mut rng
+                )
+                .err(),
+            Some(Error::OfflineSigningRequestCorrupt)
+        );
+
+        // and the payload must pay the request's outputs.
+        let mut altered = payload;
+        altered.output_owner_map.clear();
+        assert_eq!(
+            request.verify_payload(&altered).err(),
+            Some(Error::OfflineSigningPayloadMismatch)
+        );
+
+        Ok(())
+    }
+}
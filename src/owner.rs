@@ -6,8 +6,8 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{Error, PublicKey, Result};
-use blsttc::{serde_impl::SerdeSecret, SecretKey};
+use crate::{Error, PublicKey, Result, ViewKey};
+use blsttc::{serde_impl::SerdeSecret, PublicKeySet, SecretKey};
 use std::fmt;
 
 #[cfg(feature = "serde")]
@@ -25,6 +25,10 @@ pub type DerivationIndex = [u8; 32];
 /// If the type is PublicKey, the Dbc is considered
 /// to be owned (by whoever holds the SecretKey).
 ///
+/// If the type is Multisig, the Dbc is considered to be
+/// owned by a group of parties, any threshold+1 of whom
+/// must cooperate to spend it.  See MultisigOwnerShare.
+///
 /// Dbc's have both a base Owner and a one-time-use Owner.
 ///
 /// The base Owner public key is given out to other people
@@ -41,6 +45,15 @@ pub type DerivationIndex = [u8; 32];
 pub enum Owner {
     SecretKey(SerdeSecret<SecretKey>),
     PublicKey(PublicKey),
+    /// An owner with separate spend and view keys.  The view key can
+    /// decrypt the derivation index and AmountSecrets, but only the spend
+    /// key can spend.  See ViewKey.
+    SplitKey {
+        spend: PublicKey,
+        view: PublicKey,
+    },
+    /// An owner whose key is shared amongst m-of-n parties.
+    Multisig(PublicKeySet),
 }
 
 impl fmt::Debug for Owner {
@@ -50,6 +63,8 @@ impl fmt::Debug for Owner {
         match self {
             Self::SecretKey(sk) => f.field(sk),
             Self::PublicKey(pk) => f.field(pk),
+            Self::SplitKey { spend, view } => f.field(spend).field(view),
+            Self::Multisig(pks) => f.field(pks),
         };
 
         f.finish()
@@ -73,6 +88,14 @@ impl PartialEq for Owner {
         match (self, other) {
             (Self::SecretKey(a), Self::SecretKey(b)) => a == b,
             (Self::PublicKey(a), Self::PublicKey(b)) => a == b,
+            (
+                Self::SplitKey { spend, view },
+                Self::SplitKey {
+                    spend: spend_b,
+                    view: view_b,
+                },
+            ) => spend == spend_b && view == view_b,
+            (Self::Multisig(a), Self::Multisig(b)) => a == b,
             _ => false,
         }
     }
@@ -81,11 +104,52 @@ impl PartialEq for Owner {
 impl Eq for Owner {}
 
 impl Owner {
-    /// returns PublicKey
+    /// create a SplitKey Owner from a spend PublicKey and view PublicKey.
+    ///
+    /// The view key must be the one obtained via ViewKey::from_spend_key(),
+    /// else the spend key will not be able to decrypt the Dbc content.
+    pub fn from_split_keys(spend: PublicKey, view: PublicKey) -> Self {
+        Self::SplitKey { spend, view }
+    }
+
+    /// create a SplitKey Owner from a spend SecretKey.
+    pub fn from_spend_key(spend_sk: &SecretKey) -> Self {
+        Self::from_split_keys(
+            spend_sk.public_key(),
+            ViewKey::from_spend_key(spend_sk).public_key(),
+        )
+    }
+
+    /// create a Multisig Owner from a PublicKeySet.
+    pub fn from_public_key_set(public_key_set: PublicKeySet) -> Self {
+        Self::Multisig(public_key_set)
+    }
+
+    /// returns the PublicKeySet of a Multisig Owner.
+    pub fn public_key_set(&self) -> Result<&PublicKeySet> {
+        match self {
+            Self::Multisig(pks) => Ok(pks),
+            _ => Err(Error::NotMultisigOwner),
+        }
+    }
+
+    /// returns PublicKey.  For a SplitKey owner, this is the spend key.
     pub fn public_key(&self) -> PublicKey {
         match self {
             Self::SecretKey(sk) => sk.public_key(),
             Self::PublicKey(pk) => *pk,
+            Self::SplitKey { spend, .. } => *spend,
+            Self::Multisig(pks) => pks.public_key(),
+        }
+    }
+
+    /// returns the PublicKey to which the derivation index and AmountSecrets
+    /// are encrypted.  For a SplitKey owner this is the view key, otherwise
+    /// it is the same as Owner::public_key().
+    pub fn view_public_key(&self) -> PublicKey {
+        match self {
+            Self::SplitKey { view, .. } => *view,
+            _ => self.public_key(),
         }
     }
 
@@ -93,7 +157,9 @@ impl Owner {
     pub fn secret_key(&self) -> Result<SecretKey> {
         match self {
             Self::SecretKey(sk) => Ok(sk.inner().clone()),
-            Self::PublicKey(_pk) => Err(Error::SecretKeyUnavailable),
+            Self::PublicKey(_) | Self::SplitKey { .. } | Self::Multisig(_) => {
+                Err(Error::SecretKeyUnavailable)
+            }
         }
     }
 
@@ -102,6 +168,8 @@ impl Owner {
         match self {
             Self::SecretKey(sk) => Self::from(sk.inner().derive_child(i)),
             Self::PublicKey(pk) => Self::from(pk.derive_child(i)),
+            Self::SplitKey { spend, .. } => Self::from(spend.derive_child(i)),
+            Self::Multisig(pks) => Self::Multisig(pks.derive_child(i)),
         }
     }
 
@@ -110,6 +178,8 @@ impl Owner {
         match self {
             Self::SecretKey(sk) => sk.to_bytes().to_vec(),
             Self::PublicKey(pk) => pk.to_bytes().to_vec(),
+            Self::SplitKey { spend, view } => [spend.to_bytes(), view.to_bytes()].concat(),
+            Self::Multisig(pks) => pks.to_bytes(),
         }
     }
 
@@ -117,7 +187,17 @@ impl Owner {
     pub fn has_secret_key(&self) -> bool {
         match self {
             Self::SecretKey(_) => true,
-            Self::PublicKey(_) => false,
+            Self::PublicKey(_) | Self::SplitKey { .. } | Self::Multisig(_) => false,
+        }
+    }
+
+    /// returns the SecretKey that decrypts the content of a Dbc owned by this
+    /// Owner, given the base SecretKey.  For a SplitKey owner, this is the
+    /// view key derived from the (spend) base SecretKey.
+    pub(crate) fn view_secret_key(&self, base_sk: &SecretKey) -> SecretKey {
+        match self {
+            Self::SplitKey { .. } => ViewKey::from_spend_key(base_sk).secret_key().clone(),
+            _ => base_sk.clone(),
         }
     }
 
@@ -0,0 +1,184 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    AmountSecrets, Dbc, DerivationIndex, Error, OwnerOnce, PublicKey, Result, SpentProof,
+    SpentProofKeyVerifier, Token, TransactionVerifier,
+};
+use bls_ringct::{group::Curve, ringct::RingCtTransaction, RevealedCommitment};
+use std::collections::BTreeSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A proof, generated by the sender, that a payment was made to a recipient.
+///
+/// Only the recipient can decrypt a Dbc's content, so otherwise a sender has
+/// no way to show a third party (eg an arbiter of a dispute) what was paid.
+///
+/// The proof reveals the derivation index and amount opening of a single
+/// output.  A verifier checks that the transaction was logged as spent by
+/// the spentbook, that the recipient's base PublicKey derives the output's
+/// one-time key, and that the opening matches the output's commitment.
+///
+/// note: the derivation index links the output to the recipient's base key,
+/// so anyone holding the proof learns that this output belongs to them.
+/// No secret key is revealed, so the output remains spendable only by
+/// the recipient.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct PaymentProof {
+    recipient: PublicKey,
+    derivation_index: DerivationIndex,
+    amount_secrets: AmountSecrets,
+    transaction: RingCtTransaction,
+    spent_proofs: BTreeSet<SpentProof>,
+}
+
+impl PaymentProof {
+    /// create a PaymentProof from an output of DbcBuilder::build(), ie the
+    /// output Dbc, with its OwnerOnce and AmountSecrets.
+    pub fn new(dbc: &Dbc, owner_once: &OwnerOnce, amount_secrets: &AmountSecrets) -> Self {
+        Self {
+            recipient: owner_once.owner_base().public_key(),
+            derivation_index: owner_once.derivation_index,
+            amount_secrets: amount_secrets.clone(),
+            transaction: dbc.transaction.clone(),
+            spent_proofs: dbc.spent_proofs.clone(),
+        }
+    }
+
+    /// returns the recipient's base PublicKey
+    pub fn recipient(&self) -> PublicKey {
+        self.recipient
+    }
+
+    /// returns the amount paid
+    pub fn amount(&self) -> Token {
+        self.amount_secrets.amount()
+    }
+
+    /// returns the transaction containing the payment output
+    pub fn transaction(&self) -> &RingCtTransaction {
+        &self.transaction
+    }
+
+    /// returns the one-time-use PublicKey of the payment output
+    pub fn output_public_key(&self) -> PublicKey {
+        self.recipient.derive_child(&self.derivation_index)
+    }
+
+    /// Verifies the proof, and returns the amount paid to the recipient.
+    ///
+    /// The recipient is the base PublicKey which the verifier knows to
+    /// belong to the payee, eg from a PaymentRequest.
+    ///
+    /// see TransactionVerifier::verify() for a description of
+    /// verifier requirements.
+    pub fn verify<K: SpentProofKeyVerifier>(
+        &self,
+        recipient: &PublicKey,
+        verifier: &K,
+    ) -> Result<Token> {
+        if *recipient != self.recipient {
+            return Err(Error::PaymentProofRecipientMismatch);
+        }
+
+        TransactionVerifier::verify(verifier, &self.transaction, &self.spent_proofs)?;
+
+        let output_pk = self.output_public_key();
+        let output = self
+            .transaction
+            .outputs
+            .iter()
+            .find(|o| output_pk.eq(o.public_key()))
+            .ok_or(Error::OutputProofNotFound)?;
+
+        let rc: RevealedCommitment = self.amount_secrets.clone().into();
+        if rc.commit(&Default::default()).to_affine() != output.commitment() {
+            return Err(Error::AmountCommitmentsDoNotMatch);
+        }
+
+        Ok(self.amount())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::spend_genesis,
+        rand::{distributions::Standard, Rng},
+        Owner,
+    };
+    use blsttc::SecretKey;
+
+    #[test]
+    fn sender_proves_payment_to_third_party() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let recipient_sk: SecretKey = rng.sample(Standard);
+        let recipient = recipient_sk.public_key();
+
+        let (spentbook_node, dbcs) = spend_genesis(&mut rng, |tx_builder, rng| {
+            Ok(tx_builder.add_output_by_amount(
+                Token::from_nano(100),
+                OwnerOnce::from_owner_base(Owner::from(recipient), rng),
+            ))
+        })?;
+
+        let (dbc, owner_once, amount_secrets) = dbcs
+            .iter()
+            .find(|(dbc, ..)| dbc.owner_base().public_key() == recipient)
+            .ok_or(Error::PublicKeyNotFound)?;
+        let proof = PaymentProof::new(dbc, owner_once, amount_secrets);
+
+        // the third party needs only the recipient's base key and the
+        // spentbook keys.
+        assert_eq!(
+            proof.verify(&recipient, &spentbook_node.key_manager)?,
+            Token::from_nano(100)
+        );
+
+        let other: SecretKey = rng.sample(Standard);
+        assert_eq!(
+            proof
+                .verify(&other.public_key(), &spentbook_node.key_manager)
+                .err(),
+            Some(Error::PaymentProofRecipientMismatch)
+        );
+
+        // claiming a different amount is detected.
+        let mut inflated = proof.clone();
+        inflated.amount_secrets = AmountSecrets::from((1000, amount_secrets.blinding_factor()));
+        assert_eq!(
+            inflated
+                .verify(&recipient, &spentbook_node.key_manager)
+                .err(),
+            Some(Error::AmountCommitmentsDoNotMatch)
+        );
+
+        // claiming a payment to a different recipient is detected.
+        let mut misdirected = proof.clone();
+        misdirected.recipient = other.public_key();
+        assert_eq!(
+            misdirected
+                .verify(&other.public_key(), &spentbook_node.key_manager)
+                .err(),
+            Some(Error::OutputProofNotFound)
+        );
+
+        // a transaction that was never logged as spent is not a payment.
+        let mut unspent = proof;
+        unspent.spent_proofs.clear();
+        assert!(unspent
+            .verify(&recipient, &spentbook_node.key_manager)
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, PublicKey, Result, Signature, Token};
+use blsttc::SecretKey;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const PAYMENT_REQUEST_TAG: &[u8] = b"sn_dbc payment request";
+
+/// A request for payment (aka invoice), signed by the recipient.
+///
+/// The recipient PublicKey is the base key of the Owner to be paid.  The
+/// payer derives a one-time-use OwnerOnce from it as for any other payment.
+/// See TransactionBuilder::pay_request().
+///
+/// The expiry is a unix timestamp, in seconds.  The caller supplies the
+/// current time when verifying, as this crate does not read the clock.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentRequest {
+    amount: Token,
+    recipient: PublicKey,
+    invoice_id: String,
+    memo: String,
+    expiry: u64,
+    signature: Signature,
+}
+
+impl PaymentRequest {
+    /// create a PaymentRequest, signed by the recipient's base SecretKey.
+    pub fn new(
+        amount: Token,
+        recipient_sk: &SecretKey,
+        invoice_id: impl Into<String>,
+        memo: impl Into<String>,
+        expiry: u64,
+    ) -> Self {
+        let recipient = recipient_sk.public_key();
+        let invoice_id = invoice_id.into();
+        let memo = memo.into();
+        let signature = recipient_sk.sign(Self::signable_bytes(
+            amount,
+            &recipient,
+            &invoice_id,
+            &memo,
+            expiry,
+        ));
+
+        Self {
+            amount,
+            recipient,
+            invoice_id,
+            memo,
+            expiry,
+            signature,
+        }
+    }
+
+    /// returns the amount requested
+    pub fn amount(&self) -> Token {
+        self.amount
+    }
+
+    /// returns the base PublicKey of the recipient
+    pub fn recipient(&self) -> PublicKey {
+        self.recipient
+    }
+
+    /// returns the invoice id
+    pub fn invoice_id(&self) -> &str {
+        &self.invoice_id
+    }
+
+    /// returns the memo
+    pub fn memo(&self) -> &str {
+        &self.memo
+    }
+
+    /// returns the expiry, as a unix timestamp in seconds
+    pub fn expiry(&self) -> u64 {
+        self.expiry
+    }
+
+    /// returns the recipient's signature
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// returns true if the request has expired at the given time.
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expiry
+    }
+
+    /// verifies the recipient's signature, and that the request has not
+    /// expired at the given time (a unix timestamp, in seconds).
+    pub fn verify(&self, now: u64) -> Result<()> {
+        let bytes = Self::signable_bytes(
+            self.amount,
+            &self.recipient,
+            &self.invoice_id,
+            &self.memo,
+            self.expiry,
+        );
+        if !self.recipient.verify(&self.signature, bytes) {
+            return Err(Error::FailedSignature);
+        }
+        if self.is_expired(now) {
+            return Err(Error::PaymentRequestExpired {
+                expiry: self.expiry,
+                now,
+            });
+        }
+        Ok(())
+    }
+
+    /// Deserializes a `PaymentRequest` represented as a hex string.
+    #[cfg(feature = "serde")]
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = hex::decode(hex).map_err(|e| Error::HexDeserializationFailed(e.to_string()))?;
+        bincode::deserialize(&bytes).map_err(|e| Error::HexDeserializationFailed(e.to_string()))
+    }
+
+    /// Serialize this `PaymentRequest` to a hex string.
+    #[cfg(feature = "serde")]
+    pub fn to_hex(&self) -> Result<String> {
+        let bytes =
+            bincode::serialize(self).map_err(|e| Error::HexSerializationFailed(e.to_string()))?;
+        Ok(hex::encode(bytes))
+    }
+
+    // strings are length prefixed, so that fields can not run into each other.
+    fn signable_bytes(
+        amount: Token,
+        recipient: &PublicKey,
+        invoice_id: &str,
+        memo: &str,
+        expiry: u64,
+    ) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Default::default();
+        bytes.extend(PAYMENT_REQUEST_TAG);
+        bytes.extend(amount.as_nano().to_be_bytes());
+        bytes.extend(recipient.to_bytes());
+        for s in [invoice_id, memo] {
+            bytes.extend((s.len() as u64).to_be_bytes());
+            bytes.extend(s.as_bytes());
+        }
+        bytes.extend(expiry.to_be_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::spend_genesis,
+        rand::{distributions::Standard, Rng},
+        TransactionBuilder,
+    };
+
+    const NOW: u64 = 1_650_000_000;
+
+    #[test]
+    fn payment_request_signature_and_expiry() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let recipient_sk: SecretKey = rng.sample(Standard);
+
+        let request = PaymentRequest::new(
+            Token::from_nano(100),
+            &recipient_sk,
+            "inv-42",
+            "2 coffees",
+            NOW + 3600,
+        );
+        request.verify(NOW)?;
+        assert_eq!(PaymentRequest::from_hex(&request.to_hex()?)?, request);
+
+        assert_eq!(
+            request.verify(NOW + 3600),
+            Err(Error::PaymentRequestExpired {
+                expiry: NOW + 3600,
+                now: NOW + 3600
+            })
+        );
+
+        // altering any field invalidates the signature.
+        let mut altered = request.clone();
+        altered.amount = Token::from_nano(1);
+        assert_eq!(altered.verify(NOW), Err(Error::FailedSignature));
+
+        let mut altered = request.clone();
+        altered.invoice_id = "inv-4".to_string();
+        altered.memo = "22 coffees".to_string();
+        assert_eq!(altered.verify(NOW), Err(Error::FailedSignature));
+
+        let mut altered = request;
+        altered.recipient = rng.sample::<SecretKey, _>(Standard).public_key();
+        assert_eq!(altered.verify(NOW), Err(Error::FailedSignature));
+
+        Ok(())
+    }
+
+    #[test]
+    fn transaction_builder_pays_request() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let recipient_sk: SecretKey = rng.sample(Standard);
+        let request =
+            PaymentRequest::new(Token::from_nano(100), &recipient_sk, "inv-1", "", NOW + 60);
+
+        assert_eq!(
+            TransactionBuilder::default()
+                .pay_request(&request, NOW + 60, &mut rng)
+                .err(),
+            Some(Error::PaymentRequestExpired {
+                expiry: NOW + 60,
+                now: NOW + 60
+            })
+        );
+
+        let (spentbook_node, dbcs) = spend_genesis(&mut rng, |tx_builder, rng| {
+            tx_builder.pay_request(&request, NOW, rng)
+        })?;
+
+        let (payment, ..) = dbcs
+            .iter()
+            .find(|(dbc, ..)| dbc.owner_base().public_key() == request.recipient())
+            .ok_or(Error::PublicKeyNotFound)?;
+        assert_eq!(
+            payment.amount_secrets(&recipient_sk)?.amount(),
+            request.amount()
+        );
+        let metadata = payment
+            .metadata(&recipient_sk)?
+            .ok_or(Error::DecryptionBySecretKeyFailed)?;
+        assert_eq!(metadata.invoice_id, request.invoice_id());
+        assert_eq!(metadata.memo, request.memo());
+        payment.verify(&recipient_sk, &spentbook_node.key_manager)?;
+
+        Ok(())
+    }
+}
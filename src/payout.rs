@@ -0,0 +1,463 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{CryptoRng, RngCore},
+    CoinSelector, Dbc, DbcMetadata, Error, KeyImage, LargestFirst, Owner, OwnerOnce, PublicKey,
+    Result, SpentBookClient, SpentProofKeyVerifier, Token, TransactionBuilder, Wallet,
+};
+use blsttc::SecretKey;
+use std::{collections::BTreeMap, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A payment to one recipient of a BatchPayout.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayoutRow {
+    /// the base PublicKey of the recipient
+    pub recipient: PublicKey,
+    /// the amount to pay
+    pub amount: Token,
+    /// a memo for the recipient.  An empty memo adds no DbcMetadata.
+    pub memo: String,
+}
+
+/// The output Dbc that paid a PayoutRow.
+#[derive(Debug, Clone)]
+pub struct PayoutReceipt {
+    /// the row that was paid
+    pub row: PayoutRow,
+    /// the one-time-use owner of the output Dbc
+    pub owner_once: OwnerOnce,
+    /// the output Dbc, which should be sent to the recipient
+    pub dbc: Dbc,
+}
+
+/// The report of BatchPayout::pay().
+///
+/// Rows are paid in order, so the rows after the last receipt are unpaid.
+#[derive(Debug, Clone)]
+pub struct PayoutReport {
+    /// a receipt for each row that was paid, in the order of the rows
+    pub receipts: Vec<PayoutReceipt>,
+    /// the error that stopped the payout, if any
+    pub error: Option<Error>,
+}
+
+impl PayoutReport {
+    /// true if every row was paid.
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Pays many recipients from a Wallet.
+///
+/// Each row is paid to its own OwnerOnce, derived from the recipient's base
+/// PublicKey.  The rows are paid in transactions of at most <max_outputs>
+/// recipient outputs each, in addition to the change and fee.
+///
+/// Rows can be loaded from CSV via BatchPayout::from_csv().
+#[derive(Debug, Clone)]
+pub struct BatchPayout {
+    rows: Vec<PayoutRow>,
+    max_outputs: usize,
+    decoys_per_input: usize,
+    fee: Token,
+    dust_threshold: Token,
+}
+
+impl BatchPayout {
+    /// create a BatchPayout of the given rows.
+    pub fn new(rows: impl IntoIterator<Item = PayoutRow>) -> Self {
+        Self {
+            rows: rows.into_iter().collect(),
+            max_outputs: 32,
+            decoys_per_input: 10, // as TransactionBuilder.
+            fee: Token::zero(),
+            dust_threshold: Token::zero(),
+        }
+    }
+
+    /// create a BatchPayout from CSV, with a row per line of the form:
+    ///
+    ///   recipient,amount,memo
+    ///
+    /// where recipient is a hex encoded PublicKey, amount is a Token
+    /// amount such as 1.5, and memo is optional.  A memo may be quoted in
+    /// order to contain commas, with any quotes within it doubled.
+    ///
+    /// A first line starting with "recipient" is a header, and is skipped,
+    /// as are empty lines.
+    pub fn from_csv(csv: &str) -> Result<Self> {
+        let mut rows = vec![];
+        for (index, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || (index == 0 && line.starts_with("recipient")) {
+                continue;
+            }
+            let row = Self::parse_csv_line(line).map_err(|reason| Error::PayoutCsvInvalid {
+                line: index + 1,
+                reason,
+            })?;
+            rows.push(row);
+        }
+        Ok(Self::new(rows))
+    }
+
+    /// set max_outputs option.  The most recipient outputs of each transaction.
+    pub fn set_max_outputs(mut self, max_outputs: usize) -> Self {
+        self.max_outputs = max_outputs;
+        self
+    }
+
+    /// set decoys_per_input option.
+    pub fn set_decoys_per_input(mut self, decoys_per_input: usize) -> Self {
+        self.decoys_per_input = decoys_per_input;
+        self
+    }
+
+    /// set fee option.  The fee paid by each transaction.
+    pub fn set_fee(mut self, fee: Token) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// set dust_threshold option.
+    /// ::pay() rejects the payout if any row is of a smaller amount.
+    /// Rows of zero amount are always rejected.
+    pub fn set_dust_threshold(mut self, dust_threshold: Token) -> Self {
+        self.dust_threshold = dust_threshold;
+        self
+    }
+
+    /// returns the rows
+    pub fn rows(&self) -> &[PayoutRow] {
+        &self.rows
+    }
+
+    /// the number of transactions needed to pay all rows.
+    pub fn num_transactions(&self) -> usize {
+        self.rows.len().div_ceil(self.max_outputs.max(1))
+    }
+
+    /// pay the rows from the spendable Dbcs of a wallet.
+    ///
+    /// Inputs of each transaction are chosen largest amount first, and
+    /// the change is added to the wallet, owned by change_sk.  Each
+    /// transaction is built with decoys from the client, and its inputs
+    /// are logged as spent with the client.
+    ///
+    /// Nothing is paid if any row is of zero or dust amount, or if the
+    /// wallet balance cannot cover all rows and fees.  Otherwise, if a
+    /// transaction fails, the report holds the error, along with the
+    /// receipts of earlier transactions.  Its inputs are returned to
+    /// confirmed if none was logged as spent, and are otherwise left
+    /// pending.  If its inputs were all logged but its output Dbcs could
+    /// not be built, the DbcBuilder is kept in the wallet, see
+    /// Wallet::take_unbuilt().
+    pub fn pay<C: SpentBookClient, K: SpentProofKeyVerifier>(
+        &self,
+        wallet: &mut Wallet,
+        change_sk: &SecretKey,
+        client: &mut C,
+        verifier: &K,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> PayoutReport {
+        let mut receipts = vec![];
+        let error = self
+            .pay_rows(wallet, change_sk, client, verifier, &mut rng, &mut receipts)
+            .err();
+        PayoutReport { receipts, error }
+    }
+
+    // pays the rows, appending a receipt for each row as it is paid.
+    fn pay_rows<C: SpentBookClient, K: SpentProofKeyVerifier>(
+        &self,
+        wallet: &mut Wallet,
+        change_sk: &SecretKey,
+        client: &mut C,
+        verifier: &K,
+        mut rng: impl RngCore + CryptoRng,
+        receipts: &mut Vec<PayoutReceipt>,
+    ) -> Result<()> {
+        for (idx, row) in self.rows.iter().enumerate() {
+            if row.amount == Token::zero() {
+                return Err(Error::PayoutRowAmountZero(idx));
+            }
+            if row.amount < self.dust_threshold {
+                return Err(Error::PayoutRowAmountDust(idx));
+            }
+        }
+
+        let fees = (0..self.num_transactions()).map(|_| self.fee);
+        let requested = self
+            .rows
+            .iter()
+            .map(|row| row.amount)
+            .chain(fees)
+            .try_fold(Token::zero(), Token::checked_add)
+            .ok_or(Error::ExcessiveTokenValue)?;
+        let available = wallet.balance_confirmed()?;
+        if requested > available {
+            return Err(Error::InsufficientBalance {
+                available,
+                requested,
+            });
+        }
+
+        let change_owner = Owner::from(change_sk.public_key());
+        for rows in self.rows.chunks(self.max_outputs.max(1)) {
+            let amount = rows
+                .iter()
+                .try_fold(self.fee, |sum, row| sum.checked_add(row.amount))
+                .ok_or(Error::ExcessiveTokenValue)?;
+            let selection = LargestFirst.select(wallet.spendable_dbcs(), amount)?;
+
+            let mut tx_builder = TransactionBuilder::default()
+                .set_decoys_per_input(self.decoys_per_input)
+                .set_fee(self.fee)
+                .set_dust_threshold(self.dust_threshold)
+                .set_change_owner(OwnerOnce::from_owner_base(change_owner.clone(), &mut rng));
+            let mut key_images: Vec<KeyImage> = vec![];
+            for (dbc, base_sk) in selection.inputs.iter() {
+                key_images.push(dbc.key_image(base_sk)?);
+                tx_builder = tx_builder.add_input_dbc(dbc, base_sk)?;
+            }
+
+            let mut row_owners = vec![];
+            for row in rows {
+                let owner_once = OwnerOnce::from_owner_base(Owner::from(row.recipient), &mut rng);
+                tx_builder = if row.memo.is_empty() {
+                    tx_builder.add_output_by_amount(row.amount, owner_once.clone())
+                } else {
+                    tx_builder.add_output_by_amount_with_metadata(
+                        row.amount,
+                        owner_once.clone(),
+                        DbcMetadata::from_memo(row.memo.clone()),
+                    )
+                };
+                row_owners.push((row, owner_once));
+            }
+
+            let mut output_dbcs: BTreeMap<PublicKey, Dbc> = wallet
+                .send(tx_builder, key_images, client, verifier, &mut rng)?
+                .into_iter()
+                .map(|(dbc, owner_once, _)| (owner_once.as_owner().public_key(), dbc))
+                .collect();
+            for (row, owner_once) in row_owners {
+                let dbc = output_dbcs
+                    .remove(&owner_once.as_owner().public_key())
+                    .ok_or(Error::OutputProofNotFound)?;
+                receipts.push(PayoutReceipt {
+                    row: row.clone(),
+                    owner_once,
+                    dbc,
+                });
+            }
+            // note: the remaining output, if any, is the change.
+            for (_, dbc) in output_dbcs {
+                wallet.add_dbc(dbc, change_sk)?;
+            }
+        }
+        Ok(())
+    }
+
+    // parses a line of CSV into a PayoutRow, or a reason that it is invalid.
+    fn parse_csv_line(line: &str) -> std::result::Result<PayoutRow, String> {
+        let mut fields = line.splitn(3, ',').map(str::trim);
+        let recipient = fields.next().unwrap_or_default();
+        let recipient = PublicKey::from_hex(recipient)
+            .map_err(|e| format!("invalid recipient {:?}: {}", recipient, e))?;
+        let amount = fields.next().ok_or("missing amount")?;
+        let amount =
+            Token::from_str(amount).map_err(|e| format!("invalid amount {:?}: {}", amount, e))?;
+
+        let memo = fields.next().unwrap_or_default();
+        let memo = match memo.strip_prefix('"') {
+            Some(quoted) => quoted
+                .strip_suffix('"')
+                .ok_or("memo is missing its closing quote")?
+                .replace("\"\"", "\""),
+            None => memo.to_string(),
+        };
+
+        Ok(PayoutRow {
+            recipient,
+            amount,
+            memo,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock,
+        rand::{distributions::Standard, Rng},
+    };
+
+    #[test]
+    fn from_csv_parses_rows() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let alice = rng.sample::<SecretKey, _>(Standard).public_key();
+        let bob = rng.sample::<SecretKey, _>(Standard).public_key();
+        let csv = format!(
+            "recipient,amount,memo\n{},1.5,\"march, and \"\"april\"\"\"\n\n{},0.000000002\n",
+            alice.to_hex(),
+            bob.to_hex()
+        );
+
+        let payout = BatchPayout::from_csv(&csv)?;
+        assert_eq!(
+            payout.rows(),
+            &[
+                PayoutRow {
+                    recipient: alice,
+                    amount: Token::from_nano(1_500_000_000),
+                    memo: "march, and \"april\"".to_string(),
+                },
+                PayoutRow {
+                    recipient: bob,
+                    amount: Token::from_nano(2),
+                    memo: "".to_string(),
+                },
+            ]
+        );
+
+        let csv = format!("{},1\n{}\n", alice.to_hex(), bob.to_hex());
+        assert_eq!(
+            BatchPayout::from_csv(&csv).err(),
+            Some(Error::PayoutCsvInvalid {
+                line: 2,
+                reason: "missing amount".to_string()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pay_reports_output_of_each_row() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (mut spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+        let verifier = spentbook_node.key_manager.clone();
+
+        let mut wallet = Wallet::default();
+        wallet.add_dbc_bearer(genesis_dbc)?;
+        let change_sk: SecretKey = rng.sample(Standard);
+
+        let recipients: Vec<SecretKey> = (0..5).map(|_| rng.sample(Standard)).collect();
+        let rows: Vec<PayoutRow> = recipients
+            .iter()
+            .enumerate()
+            .map(|(i, sk)| PayoutRow {
+                recipient: sk.public_key(),
+                amount: Token::from_nano(100 * (i as u64 + 1)),
+                memo: if i % 2 == 0 {
+                    format!("payment {}", i)
+                } else {
+                    "".to_string()
+                },
+            })
+            .collect();
+
+        // 5 rows of at most 2 outputs, so 3 transactions.
+        let payout = BatchPayout::new(rows.clone())
+            .set_max_outputs(2)
+            .set_decoys_per_input(0);
+        assert_eq!(payout.num_transactions(), 3);
+        let report = payout.pay(
+            &mut wallet,
+            &change_sk,
+            &mut spentbook_node,
+            &verifier,
+            &mut rng,
+        );
+        assert!(report.is_complete());
+        assert_eq!(report.receipts.len(), rows.len());
+        for ((receipt, row), sk) in report.receipts.iter().zip(rows.iter()).zip(recipients) {
+            assert_eq!(&receipt.row, row);
+            assert_eq!(receipt.dbc.amount_secrets(&sk)?.amount(), row.amount);
+            let memo = receipt.dbc.metadata(&sk)?.map(|m| m.memo);
+            assert_eq!(memo.unwrap_or_default(), row.memo);
+        }
+        assert_eq!(
+            wallet.balance_confirmed()?,
+            Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT - 1500)
+        );
+
+        // a second payout, with decoys fetched from the spentbook.
+        let report = BatchPayout::new(rows[..2].to_vec())
+            .set_decoys_per_input(3)
+            .pay(
+                &mut wallet,
+                &change_sk,
+                &mut spentbook_node,
+                &verifier,
+                &mut rng,
+            );
+        assert!(report.is_complete());
+        assert_eq!(report.receipts.len(), 2);
+        for receipt in report.receipts.iter() {
+            let mlsags = &receipt.dbc.transaction.mlsags;
+            assert!(mlsags.iter().all(|m| m.ring.len() == 4));
+        }
+        assert_eq!(
+            wallet.balance_confirmed()?,
+            Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT - 1800)
+        );
+
+        // nothing is paid if a row is zero or dust.
+        let mut invalid = rows.clone();
+        invalid[3].amount = Token::zero();
+        let report = BatchPayout::new(invalid).set_decoys_per_input(0).pay(
+            &mut wallet,
+            &change_sk,
+            &mut spentbook_node,
+            &verifier,
+            &mut rng,
+        );
+        assert!(report.receipts.is_empty());
+        assert_eq!(report.error, Some(Error::PayoutRowAmountZero(3)));
+        let report = BatchPayout::new(rows.clone())
+            .set_decoys_per_input(0)
+            .set_dust_threshold(Token::from_nano(150))
+            .pay(
+                &mut wallet,
+                &change_sk,
+                &mut spentbook_node,
+                &verifier,
+                &mut rng,
+            );
+        assert!(report.receipts.is_empty());
+        assert_eq!(report.error, Some(Error::PayoutRowAmountDust(0)));
+
+        // nothing is paid if the wallet cannot cover every row.
+        let mut rows = rows;
+        rows[4].amount = wallet.balance_confirmed()?;
+        let report = BatchPayout::new(rows).set_decoys_per_input(0).pay(
+            &mut wallet,
+            &change_sk,
+            &mut spentbook_node,
+            &verifier,
+            &mut rng,
+        );
+        assert!(report.receipts.is_empty());
+        assert!(matches!(
+            report.error,
+            Some(Error::InsufficientBalance { .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,655 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    multisig::{sum_blindings, unsigned_mlsag},
+    rand::{CryptoRng, RngCore},
+    DbcBuilder, Error, KeyImage, OutputMetadataMap, OutputOwnerMap, PublicKey, Result,
+    RevealedCommitment, RingCtMaterial, RingCtTransaction, Token,
+};
+use bls_ringct::{
+    bls_bulletproofs::{merlin::Transcript, BulletproofGens, PedersenGens, RangeProof},
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve},
+    mlsag::MlsagSignature,
+    ringct::OutputProof,
+};
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+// note: these must match bls_ringct, as RingCtTransaction::verify() checks
+// all output range proofs in sequence against a single transcript.
+const MERLIN_TRANSCRIPT_LABEL: &[u8] = b"BLST_RINGCT";
+const RANGE_PROOF_BITS: usize = 64;
+
+/// A partially signed transaction, to which multiple parties each add
+/// inputs and outputs, and then each sign their own inputs.
+///
+/// The proposal holds only public data, so it may be serialized and
+/// passed between the parties (or via a coordinator) in any order:
+///   1. each party builds its inputs and outputs with a TransactionBuilder,
+///      calls TransactionBuilder::build_proposal_party(), and adds itself
+///      to the proposal via ProposalParty::add_to().
+///   2. once all parties have added theirs, each party signs its
+///      own inputs via ProposalParty::sign().
+///   3. when all inputs are signed, anyone can call ::finalize() to
+///      obtain the RingCtTransaction.  Each party then obtains a DbcBuilder
+///      for its own outputs via ProposalParty::dbc_builder().
+///
+/// Each party's inputs must exactly fund its outputs (including change).
+/// Each party's pseudo-commitments are balanced against its own outputs,
+/// so no party learns another's amounts or blinding factors.
+///
+/// Fees are not supported, as the fee output key is derived from the key
+/// images of all inputs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionProposal {
+    pub(crate) mlsags: Vec<MlsagSignature>,
+    pub(crate) outputs: Vec<OutputProof>,
+    pub(crate) signed: BTreeSet<KeyImage>,
+}
+
+impl TransactionProposal {
+    /// returns the key images of all inputs
+    pub fn key_images(&self) -> Vec<KeyImage> {
+        self.mlsags.iter().map(|m| m.key_image.into()).collect()
+    }
+
+    /// returns the key images of inputs that are not yet signed
+    pub fn unsigned_key_images(&self) -> Vec<KeyImage> {
+        self.key_images()
+            .into_iter()
+            .filter(|k| !self.signed.contains(k))
+            .collect()
+    }
+
+    /// returns the output proofs
+    pub fn outputs(&self) -> &[OutputProof] {
+        &self.outputs
+    }
+
+    /// returns true if every input has been signed
+    pub fn is_complete(&self) -> bool {
+        self.signed.len() == self.mlsags.len()
+    }
+
+    /// assemble and verify the transaction, once all inputs are signed.
+    pub fn finalize(self) -> Result<RingCtTransaction> {
+        if !self.is_complete() {
+            return Err(Error::TransactionProposalIncomplete(
+                self.unsigned_key_images(),
+            ));
+        }
+        let transaction = self.transaction();
+
+        let public_commitments: Vec<Vec<G1Affine>> =
+            transaction.mlsags.iter().map(public_commitments).collect();
+        transaction.verify(&public_commitments)?;
+
+        Ok(transaction)
+    }
+
+    pub(crate) fn transaction(&self) -> RingCtTransaction {
+        RingCtTransaction {
+            mlsags: self.mlsags.clone(),
+            outputs: self.outputs.clone(),
+        }
+    }
+}
+
+// the public commitments of a ring, recovered from the hidden commitments
+// and the pseudo-commitment.
+pub(crate) fn public_commitments(mlsag: &MlsagSignature) -> Vec<G1Affine> {
+    mlsag
+        .ring
+        .iter()
+        .map(|(_, hidden)| (*hidden + G1Projective::from(mlsag.pseudo_commitment)).to_affine())
+        .collect()
+}
+
+// forms an OutputProof from its parts, by way of its serialized form, as
+// OutputProof has no constructor.  The layout is pinned by a test.
+fn output_proof(
+    public_key: G1Affine,
+    range_proof: &RangeProof,
+    commitment: G1Affine,
+) -> Result<OutputProof> {
+    let parts = OutputProofParts {
+        public_key,
+        range_proof,
+        commitment,
+    };
+    let bytes = bincode::serialize(&parts).map_err(|e| Error::OutputProofLayout(e.to_string()))?;
+    bincode::deserialize(&bytes).map_err(|e| Error::OutputProofLayout(e.to_string()))
+}
+
+// verifies the output range proofs in sequence, returning the transcript
+// with which any further output must be proven.
+pub(crate) fn range_proof_transcript(outputs: &[OutputProof]) -> Result<Transcript> {
+    let bp_gens = BulletproofGens::new(RANGE_PROOF_BITS, 1);
+    let pc_gens = PedersenGens::default();
+    let mut transcript = Transcript::new(MERLIN_TRANSCRIPT_LABEL);
+    for output in outputs.iter() {
+        output
+            .range_proof()
+            .verify_single(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &output.commitment(),
+                RANGE_PROOF_BITS,
+            )
+            .map_err(bls_ringct::Error::from)?;
+    }
+    Ok(transcript)
+}
+
+/// One party's contribution to a TransactionProposal, including the
+/// secrets needed to sign its inputs and to build its output Dbcs.
+///
+/// This must be kept private to the party.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposalParty {
+    ringct_material: RingCtMaterial,
+    revealed_pseudo_commitments: Vec<RevealedCommitment>,
+    revealed_output_commitments: Vec<RevealedCommitment>,
+    mlsags: Vec<MlsagSignature>,
+    output_owner_map: OutputOwnerMap,
+    output_metadata: OutputMetadataMap,
+}
+
+// mirrors the serialized form of OutputProof, which has no constructor.
+#[derive(Serialize)]
+struct OutputProofParts<'a> {
+    public_key: G1Affine,
+    range_proof: &'a RangeProof,
+    commitment: G1Affine,
+}
+
+impl ProposalParty {
+    pub(crate) fn new(
+        ringct_material: RingCtMaterial,
+        output_owner_map: OutputOwnerMap,
+        output_metadata: OutputMetadataMap,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Self> {
+        if ringct_material.outputs.is_empty() {
+            return Err(Error::TransactionMustHaveAnOutput);
+        }
+        let inputs_amount = ringct_material
+            .inputs
+            .iter()
+            .try_fold(Token::zero(), |sum, m| {
+                sum.checked_add(Token::from_nano(m.true_input.revealed_commitment.value))
+            })
+            .ok_or(Error::ExcessiveTokenValue)?;
+        let outputs_amount = ringct_material
+            .outputs
+            .iter()
+            .try_fold(Token::zero(), |sum, o| {
+                sum.checked_add(Token::from_nano(o.amount))
+            })
+            .ok_or(Error::ExcessiveTokenValue)?;
+        if inputs_amount != outputs_amount {
+            return Err(Error::TransactionProposalUnbalanced {
+                inputs: inputs_amount,
+                outputs: outputs_amount,
+            });
+        }
+
+        let revealed_output_commitments: Vec<RevealedCommitment> = ringct_material
+            .outputs
+            .iter()
+            .map(|o| RevealedCommitment::from_value(o.amount, &mut rng))
+            .collect();
+
+        if ringct_material.inputs.is_empty() {
+            return Err(bls_ringct::Error::TransactionMustHaveAnInput.into());
+        }
+        let revealed_pseudo_commitments: Vec<RevealedCommitment> = ringct_material
+            .inputs
+            .iter()
+            .map(|m| m.true_input.random_pseudo_commitment(&mut rng))
+            .collect();
+
+        let mut party = Self {
+            ringct_material,
+            revealed_pseudo_commitments,
+            revealed_output_commitments,
+            mlsags: Vec::new(),
+            output_owner_map,
+            output_metadata,
+        };
+        party.set_blinding_offset(Scalar::zero());
+        Ok(party)
+    }
+
+    /// Balances our pseudo-commitments against our own outputs, less the
+    /// offset, ie so that the output blindings less the pseudo-commitment
+    /// blindings sum to the offset.
+    ///
+    /// With a zero offset, anyone can match our inputs to our outputs by
+    /// trying subsets of the commitments.  A coordinator may instead hand
+    /// each party a secret offset, such that all the offsets sum to zero.
+    /// see CoinJoinCoordinator.
+    pub(crate) fn set_blinding_offset(&mut self, offset: Scalar) {
+        if let Some((last, others)) = self.revealed_pseudo_commitments.split_last_mut() {
+            last.blinding =
+                sum_blindings(&self.revealed_output_commitments) - sum_blindings(others) - offset;
+        }
+
+        let pc_gens = PedersenGens::default();
+        self.mlsags = self
+            .ringct_material
+            .inputs
+            .iter()
+            .zip(self.revealed_pseudo_commitments.iter())
+            .map(|(m, r)| {
+                unsigned_mlsag(
+                    m.public_keys(),
+                    m.commitments(&pc_gens),
+                    m.true_input.key_image().to_affine(),
+                    r.commit(&pc_gens),
+                )
+            })
+            .collect();
+    }
+
+    // our unsigned input signatures, ie the rings and pseudo-commitments.
+    pub(crate) fn unsigned_mlsags(&self) -> &[MlsagSignature] {
+        &self.mlsags
+    }
+
+    /// returns the key images of this party's inputs
+    pub fn key_images(&self) -> Vec<KeyImage> {
+        self.mlsags.iter().map(|m| m.key_image.into()).collect()
+    }
+
+    /// returns the public keys of this party's outputs
+    pub fn output_public_keys(&self) -> Vec<PublicKey> {
+        self.ringct_material
+            .outputs
+            .iter()
+            .map(|o| o.public_key().into())
+            .collect()
+    }
+
+    /// add this party's inputs and outputs to the proposal.
+    ///
+    /// The output range proofs are chained to those already in the
+    /// proposal, so the party must add itself.  This fails once any input
+    /// has been signed, as the signatures cover all inputs and outputs.
+    pub fn add_to(
+        &self,
+        proposal: TransactionProposal,
+        rng: impl RngCore + CryptoRng,
+    ) -> Result<TransactionProposal> {
+        self.add_outputs(self.add_inputs(proposal)?, rng)
+    }
+
+    pub(crate) fn add_inputs(
+        &self,
+        mut proposal: TransactionProposal,
+    ) -> Result<TransactionProposal> {
+        if !proposal.signed.is_empty() {
+            return Err(Error::TransactionProposalAlreadySigned);
+        }
+        for mlsag in self.mlsags.iter() {
+            if proposal
+                .mlsags
+                .iter()
+                .any(|m| m.key_image == mlsag.key_image)
+            {
+                return Err(bls_ringct::Error::KeyImageNotUniqueAcrossInputs.into());
+            }
+        }
+        proposal.mlsags.extend(self.mlsags.iter().cloned());
+        Ok(proposal)
+    }
+
+    pub(crate) fn add_outputs(
+        &self,
+        mut proposal: TransactionProposal,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<TransactionProposal> {
+        if !proposal.signed.is_empty() {
+            return Err(Error::TransactionProposalAlreadySigned);
+        }
+        for output in self.ringct_material.outputs.iter() {
+            if proposal
+                .outputs
+                .iter()
+                .any(|o| *o.public_key() == output.public_key())
+            {
+                return Err(Error::PublicKeyNotUniqueAcrossOutputs);
+            }
+        }
+
+        // bring the transcript up to date by verifying the existing
+        // range proofs, then prove our own outputs in continuation.
+        let bp_gens = BulletproofGens::new(RANGE_PROOF_BITS, 1);
+        let pc_gens = PedersenGens::default();
+        let mut transcript = range_proof_transcript(&proposal.outputs)?;
+        for (output, revealed) in self
+            .ringct_material
+            .outputs
+            .iter()
+            .zip(self.revealed_output_commitments.iter())
+        {
+            let (range_proof, commitment) = RangeProof::prove_single_with_rng(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                revealed.value,
+                &revealed.blinding,
+                RANGE_PROOF_BITS,
+                &mut rng,
+            )
+            .map_err(bls_ringct::Error::from)?;
+
+            proposal
+                .outputs
+                .push(output_proof(output.public_key(), &range_proof, commitment)?);
+        }
+
+        Ok(proposal)
+    }
+
+    /// sign this party's inputs.
+    ///
+    /// The proposal must contain this party's inputs and outputs unaltered,
+    /// else the signatures could commit the party to something else.
+    pub fn sign(&self, mut proposal: TransactionProposal) -> Result<TransactionProposal> {
+        let mut mlsag_indexes = Vec::new();
+        for mlsag in self.mlsags.iter() {
+            let idx = proposal
+                .mlsags
+                .iter()
+                .position(|m| m.key_image == mlsag.key_image)
+                .ok_or(Error::TransactionProposalMismatch)?;
+            let proposed = &proposal.mlsags[idx];
+            if proposed.ring != mlsag.ring || proposed.pseudo_commitment != mlsag.pseudo_commitment
+            {
+                return Err(Error::TransactionProposalMismatch);
+            }
+            mlsag_indexes.push(idx);
+        }
+
+        let pc_gens = PedersenGens::default();
+        for (output, revealed) in self
+            .ringct_material
+            .outputs
+            .iter()
+            .zip(self.revealed_output_commitments.iter())
+        {
+            let commitment = revealed.commit(&pc_gens).to_affine();
+            if !proposal
+                .outputs
+                .iter()
+                .any(|o| *o.public_key() == output.public_key() && o.commitment() == commitment)
+            {
+                return Err(Error::TransactionProposalMismatch);
+            }
+        }
+
+        // note: the message must be generated from the proposal before any of
+        // our inputs are signed.  It does not include the signatures.
+        let msg = proposal.transaction().gen_message();
+        for ((m, r), idx) in self
+            .ringct_material
+            .inputs
+            .iter()
+            .zip(self.revealed_pseudo_commitments.iter())
+            .zip(mlsag_indexes)
+        {
+            proposal.mlsags[idx] = m.sign(&msg, r, &pc_gens);
+            proposal
+                .signed
+                .insert(m.true_input.key_image().to_affine().into());
+        }
+
+        Ok(proposal)
+    }
+
+    /// returns a DbcBuilder for this party's outputs of the finalized
+    /// transaction.  The outputs of other parties do not become Dbcs.
+    pub fn dbc_builder(&self, transaction: RingCtTransaction) -> DbcBuilder {
+        let own_outputs = self.output_public_keys();
+        let foreign_outputs = transaction
+            .outputs
+            .iter()
+            .map(|o| PublicKey::from(*o.public_key()))
+            .filter(|pk| !own_outputs.contains(pk))
+            .collect();
+        DbcBuilder {
+            output_metadata: self.output_metadata.clone(),
+            foreign_outputs,
+            ..DbcBuilder::new(
+                transaction,
+                self.revealed_output_commitments.clone(),
+                self.output_owner_map.clone(),
+                self.ringct_material.clone(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::spend_genesis,
+        mock,
+        rand::{distributions::Standard, Rng},
+        Dbc, Owner, OwnerOnce, TransactionBuilder,
+    };
+    use blsttc::SecretKey;
+
+    #[test]
+    fn parties_sign_their_own_inputs() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        // give alice and bob a Dbc each.
+        let alice_sk: SecretKey = rng.sample(Standard);
+        let bob_sk: SecretKey = rng.sample(Standard);
+        let (mut spentbook_node, dbcs) = spend_genesis(&mut rng, |tx_builder, rng| {
+            Ok(tx_builder
+                .add_output_by_amount(
+                    Token::from_nano(1000),
+                    OwnerOnce::from_owner_base(Owner::from(alice_sk.public_key()), rng),
+                )
+                .add_output_by_amount(
+                    Token::from_nano(500),
+                    OwnerOnce::from_owner_base(Owner::from(bob_sk.public_key()), rng),
+                ))
+        })?;
+        let find = |sk: &SecretKey| -> Result<Dbc> {
+            dbcs.iter()
+                .map(|(dbc, ..)| dbc)
+                .find(|dbc| dbc.owner_base().public_key() == sk.public_key())
+                .cloned()
+                .ok_or(Error::PublicKeyNotFound)
+        };
+        let (alice_dbc, bob_dbc) = (find(&alice_sk)?, find(&bob_sk)?);
+
+        // alice and bob jointly pay carol 1200, each funding part of it.
+        let carol_sk: SecretKey = rng.sample(Standard);
+        let alice = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .add_input_dbc(&alice_dbc, &alice_sk)?
+            .add_output_by_amount(
+                Token::from_nano(800),
+                OwnerOnce::from_owner_base(Owner::from(carol_sk.public_key()), &mut rng),
+            )
+            .set_change_owner(OwnerOnce::from_owner_base(
+                Owner::from(alice_sk.public_key()),
+                &mut rng,
+            ))
+            .build_proposal_party(&mut rng)?;
+        let bob = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .add_input_dbc(&bob_dbc, &bob_sk)?
+            .add_output_by_amount(
+                Token::from_nano(400),
+                OwnerOnce::from_owner_base(Owner::from(carol_sk.public_key()), &mut rng),
+            )
+            .set_change_owner(OwnerOnce::from_owner_base(
+                Owner::from(bob_sk.public_key()),
+                &mut rng,
+            ))
+            .build_proposal_party(&mut rng)?;
+
+        // the proposal is passed around in serialized form.
+        let pass = |p: TransactionProposal| -> Result<TransactionProposal> {
+            let bytes =
+                bincode::serialize(&p).map_err(|e| Error::HexSerializationFailed(e.to_string()))?;
+            bincode::deserialize(&bytes).map_err(|e| Error::HexDeserializationFailed(e.to_string()))
+        };
+
+        let proposal = pass(alice.add_to(TransactionProposal::default(), &mut rng)?)?;
+        let proposal = pass(bob.add_to(proposal, &mut rng)?)?;
+        let proposal = pass(alice.sign(proposal)?)?;
+        assert_eq!(proposal.unsigned_key_images(), bob.key_images());
+        assert_eq!(
+            proposal.clone().finalize().err(),
+            Some(Error::TransactionProposalIncomplete(bob.key_images()))
+        );
+        assert_eq!(
+            alice.add_to(proposal.clone(), &mut rng).err(),
+            Some(Error::TransactionProposalAlreadySigned)
+        );
+
+        let proposal = pass(bob.sign(proposal)?)?;
+        let transaction = proposal.finalize()?;
+
+        // all inputs are logged as spent, and each party builds its own outputs.
+        let mut spent_proof_shares = Vec::new();
+        for key_image in transaction.mlsags.iter().map(|m| m.key_image.into()) {
+            spent_proof_shares.push(spentbook_node.log_spent(key_image, transaction.clone())?);
+        }
+        // without the outputs of other parties marked as such, a DbcBuilder
+        // reports the outputs it can not open.
+        let unmarked = DbcBuilder {
+            foreign_outputs: Default::default(),
+            ..alice.dbc_builder(transaction.clone())
+        };
+        assert_eq!(
+            unmarked
+                .add_spent_proof_shares(spent_proof_shares.clone())
+                .add_spent_transaction(transaction.clone())
+                .build(&spentbook_node.key_manager)
+                .err(),
+            Some(Error::RevealedCommitmentNotFound)
+        );
+
+        let mut outputs = Vec::new();
+        for party in [&alice, &bob] {
+            let party_dbcs = party
+                .dbc_builder(transaction.clone())
+                .add_spent_proof_shares(spent_proof_shares.clone())
+                .add_spent_transaction(transaction.clone())
+                .build(&spentbook_node.key_manager)?;
+            assert_eq!(party_dbcs.len(), 2);
+            outputs.extend(party_dbcs.into_iter().map(|(dbc, ..)| dbc));
+        }
+
+        let carol_received = outputs
+            .iter()
+            .filter(|dbc| dbc.owner_base().public_key() == carol_sk.public_key())
+            .map(|dbc| dbc.amount_secrets(&carol_sk).map(|a| a.amount().as_nano()))
+            .sum::<Result<u64>>()?;
+        assert_eq!(carol_received, 1200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_proof_layout_is_pinned() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (_spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+
+        // an OutputProof formed from the parts of one from bls_ringct is
+        // identical to it.  If this fails, OutputProofParts must be updated.
+        let output = &genesis_dbc.transaction.outputs[0];
+        let formed = output_proof(
+            *output.public_key(),
+            output.range_proof(),
+            output.commitment(),
+        )?;
+        assert_eq!(&formed, output);
+        assert_eq!(formed.to_bytes(), output.to_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn proposal_misuse_is_reported() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (_spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+
+        // a party's inputs must exactly fund its outputs.
+        let result = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .add_input_dbc_bearer(&genesis_dbc)?
+            .add_output_by_amount(
+                Token::from_nano(100),
+                OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng),
+            )
+            .build_proposal_party(&mut rng);
+        assert!(matches!(
+            result,
+            Err(Error::TransactionProposalUnbalanced { .. })
+        ));
+
+        let party = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .add_input_dbc_bearer(&genesis_dbc)?
+            .set_change_owner(OwnerOnce::from_owner_base(
+                Owner::from_random_secret_key(&mut rng),
+                &mut rng,
+            ))
+            .build_proposal_party(&mut rng)?;
+
+        // a party cannot be added twice.
+        let proposal = party.add_to(TransactionProposal::default(), &mut rng)?;
+        assert_eq!(
+            party.add_to(proposal.clone(), &mut rng).err(),
+            Some(Error::RingCt(
+                bls_ringct::Error::KeyImageNotUniqueAcrossInputs
+            ))
+        );
+
+        // a party will not sign a proposal missing or altering its outputs.
+        let mut altered = proposal.clone();
+        altered.outputs.clear();
+        assert_eq!(
+            party.sign(altered).err(),
+            Some(Error::TransactionProposalMismatch)
+        );
+        assert_eq!(
+            party.sign(TransactionProposal::default()).err(),
+            Some(Error::TransactionProposalMismatch)
+        );
+
+        // fees are not supported.
+        let result = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .add_input_dbc_bearer(&genesis_dbc)?
+            .set_fee(Token::from_nano(1))
+            .set_change_owner(OwnerOnce::from_owner_base(
+                Owner::from_random_secret_key(&mut rng),
+                &mut rng,
+            ))
+            .build_proposal_party(&mut rng);
+        assert_eq!(result.err(), Some(Error::TransactionProposalFeeUnsupported));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{CryptoRng, RngCore},
+    BlindingFactor, Dbc, Error, PublicKey, Result, Signature, SpentProof, SpentProofKeyVerifier,
+    Token, TransactionVerifier,
+};
+use bls_ringct::{
+    bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Curve, Group},
+        merlin::Transcript,
+        BulletproofGens, PedersenGens, RangeProof,
+    },
+    ringct::RingCtTransaction,
+};
+use blsttc::SecretKey;
+use std::collections::BTreeSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const RESERVES_TRANSCRIPT_LABEL: &[u8] = b"sn_dbc reserves proof";
+// note: range proofs are limited to 64 bits, as is Token.
+const RANGE_PROOF_BITS: usize = 64;
+
+/// A Dbc included in a ReservesProof, with the holder's signature by
+/// its one-time-use SecretKey.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ReservesEntry {
+    output_public_key: PublicKey,
+    transaction: RingCtTransaction,
+    spent_proofs: BTreeSet<SpentProof>,
+    signature: Signature,
+}
+
+impl ReservesEntry {
+    /// returns the one-time-use PublicKey of the Dbc
+    pub fn output_public_key(&self) -> PublicKey {
+        self.output_public_key
+    }
+}
+
+/// A zero-knowledge proof that the holder of a set of Dbcs owns at least
+/// a threshold amount, without revealing the amount of any Dbc.
+///
+/// The proof consists of:
+///  1. a signature over the statement by the one-time-use key of each Dbc,
+///     proving knowledge of the keys.
+///  2. a bulletproof range proof that the sum of the Dbcs' output
+///     commitments, less the threshold, commits to a non-negative amount.
+///
+/// The statement includes a challenge chosen by the verifier, so that a
+/// proof cannot be replayed.
+///
+/// note: the proof does not show that the Dbcs are unspent.  The verifier
+/// must establish that separately, eg by querying the spentbook.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ReservesProof {
+    threshold: Token,
+    entries: Vec<ReservesEntry>,
+    range_proof: RangeProof,
+}
+
+impl ReservesProof {
+    /// create a ReservesProof that the given Dbcs, all owned by the given base
+    /// SecretKey, hold at least threshold in total.
+    pub fn new<'a>(
+        dbcs: impl IntoIterator<Item = &'a Dbc>,
+        base_sk: &SecretKey,
+        threshold: Token,
+        challenge: &[u8],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Self> {
+        let mut owners = Vec::new();
+        let mut total = Token::zero();
+        let mut blinding_sum = BlindingFactor::zero();
+        for dbc in dbcs {
+            let amount_secrets = dbc.amount_secrets(base_sk)?;
+            total = total
+                .checked_add(amount_secrets.amount())
+                .ok_or(Error::ExcessiveTokenValue)?;
+            blinding_sum += amount_secrets.blinding_factor();
+            owners.push((dbc, dbc.owner_once(base_sk)?.secret_key()?));
+        }
+
+        let excess = total
+            .checked_sub(threshold)
+            .ok_or(Error::InsufficientBalance {
+                available: total,
+                requested: threshold,
+            })?;
+
+        let output_public_keys: Vec<PublicKey> =
+            owners.iter().map(|(_, sk)| sk.public_key()).collect();
+        let statement = Self::statement(threshold, challenge, &output_public_keys);
+
+        let entries = owners
+            .into_iter()
+            .map(|(dbc, sk)| ReservesEntry {
+                output_public_key: sk.public_key(),
+                transaction: dbc.transaction.clone(),
+                spent_proofs: dbc.spent_proofs.clone(),
+                signature: sk.sign(&statement),
+            })
+            .collect();
+
+        let (range_proof, _commitment) = RangeProof::prove_single_with_rng(
+            &BulletproofGens::new(RANGE_PROOF_BITS, 1),
+            &PedersenGens::default(),
+            &mut Self::transcript(&statement),
+            excess.as_nano(),
+            &blinding_sum,
+            RANGE_PROOF_BITS,
+            &mut rng,
+        )
+        .map_err(bls_ringct::Error::from)?;
+
+        Ok(Self {
+            threshold,
+            entries,
+            range_proof,
+        })
+    }
+
+    /// returns the amount the holder proves to hold at least
+    pub fn threshold(&self) -> Token {
+        self.threshold
+    }
+
+    /// returns the Dbcs included in the proof
+    pub fn entries(&self) -> &[ReservesEntry] {
+        &self.entries
+    }
+
+    /// Verifies the proof for the given challenge.
+    ///
+    /// Each Dbc's transaction is verified along with its spent proofs.
+    ///
+    /// see TransactionVerifier::verify() for a description of
+    /// verifier requirements.
+    pub fn verify<K: SpentProofKeyVerifier>(&self, challenge: &[u8], verifier: &K) -> Result<()> {
+        let output_public_keys: Vec<PublicKey> =
+            self.entries.iter().map(|e| e.output_public_key).collect();
+        let unique: BTreeSet<&PublicKey> = output_public_keys.iter().collect();
+        if unique.len() != output_public_keys.len() {
+            return Err(Error::ReservesProofDuplicateDbc);
+        }
+        let statement = Self::statement(self.threshold, challenge, &output_public_keys);
+
+        let mut commitment_sum = G1Projective::identity();
+        for entry in self.entries.iter() {
+            if !entry.output_public_key.verify(&entry.signature, &statement) {
+                return Err(Error::FailedSignature);
+            }
+
+            TransactionVerifier::verify(verifier, &entry.transaction, &entry.spent_proofs)?;
+
+            let output = entry
+                .transaction
+                .outputs
+                .iter()
+                .find(|o| entry.output_public_key.eq(o.public_key()))
+                .ok_or(Error::OutputProofNotFound)?;
+            commitment_sum += output.commitment();
+        }
+
+        let pc_gens = PedersenGens::default();
+        let excess_commitment = commitment_sum - pc_gens.B * Scalar::from(self.threshold.as_nano());
+
+        self.range_proof
+            .verify_single(
+                &BulletproofGens::new(RANGE_PROOF_BITS, 1),
+                &pc_gens,
+                &mut Self::transcript(&statement),
+                &excess_commitment.to_affine(),
+                RANGE_PROOF_BITS,
+            )
+            .map_err(bls_ringct::Error::from)?;
+
+        Ok(())
+    }
+
+    // the statement signed by each one-time-use key, and bound to the range proof.
+    fn statement(threshold: Token, challenge: &[u8], output_public_keys: &[PublicKey]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Default::default();
+        bytes.extend(RESERVES_TRANSCRIPT_LABEL);
+        bytes.extend(threshold.as_nano().to_be_bytes());
+        bytes.extend((challenge.len() as u64).to_be_bytes());
+        bytes.extend(challenge);
+        for pk in output_public_keys {
+            bytes.extend(pk.to_bytes());
+        }
+        bytes
+    }
+
+    fn transcript(statement: &[u8]) -> Transcript {
+        let mut transcript = Transcript::new(RESERVES_TRANSCRIPT_LABEL);
+        transcript.append_message(b"statement", statement);
+        transcript
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::tests::pay_from_genesis,
+        rand::{distributions::Standard, Rng},
+        Owner,
+    };
+
+    #[test]
+    fn reserves_proof_shows_threshold_without_amounts() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let custodian_sk: SecretKey = rng.sample(Standard);
+
+        let (spentbook_node, reserves) = pay_from_genesis(
+            &[300, 450, 250],
+            &Owner::from(custodian_sk.public_key()),
+            &mut rng,
+        )?;
+
+        let challenge = b"audit 2022-06-30";
+
+        // the exact total, and any smaller threshold, can be proven.
+        for threshold in [1000, 999, 0] {
+            let proof = ReservesProof::new(
+                &reserves,
+                &custodian_sk,
+                Token::from_nano(threshold),
+                challenge,
+                &mut rng,
+            )?;
+            proof.verify(challenge, &spentbook_node.key_manager)?;
+        }
+
+        assert_eq!(
+            ReservesProof::new(
+                &reserves,
+                &custodian_sk,
+                Token::from_nano(1001),
+                challenge,
+                &mut rng
+            )
+            .err(),
+            Some(Error::InsufficientBalance {
+                available: Token::from_nano(1000),
+                requested: Token::from_nano(1001)
+            })
+        );
+
+        let proof = ReservesProof::new(
+            &reserves,
+            &custodian_sk,
+            Token::from_nano(900),
+            challenge,
+            &mut rng,
+        )?;
+
+        // the proof is bound to the challenge.
+        assert_eq!(
+            proof
+                .verify(b"audit 2022-09-30", &spentbook_node.key_manager)
+                .err(),
+            Some(Error::FailedSignature)
+        );
+
+        // raising the threshold invalidates the signatures.
+        let mut inflated = proof.clone();
+        inflated.threshold = Token::from_nano(1100);
+        assert_eq!(
+            inflated
+                .verify(challenge, &spentbook_node.key_manager)
+                .err(),
+            Some(Error::FailedSignature)
+        );
+
+        // and a range proof for a lower threshold does not verify.
+        let mut inflated = proof.clone();
+        inflated.range_proof =
+            ReservesProof::new(&reserves, &custodian_sk, Token::zero(), challenge, &mut rng)?
+                .range_proof;
+        assert!(matches!(
+            inflated.verify(challenge, &spentbook_node.key_manager),
+            Err(Error::RingCt(bls_ringct::Error::BulletProofs(_)))
+        ));
+
+        // a Dbc cannot be counted twice.
+        let mut doubled = proof.clone();
+        doubled.entries.push(doubled.entries[0].clone());
+        assert_eq!(
+            doubled.verify(challenge, &spentbook_node.key_manager).err(),
+            Some(Error::ReservesProofDuplicateDbc)
+        );
+
+        // dropping a Dbc breaks the signatures over the statement.
+        let mut dropped = proof;
+        dropped.entries.pop();
+        assert_eq!(
+            dropped.verify(challenge, &spentbook_node.key_manager).err(),
+            Some(Error::FailedSignature)
+        );
+
+        Ok(())
+    }
+}
@@ -7,7 +7,8 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{
-    Commitment, Error, Hash, KeyImage, PublicKey, PublicKeySet, Result, Signature, SignatureShare,
+    Commitment, DecoyProvider, Error, Fee, Hash, KeyImage, PublicKey, PublicKeySet, Result,
+    RingCtTransaction, Signature, SignatureShare,
 };
 
 use std::cmp::Ordering;
@@ -161,6 +162,20 @@ pub trait SpentProofKeyVerifier {
     fn verify_known_key(&self, key: &PublicKey) -> Result<(), Self::Error>;
 }
 
+/// To drive transactions through to output Dbcs, eg via ConsolidationPlanner,
+/// the caller must provide an implementation of this trait which logs
+/// inputs as spent with the spentbook, and provides decoys from it.
+pub trait SpentBookClient: DecoyProvider {
+    /// log key_image as spent by tx, which pays fee.  Returns the
+    /// SpentProofShare of each spentbook node that logged it.
+    fn log_spent_shares(
+        &mut self,
+        key_image: KeyImage,
+        tx: RingCtTransaction,
+        fee: &Fee,
+    ) -> Result<Vec<SpentProofShare>>;
+}
+
 /// SpentProof's are constructed when a DBC is logged to the spentbook.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -254,7 +269,7 @@ impl SpentProof {
 
         let pub_key = &self.spentbook_pub_key;
 
-        if !pub_key.verify(&self.spentbook_sig, &self.content.hash()) {
+        if !pub_key.verify(&self.spentbook_sig, self.content.hash()) {
             return Err(Error::InvalidSpentProofSignature(
                 *self.key_image(),
                 format!(
@@ -100,7 +100,6 @@ impl Display for Token {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::u64;
 
     #[test]
     fn from_str() -> Result<()> {
@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{Commitment, Error, Hash, KeyImage, Result, SpentProof, SpentProofKeyVerifier};
+use crate::{Commitment, Error, Fee, Hash, KeyImage, Result, SpentProof, SpentProofKeyVerifier};
 use bls_ringct::ringct::RingCtTransaction;
 use std::collections::BTreeSet;
 
@@ -100,4 +100,19 @@ impl TransactionVerifier {
 
         Ok(())
     }
+
+    /// Verifies a transaction including spent proofs, and that the
+    /// transaction pays the given fee.
+    ///
+    /// see TransactionVerifier::verify() for a description of
+    /// verifier requirements.
+    pub fn verify_with_fee<K: SpentProofKeyVerifier>(
+        verifier: &K,
+        transaction: &RingCtTransaction,
+        fee: &Fee,
+        spent_proofs: &BTreeSet<SpentProof>,
+    ) -> Result<(), Error> {
+        Self::verify(verifier, transaction, spent_proofs)?;
+        fee.verify(transaction)
+    }
 }
@@ -0,0 +1,158 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{distributions::Standard, Rng},
+    rng, AmountSecrets, Dbc, DerivationIndex, Error, Owner, PublicKey, Result, Token,
+};
+use blsttc::{serde_impl::SerdeSecret, SecretKey};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const VIEW_KEY_TAG: &[u8] = b"sn_dbc view key";
+
+/// A ViewKey can see, but not spend, Dbcs owned by a SplitKey Owner.
+///
+/// A SplitKey Owner has a spend key and a view key.  The derivation index
+/// and AmountSecrets of each Dbc are encrypted to the view key, so a ViewKey
+/// can decrypt them and compute balances.  The one-time-use owner key is
+/// derived from the spend key, so a TrueInput can only be produced with
+/// the spend SecretKey.  This enables watch-only wallets and accounting
+/// servers.
+///
+/// The ViewKey is derived one-way from the spend SecretKey, so the spend
+/// SecretKey alone suffices for all Dbc operations, via the usual Dbc
+/// methods that accept a base SecretKey.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ViewKey(SerdeSecret<SecretKey>);
+
+impl ViewKey {
+    /// derive the ViewKey from a spend SecretKey.
+    pub fn from_spend_key(spend_sk: &SecretKey) -> Self {
+        let seed = crate::sha3_256(&[VIEW_KEY_TAG, &spend_sk.to_bytes()].concat());
+        let sk: SecretKey = rng::from_seed(seed).sample(Standard);
+        Self(SerdeSecret(sk))
+    }
+
+    /// returns the view SecretKey
+    pub fn secret_key(&self) -> &SecretKey {
+        self.0.inner()
+    }
+
+    /// returns the view PublicKey
+    pub fn public_key(&self) -> PublicKey {
+        self.0.public_key()
+    }
+
+    /// decrypts and returns the derivation index of a Dbc
+    pub fn derivation_index(&self, dbc: &Dbc) -> Result<DerivationIndex> {
+        self.check_owner(dbc.owner_base())?;
+        dbc.content.derivation_index(self.secret_key())
+    }
+
+    /// decrypts and returns the AmountSecrets of a Dbc
+    pub fn amount_secrets(&self, dbc: &Dbc) -> Result<AmountSecrets> {
+        self.check_owner(dbc.owner_base())?;
+        dbc.content.amount_secrets(self.secret_key())
+    }
+
+    /// returns the one-time-use owner PublicKey of a Dbc.  This can be used
+    /// to locate the Dbc's output in a transaction.
+    pub fn owner_once_public_key(&self, dbc: &Dbc) -> Result<PublicKey> {
+        Ok(dbc
+            .owner_base()
+            .public_key()
+            .derive_child(&self.derivation_index(dbc)?))
+    }
+
+    /// returns the sum of the amounts of the given Dbcs
+    pub fn balance<'a>(&self, dbcs: impl IntoIterator<Item = &'a Dbc>) -> Result<Token> {
+        dbcs.into_iter().try_fold(Token::zero(), |sum, dbc| {
+            sum.checked_add(self.amount_secrets(dbc)?.amount())
+                .ok_or(Error::ExcessiveTokenValue)
+        })
+    }
+
+    fn check_owner(&self, owner: &Owner) -> Result<()> {
+        match owner {
+            Owner::SplitKey { view, .. } if *view == self.public_key() => Ok(()),
+            _ => Err(Error::ViewKeyDoesNotMatchOwner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dbc::tests::pay_from_genesis, mock, OwnerOnce, TransactionBuilder};
+
+    #[test]
+    fn view_key_sees_but_cannot_spend() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+
+        let spend_sk: SecretKey = rng.sample(Standard);
+        let view_key = ViewKey::from_spend_key(&spend_sk);
+        let owner = Owner::from_spend_key(&spend_sk);
+        assert_eq!(
+            owner,
+            Owner::from_split_keys(spend_sk.public_key(), view_key.public_key())
+        );
+
+        let (mut spentbook_node, dbcs) = pay_from_genesis(
+            &[100, mock::GenesisMaterial::GENESIS_AMOUNT - 100],
+            &owner,
+            &mut rng,
+        )?;
+
+        // the view key sees amounts and the one-time-use owner.
+        assert_eq!(
+            view_key.balance(dbcs.iter())?,
+            Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT)
+        );
+        assert_eq!(
+            view_key.owner_once_public_key(&dbcs[0])?,
+            dbcs[0].owner_once(&spend_sk)?.public_key()
+        );
+
+        // but cannot produce a TrueInput.
+        assert_eq!(
+            dbcs[0].as_true_input(view_key.secret_key()).err(),
+            Some(Error::SecretKeyDoesNotMatchPublicKey)
+        );
+
+        // the spend key can do everything, including spending.
+        assert_eq!(
+            dbcs[0].amount_secrets(&spend_sk)?.amount(),
+            view_key.amount_secrets(&dbcs[0])?.amount()
+        );
+        dbcs[0].verify(&spend_sk, &spentbook_node.key_manager)?;
+
+        let dbc_builder = TransactionBuilder::default()
+            .set_require_all_decoys(false)
+            .add_inputs_dbc(dbcs.iter().map(|dbc| (dbc.clone(), spend_sk.clone())))?
+            .add_output_by_amount(
+                Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT),
+                OwnerOnce::from_owner_base(Owner::from_random_secret_key(&mut rng), &mut rng),
+            )
+            .build(&mut rng)?;
+        for (key_image, tx) in dbc_builder.inputs() {
+            spentbook_node.log_spent(key_image, tx)?;
+        }
+
+        // a view key for another owner sees nothing.
+        let other_view_key = ViewKey::from_spend_key(&rng.sample(Standard));
+        assert_eq!(
+            other_view_key.amount_secrets(&dbcs[0]).err(),
+            Some(Error::ViewKeyDoesNotMatchOwner)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,482 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{CryptoRng, RngCore},
+    AmountSecrets, CoinSelector, Dbc, DbcBuilder, Error, KeyImage, LargestFirst, OwnerOnce, Result,
+    SpentBookClient, SpentProofKeyVerifier, Token, TransactionBuilder,
+};
+use blsttc::{serde_impl::SerdeSecret, SecretKey};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The spend status of a Dbc held in a Wallet.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DbcStatus {
+    /// The Dbc is unspent and available for spending.
+    Confirmed,
+    /// The Dbc is an input of a transaction that has been built but
+    /// not yet logged as spent in the SpentBook.
+    Pending,
+    /// The Dbc has been logged as spent in the SpentBook.
+    Spent,
+}
+
+/// A Dbc held by a Wallet, along with the base SecretKey that owns it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct WalletDbc {
+    dbc: Dbc,
+    base_sk: SerdeSecret<SecretKey>,
+    amount: Token,
+    status: DbcStatus,
+}
+
+impl WalletDbc {
+    /// returns the Dbc
+    pub fn dbc(&self) -> &Dbc {
+        &self.dbc
+    }
+
+    /// returns the base SecretKey that owns the Dbc
+    pub fn base_sk(&self) -> &SecretKey {
+        self.base_sk.inner()
+    }
+
+    /// returns the (decrypted) amount of the Dbc
+    pub fn amount(&self) -> Token {
+        self.amount
+    }
+
+    /// returns the spend status of the Dbc
+    pub fn status(&self) -> DbcStatus {
+        self.status
+    }
+}
+
+/// A Wallet stores owned and bearer Dbcs keyed by KeyImage, along with
+/// the base SecretKey for each, and tracks whether each Dbc is
+/// confirmed (spendable), pending (in an unconfirmed transaction)
+/// or spent.
+///
+/// The Wallet does not communicate with a SpentBook.  The caller is
+/// responsible for logging transactions built from the wallet as spent
+/// and then informing the wallet via Wallet::confirm_spent() or
+/// Wallet::cancel_pending().
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub struct Wallet {
+    dbcs: BTreeMap<KeyImage, WalletDbc>,
+    unbuilt: Vec<DbcBuilder>,
+}
+
+impl Wallet {
+    /// add an owned Dbc, along with the base SecretKey that owns it.
+    ///
+    /// The amount is decrypted and cached, so this will return an error
+    /// if the SecretKey does not match the Dbc owner.
+    ///
+    /// If the Dbc is already present in the wallet, its status is left unchanged.
+    pub fn add_dbc(&mut self, dbc: Dbc, base_sk: &SecretKey) -> Result<KeyImage> {
+        let key_image = dbc.key_image(base_sk)?;
+        let amount = dbc.amount_secrets(base_sk)?.amount();
+
+        self.dbcs.entry(key_image).or_insert_with(|| WalletDbc {
+            dbc,
+            base_sk: SerdeSecret(base_sk.clone()),
+            amount,
+            status: DbcStatus::Confirmed,
+        });
+        Ok(key_image)
+    }
+
+    /// add a bearer Dbc.  The base SecretKey is obtained from the Dbc itself.
+    pub fn add_dbc_bearer(&mut self, dbc: Dbc) -> Result<KeyImage> {
+        let base_sk = dbc.owner_base().secret_key()?;
+        self.add_dbc(dbc, &base_sk)
+    }
+
+    /// add a list of owned Dbcs and associated base SecretKey
+    pub fn add_dbcs(
+        &mut self,
+        dbcs: impl IntoIterator<Item = (Dbc, SecretKey)>,
+    ) -> Result<Vec<KeyImage>> {
+        dbcs.into_iter()
+            .map(|(dbc, base_sk)| self.add_dbc(dbc, &base_sk))
+            .collect()
+    }
+
+    /// get a Dbc by KeyImage
+    pub fn get(&self, key_image: &KeyImage) -> Option<&WalletDbc> {
+        self.dbcs.get(key_image)
+    }
+
+    /// iterate over all Dbcs in the wallet, regardless of status.
+    pub fn iter(&self) -> impl Iterator<Item = (&KeyImage, &WalletDbc)> {
+        self.dbcs.iter()
+    }
+
+    /// iterate over Dbcs with the given status.
+    pub fn iter_by_status(
+        &self,
+        status: DbcStatus,
+    ) -> impl Iterator<Item = (&KeyImage, &WalletDbc)> {
+        self.dbcs.iter().filter(move |(_, w)| w.status == status)
+    }
+
+    /// returns (Dbc, base SecretKey) for each spendable (confirmed) Dbc.
+    pub fn spendable_dbcs(&self) -> Vec<(Dbc, SecretKey)> {
+        self.iter_by_status(DbcStatus::Confirmed)
+            .map(|(_, w)| (w.dbc.clone(), w.base_sk().clone()))
+            .collect()
+    }
+
+    /// returns number of Dbcs in the wallet, regardless of status.
+    pub fn len(&self) -> usize {
+        self.dbcs.len()
+    }
+
+    /// returns true if the wallet holds no Dbcs.
+    pub fn is_empty(&self) -> bool {
+        self.dbcs.is_empty()
+    }
+
+    /// sum of amounts of all Dbcs with the given status
+    pub fn balance(&self, status: DbcStatus) -> Result<Token> {
+        self.iter_by_status(status)
+            .try_fold(Token::zero(), |sum, (_, w)| {
+                sum.checked_add(w.amount).ok_or(Error::ExcessiveTokenValue)
+            })
+    }
+
+    /// sum of amounts of all spendable Dbcs
+    pub fn balance_confirmed(&self) -> Result<Token> {
+        self.balance(DbcStatus::Confirmed)
+    }
+
+    /// sum of amounts of all Dbcs that are inputs to a pending transaction
+    pub fn balance_pending(&self) -> Result<Token> {
+        self.balance(DbcStatus::Pending)
+    }
+
+    /// sum of amounts of all spent Dbcs
+    pub fn balance_spent(&self) -> Result<Token> {
+        self.balance(DbcStatus::Spent)
+    }
+
+    /// mark Dbcs as pending, ie inputs to a transaction that has not yet been
+    /// logged as spent.
+    pub fn mark_pending(&mut self, key_images: impl IntoIterator<Item = KeyImage>) -> Result<()> {
+        self.set_status(key_images, DbcStatus::Pending)
+    }
+
+    /// mark Dbcs as spent.  This should be called once the SpentBook has
+    /// logged the Dbcs as spent.
+    pub fn confirm_spent(&mut self, key_images: impl IntoIterator<Item = KeyImage>) -> Result<()> {
+        self.set_status(key_images, DbcStatus::Spent)
+    }
+
+    /// return pending Dbcs to confirmed (spendable) status.  This should be
+    /// called if a pending transaction is abandoned before any input was
+    /// logged as spent.
+    pub fn cancel_pending(&mut self, key_images: impl IntoIterator<Item = KeyImage>) -> Result<()> {
+        self.set_status(key_images, DbcStatus::Confirmed)
+    }
+
+    /// DbcBuilders whose inputs were logged as spent by the wallet, but
+    /// whose output Dbcs could not then be built.
+    pub fn unbuilt(&self) -> &[DbcBuilder] {
+        &self.unbuilt
+    }
+
+    /// remove and return the unbuilt DbcBuilders, so that building their
+    /// output Dbcs may be retried.  See ::unbuilt().
+    pub fn take_unbuilt(&mut self) -> Vec<DbcBuilder> {
+        std::mem::take(&mut self.unbuilt)
+    }
+
+    /// remove a Dbc from the wallet, returning it (if present)
+    pub fn remove(&mut self, key_image: &KeyImage) -> Option<WalletDbc> {
+        self.dbcs.remove(key_image)
+    }
+
+    /// Create a TransactionBuilder that pays `amount` to `recipient`.
+    ///
+    /// Inputs are chosen from confirmed Dbcs, largest amount first, until
+    /// the amount is covered.  Any remainder is paid to `change`.
+    ///
+    /// The chosen inputs are marked as pending.  The caller is expected to
+    /// add decoys (if desired), build and log the transaction, and then
+    /// call Wallet::confirm_spent() or Wallet::cancel_pending() with the
+    /// key images of the transaction inputs.
+    pub fn pay(
+        &mut self,
+        amount: Token,
+        recipient: OwnerOnce,
+        change: OwnerOnce,
+    ) -> Result<TransactionBuilder> {
+        self.pay_with_selector(amount, recipient, change, &LargestFirst)
+    }
+
+    /// As Wallet::pay(), but inputs are chosen by the provided CoinSelector.
+    pub fn pay_with_selector(
+        &mut self,
+        amount: Token,
+        recipient: OwnerOnce,
+        change: OwnerOnce,
+        selector: &impl CoinSelector,
+    ) -> Result<TransactionBuilder> {
+        let selection = selector.select(self.spendable_dbcs(), amount)?;
+
+        let mut selected: Vec<KeyImage> = vec![];
+        let mut tx_builder = TransactionBuilder::default().set_change_owner(change);
+        for (dbc, base_sk) in selection.inputs.iter() {
+            selected.push(dbc.key_image(base_sk)?);
+            tx_builder = tx_builder.add_input_dbc(dbc, base_sk)?;
+        }
+
+        tx_builder = tx_builder.add_output_by_amount(amount, recipient);
+
+        self.mark_pending(selected)?;
+        Ok(tx_builder)
+    }
+
+    // builds a transaction whose inputs are the wallet Dbcs of key_images,
+    // logs the inputs as spent with client, and returns the output Dbcs.
+    //
+    // If this fails before any input is logged, the inputs are returned to
+    // confirmed.  If logging fails, they are left pending.  If the output
+    // Dbcs cannot be built once all inputs are logged, the DbcBuilder is
+    // kept in ::unbuilt(), for the caller to retry.
+    pub(crate) fn send<C: SpentBookClient, K: SpentProofKeyVerifier>(
+        &mut self,
+        tx_builder: TransactionBuilder,
+        key_images: Vec<KeyImage>,
+        client: &mut C,
+        verifier: &K,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Vec<(Dbc, OwnerOnce, AmountSecrets)>> {
+        self.mark_pending(key_images.clone())?;
+
+        let mut dbc_builder = match tx_builder.build_with_decoy_provider(&*client, &mut rng) {
+            Ok(dbc_builder) => dbc_builder,
+            Err(e) => {
+                self.cancel_pending(key_images)?;
+                return Err(e);
+            }
+        };
+        for (key_image, tx) in dbc_builder.inputs() {
+            let shares = client.log_spent_shares(key_image, tx.clone(), &dbc_builder.fee)?;
+            dbc_builder = dbc_builder
+                .add_spent_proof_shares(shares)
+                .add_spent_transaction(tx);
+        }
+        self.confirm_spent(key_images)?;
+
+        match dbc_builder.clone().build_with_rng(verifier, rng) {
+            Ok(outputs) => Ok(outputs),
+            Err(e) => {
+                self.unbuilt.push(dbc_builder);
+                Err(e)
+            }
+        }
+    }
+
+    fn set_status(
+        &mut self,
+        key_images: impl IntoIterator<Item = KeyImage>,
+        status: DbcStatus,
+    ) -> Result<()> {
+        let key_images: Vec<KeyImage> = key_images.into_iter().collect();
+
+        // check all are present before modifying any.
+        if let Some(k) = key_images.iter().find(|k| !self.dbcs.contains_key(k)) {
+            return Err(Error::DbcNotFoundInWallet(*k));
+        }
+        for k in key_images.iter() {
+            if let Some(w) = self.dbcs.get_mut(k) {
+                w.status = status;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock,
+        tests::{STD_DECOYS_PER_INPUT, STD_DECOYS_TO_FETCH},
+        Owner,
+    };
+
+    #[test]
+    fn wallet_tracks_balances_through_a_payment() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+
+        let (mut spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+
+        let mut wallet = Wallet::default();
+        wallet.add_dbc_bearer(genesis_dbc)?;
+        assert_eq!(
+            wallet.balance_confirmed()?,
+            Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT)
+        );
+
+        let recipient_owner = Owner::from_random_secret_key(&mut rng);
+        let change_owner = Owner::from_random_secret_key(&mut rng);
+        let payment = Token::from_nano(100);
+
+        let mut dbc_builder = wallet
+            .pay(
+                payment,
+                OwnerOnce::from_owner_base(recipient_owner.clone(), &mut rng),
+                OwnerOnce::from_owner_base(change_owner.clone(), &mut rng),
+            )?
+            .set_decoys_per_input(STD_DECOYS_PER_INPUT)
+            .set_require_all_decoys(false)
+            .add_decoy_inputs(spentbook_node.random_decoys(STD_DECOYS_TO_FETCH, &mut rng))
+            .build(&mut rng)?;
+
+        assert_eq!(wallet.balance_confirmed()?, Token::zero());
+        assert_eq!(
+            wallet.balance_pending()?,
+            Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT)
+        );
+
+        let mut spent = vec![];
+        for (key_image, tx) in dbc_builder.inputs() {
+            dbc_builder = dbc_builder
+                .add_spent_proof_share(spentbook_node.log_spent(key_image, tx.clone())?)
+                .add_spent_transaction(tx);
+            spent.push(key_image);
+        }
+        wallet.confirm_spent(spent)?;
+
+        for (dbc, owner_once, _amount_secrets) in dbc_builder.build(&spentbook_node.key_manager)? {
+            if owner_once.owner_base == change_owner {
+                wallet.add_dbc(dbc, &change_owner.secret_key()?)?;
+            }
+        }
+
+        assert_eq!(wallet.len(), 2);
+        assert_eq!(
+            wallet.balance_confirmed()?,
+            Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT - payment.as_nano())
+        );
+        assert_eq!(wallet.balance_pending()?, Token::zero());
+        assert_eq!(
+            wallet.balance_spent()?,
+            Token::from_nano(mock::GenesisMaterial::GENESIS_AMOUNT)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn send_restores_inputs_or_keeps_the_dbc_builder_on_failure() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (mut spentbook_node, genesis_dbc, _genesis, _amount_secrets) =
+            mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+        let verifier = spentbook_node.key_manager.clone();
+
+        let mut wallet = Wallet::default();
+        let key_image = wallet.add_dbc_bearer(genesis_dbc)?;
+        let owner = Owner::from_random_secret_key(&mut rng);
+
+        // the spentbook has no decoys for the input, so nothing is logged
+        // and the input is spendable again.
+        let tx_builder = wallet.pay(
+            Token::from_nano(100),
+            OwnerOnce::from_owner_base(owner.clone(), &mut rng),
+            OwnerOnce::from_owner_base(owner.clone(), &mut rng),
+        )?;
+        let result = wallet.send(
+            tx_builder,
+            vec![key_image],
+            &mut spentbook_node,
+            &verifier,
+            &mut rng,
+        );
+        assert_eq!(result.err(), Some(Error::InsufficientDecoys));
+        assert_eq!(
+            wallet.get(&key_image).map(WalletDbc::status),
+            Some(DbcStatus::Confirmed)
+        );
+
+        // a verifier that does not know the spentbook fails once the input
+        // is logged, and the DbcBuilder is kept for a retry.
+        let (other_spentbook_node, ..) = mock::GenesisBuilder::init_genesis_single(&mut rng)?;
+        let tx_builder = wallet
+            .pay(
+                Token::from_nano(100),
+                OwnerOnce::from_owner_base(owner.clone(), &mut rng),
+                OwnerOnce::from_owner_base(owner, &mut rng),
+            )?
+            .set_require_all_decoys(false);
+        let result = wallet.send(
+            tx_builder,
+            vec![key_image],
+            &mut spentbook_node,
+            &other_spentbook_node.key_manager,
+            &mut rng,
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            wallet.get(&key_image).map(WalletDbc::status),
+            Some(DbcStatus::Spent)
+        );
+        assert_eq!(wallet.unbuilt().len(), 1);
+
+        let unbuilt = wallet.take_unbuilt();
+        assert!(wallet.unbuilt().is_empty());
+        assert_eq!(unbuilt.len(), 1);
+        for dbc_builder in unbuilt {
+            assert_eq!(dbc_builder.build(&verifier)?.len(), 2);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn pay_fails_without_sufficient_balance() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let (_spentbook_node, _genesis_dbc, starting_dbc, _change_dbc) =
+            crate::dbc::tests::generate_bearer_dbc_of_value(100, &mut rng)?;
+
+        let mut wallet = Wallet::default();
+        let key_image = wallet.add_dbc_bearer(starting_dbc)?;
+
+        let owner = Owner::from_random_secret_key(&mut rng);
+        let result = wallet.pay(
+            Token::from_nano(101),
+            OwnerOnce::from_owner_base(owner.clone(), &mut rng),
+            OwnerOnce::from_owner_base(owner, &mut rng),
+        );
+
+        assert_eq!(
+            result.err(),
+            Some(Error::InsufficientBalance {
+                available: Token::from_nano(100),
+                requested: Token::from_nano(101),
+            })
+        );
+
+        // inputs must not be left pending after a failure.
+        assert_eq!(
+            wallet.get(&key_image).map(WalletDbc::status),
+            Some(DbcStatus::Confirmed)
+        );
+        Ok(())
+    }
+}
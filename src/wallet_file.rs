@@ -0,0 +1,371 @@
+// Copyright 2022 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rand::{CryptoRng, RngCore},
+    Error, OwnerOnce, Result, Wallet,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use blsttc::{serde_impl::SerdeSecret, SecretKey};
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{Deserialize, Serialize};
+use std::{convert::TryInto, fs, path::Path};
+
+const MAGIC: &[u8; 8] = b"SNDBCWAL";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+// magic, version, kdf params (3 x u32), salt, nonce
+const HEADER_LEN: usize = MAGIC.len() + 2 + 12 + SALT_LEN + NONCE_LEN;
+
+// the largest kdf parameters accepted, so that a crafted file header can
+// not make loading exhaust memory or time.
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024; // 1 GiB
+const MAX_KDF_ITERATIONS: u32 = 16;
+const MAX_KDF_PARALLELISM: u32 = 16;
+
+/// Parameters of the Argon2id key derivation function used to derive
+/// the encryption key of a WalletFile from a password.
+///
+/// The parameters are stored in the file header, so a file can always be
+/// decrypted with the parameters it was saved with.  They are limited to
+/// at most 1 GiB of memory, 16 iterations and 16 lanes of parallelism.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalletFileKdf {
+    /// memory cost, in KiB
+    pub memory_kib: u32,
+    /// number of passes over the memory
+    pub iterations: u32,
+    /// degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for WalletFileKdf {
+    // the OWASP recommended minimum for Argon2id.
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl WalletFileKdf {
+    fn is_within_limits(&self) -> bool {
+        self.memory_kib <= MAX_KDF_MEMORY_KIB
+            && self.iterations <= MAX_KDF_ITERATIONS
+            && self.parallelism <= MAX_KDF_PARALLELISM
+    }
+
+    fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<[u8; KEY_LEN]> {
+        if !self.is_within_limits() {
+            return Err(Error::WalletFileKdf(format!(
+                "parameters exceed the maximum: {:?}",
+                self
+            )));
+        }
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|e| Error::WalletFileKdf(e.to_string()))?;
+
+        let mut key = [0u8; KEY_LEN];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password, salt, &mut key)
+            .map_err(|e| Error::WalletFileKdf(e.to_string()))?;
+        Ok(key)
+    }
+}
+
+/// The contents of an encrypted wallet file: a Wallet (with its Dbcs and
+/// their base SecretKeys), any OwnerOnce records the caller wishes to keep,
+/// eg for payments sent or change expected, and any other SecretKeys.
+///
+/// The file format is:
+///
+///   magic (8 bytes) | version (u16) | kdf memory, iterations, parallelism (3 x u32)
+///   | salt (16 bytes) | nonce (12 bytes) | ciphertext
+///
+/// with integers in big endian.  The ciphertext is the bincode serialized
+/// WalletFile, encrypted with ChaCha20Poly1305 under a key derived from
+/// the password and salt with Argon2id.  The header is authenticated as
+/// associated data, so it can not be altered undetected.
+///
+/// A fresh salt and nonce are generated each time the file is saved.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletFile {
+    pub wallet: Wallet,
+    pub owner_onces: Vec<OwnerOnce>,
+    pub secret_keys: Vec<SerdeSecret<SecretKey>>,
+}
+
+impl WalletFile {
+    /// The current file format version.
+    ///
+    /// This is the only version, so files with any other version are
+    /// rejected with Error::WalletFileUnsupportedVersion.
+    pub const VERSION: u16 = 1;
+
+    /// create a WalletFile holding the given Wallet
+    pub fn new(wallet: Wallet) -> Self {
+        Self {
+            wallet,
+            ..Default::default()
+        }
+    }
+
+    /// returns the format version of an encrypted wallet file, without decrypting it.
+    pub fn version(bytes: &[u8]) -> Result<u16> {
+        if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(Error::WalletFileInvalid);
+        }
+        Ok(u16::from_be_bytes([
+            bytes[MAGIC.len()],
+            bytes[MAGIC.len() + 1],
+        ]))
+    }
+
+    /// encrypt to bytes with the default key derivation parameters
+    pub fn to_encrypted_bytes(
+        &self,
+        password: &[u8],
+        rng: impl RngCore + CryptoRng,
+    ) -> Result<Vec<u8>> {
+        self.to_encrypted_bytes_with_kdf(password, &WalletFileKdf::default(), rng)
+    }
+
+    /// encrypt to bytes with the given key derivation parameters
+    pub fn to_encrypted_bytes_with_kdf(
+        &self,
+        password: &[u8],
+        kdf: &WalletFileKdf,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<Vec<u8>> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend(MAGIC);
+        header.extend(Self::VERSION.to_be_bytes());
+        header.extend(kdf.memory_kib.to_be_bytes());
+        header.extend(kdf.iterations.to_be_bytes());
+        header.extend(kdf.parallelism.to_be_bytes());
+        header.extend(salt);
+        header.extend(nonce);
+
+        let plaintext =
+            bincode::serialize(self).map_err(|e| Error::WalletFileSerialization(e.to_string()))?;
+        let key = kdf.derive_key(password, &salt)?;
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| Error::WalletFileSerialization("encryption failed".to_string()))?;
+
+        Ok([header, ciphertext].concat())
+    }
+
+    /// decrypt from bytes.
+    ///
+    /// Returns Error::WalletFileDecryptionFailed if the password is wrong
+    /// or the file has been altered, and Error::WalletFileInvalid if the
+    /// header has kdf parameters beyond the maximum.
+    pub fn from_encrypted_bytes(bytes: &[u8], password: &[u8]) -> Result<Self> {
+        match Self::version(bytes)? {
+            1 => Self::from_encrypted_bytes_v1(bytes, password),
+            version => Err(Error::WalletFileUnsupportedVersion(version)),
+        }
+    }
+
+    /// encrypt with the default key derivation parameters and write to a file.
+    ///
+    /// The file is written to a temporary file first and then renamed,
+    /// so an existing wallet file is not lost if writing fails.
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+        password: &[u8],
+        rng: impl RngCore + CryptoRng,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = self.to_encrypted_bytes(password, rng)?;
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bytes).map_err(|e| Error::WalletFileIo(e.to_string()))?;
+        fs::rename(&tmp_path, path).map_err(|e| Error::WalletFileIo(e.to_string()))
+    }
+
+    /// read from a file and decrypt
+    pub fn load(path: impl AsRef<Path>, password: &[u8]) -> Result<Self> {
+        let bytes = fs::read(path).map_err(|e| Error::WalletFileIo(e.to_string()))?;
+        Self::from_encrypted_bytes(&bytes, password)
+    }
+
+    fn from_encrypted_bytes_v1(bytes: &[u8], password: &[u8]) -> Result<Self> {
+        let (header, ciphertext) = bytes.split_at(HEADER_LEN);
+
+        let u32_at = |i: usize| u32::from_be_bytes(header[i..i + 4].try_into().unwrap());
+        let params_start = MAGIC.len() + 2;
+        let kdf = WalletFileKdf {
+            memory_kib: u32_at(params_start),
+            iterations: u32_at(params_start + 4),
+            parallelism: u32_at(params_start + 8),
+        };
+        if !kdf.is_within_limits() {
+            return Err(Error::WalletFileInvalid);
+        }
+        let salt = &header[params_start + 12..params_start + 12 + SALT_LEN];
+        let nonce = &header[HEADER_LEN - NONCE_LEN..];
+
+        let key = kdf.derive_key(password, salt)?;
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| Error::WalletFileDecryptionFailed)?;
+
+        bincode::deserialize(&plaintext).map_err(|e| Error::WalletFileSerialization(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rand::{distributions::Standard, Rng},
+        Owner, Token,
+    };
+
+    // cheap parameters, to keep tests fast.
+    const TEST_KDF: WalletFileKdf = WalletFileKdf {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn wallet_file(rng: &mut (impl RngCore + CryptoRng)) -> Result<WalletFile> {
+        let (_spentbook, _genesis, dbc, _change) =
+            crate::dbc::tests::generate_bearer_dbc_of_value(100, rng)?;
+
+        let mut wallet = Wallet::default();
+        wallet.add_dbc_bearer(dbc)?;
+
+        let sk: SecretKey = rng.sample(Standard);
+        Ok(WalletFile {
+            wallet,
+            owner_onces: vec![OwnerOnce::from_owner_base(
+                Owner::from(sk.public_key()),
+                rng,
+            )],
+            secret_keys: vec![SerdeSecret(sk)],
+        })
+    }
+
+    #[test]
+    fn wallet_file_round_trip() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let wallet_file = wallet_file(&mut rng)?;
+
+        let bytes = wallet_file.to_encrypted_bytes_with_kdf(b"password", &TEST_KDF, &mut rng)?;
+        assert_eq!(WalletFile::version(&bytes)?, WalletFile::VERSION);
+
+        let restored = WalletFile::from_encrypted_bytes(&bytes, b"password")?;
+        assert_eq!(restored.wallet.balance_confirmed()?, Token::from_nano(100));
+        assert_eq!(restored.owner_onces, wallet_file.owner_onces);
+        assert_eq!(restored.secret_keys, wallet_file.secret_keys);
+
+        // the file is saved with the default kdf, and a fresh salt and nonce.
+        let mut suffix = [0u8; 8];
+        crate::rng::thread_rng().fill_bytes(&mut suffix);
+        let path = std::env::temp_dir().join(format!(
+            "sn_dbc_wallet_file_{}_{}.wallet",
+            std::process::id(),
+            hex::encode(suffix)
+        ));
+        wallet_file.save(&path, b"password", &mut rng)?;
+        let saved = fs::read(&path).map_err(|e| Error::WalletFileIo(e.to_string()))?;
+        assert_ne!(saved[..HEADER_LEN], bytes[..HEADER_LEN]);
+
+        let loaded = WalletFile::load(&path, b"password")?;
+        fs::remove_file(&path).map_err(|e| Error::WalletFileIo(e.to_string()))?;
+        assert_eq!(loaded.secret_keys, wallet_file.secret_keys);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wallet_file_rejects_wrong_password_and_tampering() -> Result<()> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let bytes =
+            wallet_file(&mut rng)?.to_encrypted_bytes_with_kdf(b"password", &TEST_KDF, &mut rng)?;
+
+        assert_eq!(
+            WalletFile::from_encrypted_bytes(&bytes, b"passw0rd").err(),
+            Some(Error::WalletFileDecryptionFailed)
+        );
+
+        // altering the header (here, the kdf iterations) is detected.
+        let mut tampered = bytes.clone();
+        tampered[MAGIC.len() + 2 + 7] = 2;
+        assert_eq!(
+            WalletFile::from_encrypted_bytes(&tampered, b"password").err(),
+            Some(Error::WalletFileDecryptionFailed)
+        );
+
+        // kdf parameters beyond the maximum are rejected before deriving a key.
+        let mut costly = bytes.clone();
+        costly[MAGIC.len() + 2..MAGIC.len() + 6].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(
+            WalletFile::from_encrypted_bytes(&costly, b"password").err(),
+            Some(Error::WalletFileInvalid)
+        );
+        let too_many_lanes = WalletFileKdf {
+            parallelism: MAX_KDF_PARALLELISM + 1,
+            ..TEST_KDF
+        };
+        assert!(matches!(
+            WalletFile::default().to_encrypted_bytes_with_kdf(
+                b"password",
+                &too_many_lanes,
+                &mut rng
+            ),
+            Err(Error::WalletFileKdf(_))
+        ));
+
+        let mut future = bytes.clone();
+        future[MAGIC.len() + 1] = 2;
+        assert_eq!(
+            WalletFile::from_encrypted_bytes(&future, b"password").err(),
+            Some(Error::WalletFileUnsupportedVersion(2))
+        );
+
+        assert_eq!(
+            WalletFile::from_encrypted_bytes(&bytes[..HEADER_LEN - 1], b"password").err(),
+            Some(Error::WalletFileInvalid)
+        );
+        Ok(())
+    }
+}